@@ -51,6 +51,17 @@ cache_ref!(ZipWriter_ptr: JFieldID);
 cache_ref!(ZipEntry: GlobalRef);
 cache_ref!(ZipEntry_ctor: JMethodID);
 cache_ref!(ZipEntry_ptr: JFieldID);
+cache_ref!(ZipEntryMetadata: GlobalRef);
+cache_ref!(ZipEntryMetadata_ctor: JMethodID);
+cache_ref!(ZipVerifyProblem: GlobalRef);
+cache_ref!(ZipVerifyProblem_ctor: JMethodID);
+cache_ref!(ZipNameIssue: GlobalRef);
+cache_ref!(ZipNameIssue_ctor: JMethodID);
+cache_ref!(ZipException: GlobalRef);
+cache_ref!(ZipFormatException: GlobalRef);
+cache_ref!(ZipPasswordException: GlobalRef);
+cache_ref!(ZipEntryNotFoundException: GlobalRef);
+cache_ref!(ZipLimitException: GlobalRef);
 
 pub(super) fn init(env: &mut JNIEnv) -> jni::errors::Result<()> {
     // Java Stdlib
@@ -78,9 +89,39 @@ pub(super) fn init(env: &mut JNIEnv) -> jni::errors::Result<()> {
         .find_class("com/github/diamondminer88/zip/ZipEntry")
         .and_then(|cls| env.new_global_ref(cls))?);
     init_ZipEntry_ctor(env
-        .get_method_id(&ZipEntry(), "<init>", "()V")?);
+        .get_method_id(&ZipEntry(), "<init>", "(Lcom/github/diamondminer88/zip/ZipReader;Z)V")?);
     init_ZipEntry_ptr(env
         .get_field_id(&ZipEntry(), "ptr", "J")?);
+    init_ZipEntryMetadata(env
+        .find_class("com/github/diamondminer88/zip/ZipEntryMetadata")
+        .and_then(|cls| env.new_global_ref(cls))?);
+    init_ZipEntryMetadata_ctor(env
+        .get_method_id(&ZipEntryMetadata(), "<init>", "(ILjava/lang/String;Ljava/lang/String;JZZIIJJI)V")?);
+    init_ZipVerifyProblem(env
+        .find_class("com/github/diamondminer88/zip/ZipVerifyProblem")
+        .and_then(|cls| env.new_global_ref(cls))?);
+    init_ZipVerifyProblem_ctor(env
+        .get_method_id(&ZipVerifyProblem(), "<init>", "(ILjava/lang/String;ILjava/lang/String;)V")?);
+    init_ZipNameIssue(env
+        .find_class("com/github/diamondminer88/zip/ZipNameIssue")
+        .and_then(|cls| env.new_global_ref(cls))?);
+    init_ZipNameIssue_ctor(env
+        .get_method_id(&ZipNameIssue(), "<init>", "(ILjava/lang/String;ILjava/lang/String;)V")?);
+    init_ZipException(env
+        .find_class("com/github/diamondminer88/zip/ZipException")
+        .and_then(|cls| env.new_global_ref(cls))?);
+    init_ZipFormatException(env
+        .find_class("com/github/diamondminer88/zip/ZipFormatException")
+        .and_then(|cls| env.new_global_ref(cls))?);
+    init_ZipPasswordException(env
+        .find_class("com/github/diamondminer88/zip/ZipPasswordException")
+        .and_then(|cls| env.new_global_ref(cls))?);
+    init_ZipEntryNotFoundException(env
+        .find_class("com/github/diamondminer88/zip/ZipEntryNotFoundException")
+        .and_then(|cls| env.new_global_ref(cls))?);
+    init_ZipLimitException(env
+        .find_class("com/github/diamondminer88/zip/ZipLimitException")
+        .and_then(|cls| env.new_global_ref(cls))?);
 
     Ok(())
 }
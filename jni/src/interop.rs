@@ -14,6 +14,9 @@ use jni::{
     sys::jlong,
     JNIEnv,
 };
+use zip::result::ZipError;
+
+use crate::cache;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -188,3 +191,32 @@ where
 
     Ok(Some(mbox.mutex.into_inner().unwrap()))
 }
+
+/// Throw the `ZipException` matching a [`ZipError`], with its message prefixed by `context`.
+///
+/// | Error                          | Exception                   |
+/// | ------------------------------ | --------------------------- |
+/// | Corrupt or unsupported archive | `ZipFormatException`        |
+/// | Password required or invalid   | `ZipPasswordException`      |
+/// | [`ZipError::FileNotFound`]     | `ZipEntryNotFoundException` |
+/// | [`ZipError::LimitExceeded`]    | `ZipLimitException`         |
+/// | [`ZipError::Io`]               | `ZipException`              |
+pub fn throw_zip_error<E: Into<ZipError>>(env: &mut JNIEnv, context: &str, err: E) {
+    let err = err.into();
+    let class = match &err {
+        ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) | ZipError::InvalidPassword => {
+            cache::ZipPasswordException()
+        }
+        ZipError::InvalidArchive(_)
+        | ZipError::InvalidSignature { .. }
+        | ZipError::Crc32Mismatch { .. }
+        | ZipError::UnsupportedArchive(_)
+        | ZipError::UnsupportedCompressionMethod { .. } => cache::ZipFormatException(),
+        ZipError::FileNotFound => cache::ZipEntryNotFoundException(),
+        ZipError::LimitExceeded(_) => cache::ZipLimitException(),
+        ZipError::Io(_) => cache::ZipException(),
+    };
+
+    env.throw_new(&class, format!("{}: {}", context, err))
+        .unwrap();
+}
@@ -1,10 +1,10 @@
 use catch_panic::catch_panic;
-use std::io::Read;
+use std::io::{self, Read};
 
 use jni::signature::ReturnType;
 use jni::sys::jvalue;
 use jni::{
-    objects::{JObject, JValue},
+    objects::{JByteArray, JByteBuffer, JObject, JValue},
     signature::Primitive::Long,
     sys::{jboolean, jbyteArray, jint, jlong, jstring},
     JNIEnv,
@@ -12,36 +12,44 @@ use jni::{
 use jni_fn::jni_fn;
 
 use zip::read::ZipFile;
-use zip::CompressionMethod;
+use zip::{CompressionMethod, DateTime};
 
 use crate::{cache, interop};
 
 /// Obtains an exclusive reference to the rust zip entry from a pointer in a JVM class.
 macro_rules! obtain_entry {
-    (&mut $env:ident, &$class:ident) => {{
+    (&mut $env:ident, &$class:ident, $ret_value:expr) => {{
         let entry = crate::interop::get_field::<_, _, ZipFile<'static>>(
             &mut $env,
             &$class,
             crate::cache::ZipEntry_ptr(),
         );
 
-        entry
-            .unwrap()
-            .expect("using a closed entry, after JVM finalizer called!")
+        match entry.unwrap() {
+            Some(e) => e,
+            None => {
+                $env.throw((
+                    "java/lang/IllegalStateException",
+                    "Cannot use a closed entry!",
+                ))
+                .unwrap();
+                return $ret_value;
+            }
+        }
     }};
 }
 
 #[catch_panic(default = "0")]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn getIndex(mut env: JNIEnv, class: JObject) -> jint {
-    let entry = obtain_entry!(&mut env, &class);
+    let entry = obtain_entry!(&mut env, &class, 0);
     entry.index() as jint
 }
 
 #[catch_panic(default = "JObject::null().into_raw()")]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn getName(mut env: JNIEnv, class: JObject) -> jstring {
-    let entry = obtain_entry!(&mut env, &class);
+    let entry = obtain_entry!(&mut env, &class, JObject::null().into_raw());
 
     env.new_string(entry.name()).unwrap().into_raw()
 }
@@ -49,34 +57,39 @@ pub fn getName(mut env: JNIEnv, class: JObject) -> jstring {
 #[catch_panic(default = "JObject::null().into_raw()")]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn getComment(mut env: JNIEnv, class: JObject) -> jstring {
-    let entry = obtain_entry!(&mut env, &class);
+    let entry = obtain_entry!(&mut env, &class, JObject::null().into_raw());
 
     env.new_string(entry.comment()).unwrap().into_raw()
 }
 
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
-pub unsafe fn getLastModified(mut env: JNIEnv, class: JObject) -> jlong {
-    let entry = obtain_entry!(&mut env, &class);
-    let modified = entry.last_modified();
+pub fn getLastModified(mut env: JNIEnv, class: JObject) -> jlong {
+    let entry = obtain_entry!(&mut env, &class, 0);
+    unix_time(&mut env, entry.last_modified())
+}
+
+/// Converts the modification time of an entry to a UNIX timestamp in milliseconds
+pub fn unix_time(env: &mut JNIEnv, modified: DateTime) -> jlong {
     let args: Vec<jvalue> = vec![
-        JValue::from(modified.year() - 1900).as_jni(),
-        JValue::from(modified.month() - 1).as_jni(),
-        JValue::from(modified.day()).as_jni(),
-        JValue::from(modified.hour()).as_jni(),
-        JValue::from(modified.minute() - 1).as_jni(),
-        JValue::from(modified.second() - 1).as_jni(),
+        JValue::Int(modified.year() as jint - 1900).as_jni(),
+        JValue::Int(modified.month() as jint - 1).as_jni(),
+        JValue::Int(modified.day() as jint).as_jni(),
+        JValue::Int(modified.hour() as jint).as_jni(),
+        JValue::Int(modified.minute() as jint).as_jni(),
+        JValue::Int(modified.second() as jint).as_jni(),
     ];
 
     // Yes I could do this natively, however I'm not adding chrono just for this
-    let unix_time = env
-        .call_static_method_unchecked(
+    let unix_time = unsafe {
+        env.call_static_method_unchecked(
             &cache::Date(),
             cache::Date_UTC(),
             ReturnType::Primitive(Long),
             &*args,
         )
-        .unwrap();
+    }
+    .unwrap();
 
     unix_time.j().unwrap()
 }
@@ -84,28 +97,35 @@ pub unsafe fn getLastModified(mut env: JNIEnv, class: JObject) -> jlong {
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn isDir(mut env: JNIEnv, class: JObject) -> jboolean {
-    let entry = obtain_entry!(&mut env, &class);
+    let entry = obtain_entry!(&mut env, &class, 0);
     entry.is_dir().into()
 }
 
+#[catch_panic]
+#[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
+pub fn isEncrypted(mut env: JNIEnv, class: JObject) -> jboolean {
+    let entry = obtain_entry!(&mut env, &class, 0);
+    entry.metadata().encrypted().into()
+}
+
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn getMode(mut env: JNIEnv, class: JObject) -> jint {
-    let entry = obtain_entry!(&mut env, &class);
+    let entry = obtain_entry!(&mut env, &class, 0);
     entry.unix_mode().unwrap_or(0) as i32
 }
 
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn getCRC32(mut env: JNIEnv, class: JObject) -> jint {
-    let entry = obtain_entry!(&mut env, &class);
+    let entry = obtain_entry!(&mut env, &class, 0);
     entry.crc32() as i32
 }
 
 #[catch_panic(default = "JObject::null().into_raw()")]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn getExtraData(mut env: JNIEnv, class: JObject) -> jbyteArray {
-    let entry = obtain_entry!(&mut env, &class);
+    let entry = obtain_entry!(&mut env, &class, JObject::null().into_raw());
     env.byte_array_from_slice(entry.extra_data())
         .unwrap()
         .into_raw()
@@ -114,23 +134,28 @@ pub fn getExtraData(mut env: JNIEnv, class: JObject) -> jbyteArray {
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn getSize(mut env: JNIEnv, class: JObject) -> jlong {
-    let entry = obtain_entry!(&mut env, &class);
+    let entry = obtain_entry!(&mut env, &class, 0);
     entry.size() as i64
 }
 
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn getCompressedSize(mut env: JNIEnv, class: JObject) -> jlong {
-    let entry = obtain_entry!(&mut env, &class);
+    let entry = obtain_entry!(&mut env, &class, 0);
     entry.compressed_size() as i64
 }
 
-#[allow(deprecated)]
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn _getCompression(mut env: JNIEnv, class: JObject) -> jlong {
-    let entry = obtain_entry!(&mut env, &class);
-    match entry.compression() {
+    let entry = obtain_entry!(&mut env, &class, 0);
+    compression_internal(entry.compression()) as jlong
+}
+
+/// Maps compression methods to the values of `ZipCompression`
+#[allow(deprecated)]
+pub fn compression_internal(compression: CompressionMethod) -> jint {
+    match compression {
         CompressionMethod::Unsupported(_) => -1,
         CompressionMethod::Stored => 0,
         CompressionMethod::Deflated => 1,
@@ -143,26 +168,132 @@ pub fn _getCompression(mut env: JNIEnv, class: JObject) -> jlong {
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn getDataOffset(mut env: JNIEnv, class: JObject) -> jlong {
-    let entry = obtain_entry!(&mut env, &class);
+    let entry = obtain_entry!(&mut env, &class, 0);
     entry.data_start() as i64
 }
 
 #[catch_panic(default = "JObject::null().into_raw()")]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn read(mut env: JNIEnv, class: JObject) -> jbyteArray {
-    let mut entry = obtain_entry!(&mut env, &class);
+    let mut entry = obtain_entry!(&mut env, &class, JObject::null().into_raw());
 
     if entry.is_dir() {
-        env.throw("Cannot read data from a dir entry!").unwrap();
+        env.throw((
+            "java/lang/IllegalStateException",
+            "Cannot read data from a dir entry!",
+        ))
+        .unwrap();
         return JObject::null().into_raw();
     }
 
     let mut data = Vec::new();
-    entry.read_to_end(&mut data).unwrap();
+    if let Err(e) = entry.read_to_end(&mut data) {
+        interop::throw_zip_error(&mut env, "Failed to read zip entry", e);
+        return JObject::null().into_raw();
+    }
 
     env.byte_array_from_slice(&data).unwrap().into_raw()
 }
 
+#[catch_panic(default = "-1")]
+#[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
+pub fn read0(
+    mut env: JNIEnv,
+    class: JObject,
+    buffer: JByteArray,
+    offset: jint,
+    length: jint,
+) -> jint {
+    let mut entry = obtain_entry!(&mut env, &class, -1);
+
+    // Decompress in chunks, so that large reads don't need as much native memory
+    let mut data = vec![0u8; (length as usize).min(1 << 16)];
+    let count = match entry.read(&mut data) {
+        Ok(0) if length > 0 => return -1,
+        Ok(count) => count,
+        Err(e) => {
+            interop::throw_zip_error(&mut env, "Failed to read zip entry", e);
+            return -1;
+        }
+    };
+
+    // SAFETY: u8 and i8 have the same layout
+    let data = unsafe { &*(&data[..count] as *const [u8] as *const [i8]) };
+    env.set_byte_array_region(&buffer, offset, data).unwrap();
+    count as jint
+}
+
+#[catch_panic(default = "-1")]
+#[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
+pub fn readInto0(
+    mut env: JNIEnv,
+    class: JObject,
+    buffer: JByteBuffer,
+    position: jint,
+    length: jint,
+) -> jint {
+    let mut entry = obtain_entry!(&mut env, &class, -1);
+
+    let (address, capacity) = match (
+        env.get_direct_buffer_address(&buffer),
+        env.get_direct_buffer_capacity(&buffer),
+    ) {
+        (Ok(address), Ok(capacity)) => (address, capacity),
+        _ => {
+            env.throw((
+                "java/lang/IllegalArgumentException",
+                "Buffer is not a direct buffer!",
+            ))
+            .unwrap();
+            return -1;
+        }
+    };
+
+    if position < 0 || length < 0 || position as usize + length as usize > capacity {
+        env.throw((
+            "java/lang/IndexOutOfBoundsException",
+            "Range out of the buffer bounds!",
+        ))
+        .unwrap();
+        return -1;
+    }
+    let (position, length) = (position as usize, length as usize);
+
+    // SAFETY: the range is within the buffer, which can't be freed while it is referenced here
+    let data = unsafe { std::slice::from_raw_parts_mut(address.add(position), length) };
+    let mut count = 0;
+    while count < length {
+        match entry.read(&mut data[count..]) {
+            Ok(0) => break,
+            Ok(read) => count += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => {
+                interop::throw_zip_error(&mut env, "Failed to read zip entry", e);
+                return -1;
+            }
+        }
+    }
+
+    match count {
+        0 if length > 0 => -1,
+        count => count as jint,
+    }
+}
+
+#[catch_panic]
+#[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
+pub fn skip0(mut env: JNIEnv, class: JObject, count: jlong) -> jlong {
+    let mut entry = obtain_entry!(&mut env, &class, 0);
+
+    match io::copy(&mut (&mut *entry).take(count as u64), &mut io::sink()) {
+        Ok(skipped) => skipped as jlong,
+        Err(e) => {
+            interop::throw_zip_error(&mut env, "Failed to skip zip entry data", e);
+            0
+        }
+    }
+}
+
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipEntry")]
 pub fn _finalize(mut env: JNIEnv, class: JObject) {
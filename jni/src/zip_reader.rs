@@ -1,15 +1,16 @@
-use crate::{cache, interop};
+use crate::{cache, interop, zip_entry};
 use catch_panic::catch_panic;
-use jni::objects::JByteArray;
-use jni::sys::jboolean;
+use jni::objects::{GlobalRef, JByteArray, JMethodID};
+use jni::sys::{jboolean, jlong};
 use jni::{
-    objects::{JObject, JString},
+    objects::{JObject, JString, JValue},
     sys::{jbyteArray, jint, jobject, jobjectArray, jsize},
     JNIEnv,
 };
 use jni_fn::jni_fn;
 use std::io::{Cursor, Read, Seek};
 use std::{fs::File, path::Path};
+use zip::read::{Limits, NameIssue, Problem};
 use zip::{result::ZipError, ZipArchive};
 
 trait ReaderTrait: Read + Seek {}
@@ -49,7 +50,7 @@ pub extern "system" fn Java_com_github_diamondminer88_zip_ZipReader_open__Ljava_
     let file = match File::open(Path::new(&path)) {
         Ok(file) => file,
         Err(e) => {
-            env.throw(format!("Failed to open file: {:?}", e)).unwrap();
+            interop::throw_zip_error(&mut env, "Failed to open file", e);
             return;
         }
     };
@@ -58,8 +59,38 @@ pub extern "system" fn Java_com_github_diamondminer88_zip_ZipReader_open__Ljava_
     let zip = match ZipArchive::new(reader) {
         Ok(zip) => zip,
         Err(e) => {
-            env.throw(format!("Failed to open archive: {:?}", e))
-                .unwrap();
+            interop::throw_zip_error(&mut env, "Failed to open archive", e);
+            return;
+        }
+    };
+
+    interop::set_field(&mut env, &class, cache::ZipReader_ptr(), zip).unwrap();
+}
+
+#[catch_panic]
+#[no_mangle]
+pub extern "system" fn Java_com_github_diamondminer88_zip_ZipReader_open__Ljava_lang_String_2Ljava_lang_String_2(
+    mut env: JNIEnv,
+    class: JObject,
+    path: JString,
+    index_path: JString,
+) {
+    let path: String = env.get_string(&path).unwrap().into();
+    let index_path: String = env.get_string(&index_path).unwrap().into();
+    let file = match File::open(Path::new(&path)) {
+        Ok(file) => file,
+        Err(e) => {
+            interop::throw_zip_error(&mut env, "Failed to open file", e);
+            return;
+        }
+    };
+
+    let modified = file.metadata().and_then(|m| m.modified()).ok();
+    let reader: Box<dyn ReaderTrait> = Box::new(file);
+    let zip = match ZipArchive::open_cached(reader, modified, index_path) {
+        Ok(zip) => zip,
+        Err(e) => {
+            interop::throw_zip_error(&mut env, "Failed to open archive", e);
             return;
         }
     };
@@ -81,7 +112,7 @@ pub extern "system" fn Java_com_github_diamondminer88_zip_ZipReader_open___3B(
     let zip = match ZipArchive::new(reader) {
         Ok(zip) => zip,
         Err(e) => {
-            env.throw(format!("Failed to parse zip: {:?}", e)).unwrap();
+            interop::throw_zip_error(&mut env, "Failed to parse zip", e);
             return;
         }
     };
@@ -128,14 +159,17 @@ pub fn openEntry0(
             return JObject::null().into_raw();
         }
         Err(e) => {
-            env.throw(format!("Failed to open zip entry! {:?}", e))
-                .unwrap();
+            interop::throw_zip_error(&mut env, "Failed to open zip entry", e);
             return JObject::null().into_raw();
         }
     };
 
     let zip_entry = unsafe {
-        env.new_object_unchecked(&cache::ZipEntry(), cache::ZipEntry_ctor(), &[])
+        let args = [
+            JValue::Object(&class).as_jni(),
+            JValue::Bool(raw as jboolean).as_jni(),
+        ];
+        env.new_object_unchecked(&cache::ZipEntry(), cache::ZipEntry_ctor(), &args)
             .unwrap()
     };
     interop::set_field(&mut env, &zip_entry, cache::ZipEntry_ptr(), zip_file).unwrap();
@@ -175,3 +209,265 @@ pub fn getEntryNames(mut env: JNIEnv, class: JObject) -> jobjectArray {
 
     array.into_raw()
 }
+
+#[catch_panic(default = "JObject::null().into_raw()")]
+#[jni_fn("com.github.diamondminer88.zip.ZipReader")]
+pub fn getEntriesMetadata(mut env: JNIEnv, class: JObject) -> jobjectArray {
+    let mut zip = obtain_reader!(&mut env, &class, JObject::null().into_raw());
+
+    if let Err(e) = zip.decode_metadata() {
+        interop::throw_zip_error(&mut env, "Failed to read entries", e);
+        return JObject::null().into_raw();
+    }
+
+    let array = env
+        .new_object_array(
+            zip.len() as jsize,
+            &cache::ZipEntryMetadata(),
+            JObject::null(),
+        )
+        .unwrap();
+
+    for entry in zip.entries() {
+        let name = env.auto_local(env.new_string(entry.name()).unwrap());
+        let comment = env.auto_local(env.new_string(entry.comment()).unwrap());
+        let last_modified = zip_entry::unix_time(&mut env, entry.last_modified());
+        let metadata = unsafe {
+            let args = [
+                JValue::Int(entry.index() as jint).as_jni(),
+                JValue::Object(&name).as_jni(),
+                JValue::Object(&comment).as_jni(),
+                JValue::Long(last_modified).as_jni(),
+                JValue::Bool(entry.is_dir() as jboolean).as_jni(),
+                JValue::Bool(entry.encrypted() as jboolean).as_jni(),
+                JValue::Int(entry.unix_mode().unwrap_or(0) as jint).as_jni(),
+                JValue::Int(entry.crc32() as jint).as_jni(),
+                JValue::Long(entry.size() as jlong).as_jni(),
+                JValue::Long(entry.compressed_size() as jlong).as_jni(),
+                JValue::Int(zip_entry::compression_internal(entry.compression())).as_jni(),
+            ];
+            env.new_object_unchecked(
+                &cache::ZipEntryMetadata(),
+                cache::ZipEntryMetadata_ctor(),
+                &args,
+            )
+            .unwrap()
+        };
+        let metadata = env.auto_local(metadata);
+        env.set_object_array_element(&array, entry.index() as jsize, metadata)
+            .unwrap();
+    }
+
+    array.into_raw()
+}
+
+fn new_string_array(env: &mut JNIEnv, strings: &[&str]) -> jobjectArray {
+    let array = env
+        .new_object_array(strings.len() as jsize, &cache::String(), JObject::null())
+        .unwrap();
+
+    for (i, string) in strings.iter().enumerate() {
+        let jvm_string = env.auto_local(env.new_string(string).unwrap());
+        env.set_object_array_element(&array, i as jsize, jvm_string)
+            .unwrap();
+    }
+
+    array.into_raw()
+}
+
+#[catch_panic(default = "JObject::null().into_raw()")]
+#[jni_fn("com.github.diamondminer88.zip.ZipReader")]
+pub fn list(mut env: JNIEnv, class: JObject, dir: JString) -> jobjectArray {
+    let dir: String = env.get_string(&dir).unwrap().into();
+    let zip = obtain_reader!(&mut env, &class, JObject::null().into_raw());
+
+    let paths = match zip.tree().list(&dir) {
+        Some(children) => children.map(|entry| entry.path()).collect::<Vec<&str>>(),
+        None => return JObject::null().into_raw(),
+    };
+
+    new_string_array(&mut env, &paths)
+}
+
+#[catch_panic(default = "JObject::null().into_raw()")]
+#[jni_fn("com.github.diamondminer88.zip.ZipReader")]
+pub fn glob(mut env: JNIEnv, class: JObject, pattern: JString) -> jobjectArray {
+    let pattern: String = env.get_string(&pattern).unwrap().into();
+    let zip = obtain_reader!(&mut env, &class, JObject::null().into_raw());
+
+    let paths = zip
+        .tree()
+        .glob(&pattern)
+        .map(|entry| entry.path())
+        .collect::<Vec<&str>>();
+
+    new_string_array(&mut env, &paths)
+}
+
+#[catch_panic]
+#[jni_fn("com.github.diamondminer88.zip.ZipReader")]
+pub fn isDirectory(mut env: JNIEnv, class: JObject, path: JString) -> jboolean {
+    let path: String = env.get_string(&path).unwrap().into();
+    let zip = obtain_reader!(&mut env, &class, 0);
+
+    zip.tree().is_dir(&path) as jboolean
+}
+
+#[catch_panic]
+#[jni_fn("com.github.diamondminer88.zip.ZipReader")]
+pub fn setLimits(
+    mut env: JNIEnv,
+    class: JObject,
+    max_entries: jlong,
+    max_total_size: jlong,
+    max_entry_size: jlong,
+    max_compression_ratio: jlong,
+    reject_overlapping: jboolean,
+) {
+    let mut zip = obtain_reader!(&mut env, &class, Default::default());
+
+    // Negative values mean no limit
+    let mut limits = Limits::default().reject_overlapping(reject_overlapping == 1);
+    if max_entries >= 0 {
+        limits = limits.max_entries(max_entries as usize);
+    }
+    if max_total_size >= 0 {
+        limits = limits.max_total_size(max_total_size as u64);
+    }
+    if max_entry_size >= 0 {
+        limits = limits.max_entry_size(max_entry_size as u64);
+    }
+    if max_compression_ratio >= 0 {
+        limits = limits.max_compression_ratio(max_compression_ratio as u64);
+    }
+
+    if let Err(e) = zip.set_limits(limits) {
+        interop::throw_zip_error(&mut env, "Archive exceeds limits", e);
+    }
+}
+
+#[catch_panic(default = "JObject::null().into_raw()")]
+#[jni_fn("com.github.diamondminer88.zip.ZipReader")]
+pub fn verify(mut env: JNIEnv, class: JObject) -> jobjectArray {
+    let mut zip = obtain_reader!(&mut env, &class, JObject::null().into_raw());
+
+    let report = match zip.verify() {
+        Ok(report) => report,
+        Err(e) => {
+            interop::throw_zip_error(&mut env, "Failed to verify archive", e);
+            return JObject::null().into_raw();
+        }
+    };
+    let problems = report
+        .failed()
+        .flat_map(|file| {
+            file.problems().iter().map(move |problem| Report {
+                index: file.index(),
+                name: file.name(),
+                kind: problem_kind(problem),
+                message: problem.to_string(),
+            })
+        })
+        .collect::<Vec<Report>>();
+
+    new_report_array(
+        &mut env,
+        &cache::ZipVerifyProblem(),
+        cache::ZipVerifyProblem_ctor(),
+        &problems,
+    )
+}
+
+/// Maps the problems found by `verify` to the values of `ZipVerifyProblem.Kind`
+fn problem_kind(problem: &Problem) -> jint {
+    match problem {
+        Problem::PasswordRequired => 0,
+        Problem::Unreadable(_) => 1,
+        Problem::Crc32Mismatch { .. } => 2,
+        Problem::SizeMismatch { .. } => 3,
+        Problem::LocalHeaderMismatch(_) => 4,
+        Problem::OutOfBounds => 5,
+        Problem::Overlap(_) => 6,
+    }
+}
+
+#[catch_panic(default = "JObject::null().into_raw()")]
+#[jni_fn("com.github.diamondminer88.zip.ZipReader")]
+pub fn analyzeNames(mut env: JNIEnv, class: JObject) -> jobjectArray {
+    let mut zip = obtain_reader!(&mut env, &class, JObject::null().into_raw());
+
+    let report = match zip.analyze_names() {
+        Ok(report) => report,
+        Err(e) => {
+            interop::throw_zip_error(&mut env, "Failed to analyze names", e);
+            return JObject::null().into_raw();
+        }
+    };
+    let names = zip.entries().map(|file| file.name()).collect::<Vec<&str>>();
+    let issues = report
+        .issues()
+        .iter()
+        .map(|issue| Report {
+            index: issue.index(),
+            name: names[issue.index()],
+            kind: issue_kind(issue),
+            message: issue.to_string(),
+        })
+        .collect::<Vec<Report>>();
+
+    new_report_array(
+        &mut env,
+        &cache::ZipNameIssue(),
+        cache::ZipNameIssue_ctor(),
+        &issues,
+    )
+}
+
+/// Maps the issues found by `analyzeNames` to the values of `ZipNameIssue.Kind`
+fn issue_kind(issue: &NameIssue) -> jint {
+    match issue {
+        NameIssue::Duplicate { .. } => 0,
+        NameIssue::CaseCollision { .. } => 1,
+        NameIssue::NormalizationCollision { .. } => 2,
+        NameIssue::FileDirectoryConflict { .. } => 3,
+        NameIssue::InvalidOnFat { .. } => 4,
+    }
+}
+
+/// A problem with an entry, as passed to the constructors of `ZipVerifyProblem` and `ZipNameIssue`
+struct Report<'a> {
+    index: usize,
+    name: &'a str,
+    kind: jint,
+    message: String,
+}
+
+fn new_report_array(
+    env: &mut JNIEnv,
+    class: &GlobalRef,
+    ctor: JMethodID,
+    reports: &[Report],
+) -> jobjectArray {
+    let array = env
+        .new_object_array(reports.len() as jsize, class, JObject::null())
+        .unwrap();
+
+    for (i, report) in reports.iter().enumerate() {
+        let name = env.auto_local(env.new_string(report.name).unwrap());
+        let message = env.auto_local(env.new_string(&report.message).unwrap());
+        let object = unsafe {
+            let args = [
+                JValue::Int(report.index as jint).as_jni(),
+                JValue::Object(&name).as_jni(),
+                JValue::Int(report.kind).as_jni(),
+                JValue::Object(&message).as_jni(),
+            ];
+            env.new_object_unchecked(class, ctor, &args).unwrap()
+        };
+        let object = env.auto_local(object);
+        env.set_object_array_element(&array, i as jsize, object)
+            .unwrap();
+    }
+
+    array.into_raw()
+}
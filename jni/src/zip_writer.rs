@@ -4,7 +4,7 @@ use std::io::{Cursor, Read, Seek, Write};
 use std::path::Path;
 
 use jni::objects::{JByteArray, JObjectArray};
-use jni::sys::{jbyte, jbyteArray, jshort};
+use jni::sys::{jbyte, jbyteArray, jint, jshort};
 use jni::{
     objects::{JObject, JString},
     sys::{jboolean, jsize},
@@ -13,7 +13,7 @@ use jni::{
 use jni_fn::jni_fn;
 
 use crate::{cache, interop};
-use zip::result::ZipError;
+use zip::result::{ZipError, ZipResult};
 use zip::truncate::Truncate;
 use zip::write::FileOptions;
 use zip::{CompressionMethod, ZipWriter};
@@ -82,7 +82,7 @@ pub extern "system" fn Java_com_github_diamondminer88_zip_ZipWriter_open__Ljava_
     let file = match fopen {
         Ok(file) => file,
         Err(e) => {
-            env.throw(format!("Failed to open file: {:?}", e)).unwrap();
+            interop::throw_zip_error(&mut env, "Failed to open file", e);
             return;
         }
     };
@@ -94,8 +94,7 @@ pub extern "system" fn Java_com_github_diamondminer88_zip_ZipWriter_open__Ljava_
         match ZipWriter::new_append(writer) {
             Ok(w) => w,
             Err(e) => {
-                env.throw(format!("Failed to open zip in append mode: {:?}", e))
-                    .unwrap();
+                interop::throw_zip_error(&mut env, "Failed to open zip in append mode", e);
                 return;
             }
         }
@@ -124,7 +123,7 @@ pub extern "system" fn Java_com_github_diamondminer88_zip_ZipWriter_open___3B(
     let zip = match zip_result {
         Ok(w) => w,
         Err(e) => {
-            env.throw(format!("Failed to parse zip: {:?}", e)).unwrap();
+            interop::throw_zip_error(&mut env, "Failed to parse zip", e);
             return;
         }
     };
@@ -155,56 +154,160 @@ pub fn writeEntry(
     let bytes = env.convert_byte_array(bytes).unwrap();
     let path = env.get_string(&path).unwrap();
     let alignment = alignment as u16;
-    let compression = match compression {
-        -1 => None,
+    let compression = compression_method(compression as jint);
+
+    if compression.is_none() {
+        env.throw((
+            "java/lang/IllegalArgumentException",
+            "Invalid compression type supplied!",
+        ))
+        .unwrap();
+        return;
+    }
+
+    let options = FileOptions::default()
+        // .large_file(bytes.len() >= (1024 * 1024 * 1024 * 4)) // 4 GiB
+        .compression_method(compression.unwrap());
+
+    let result = start_entry(&mut writer, path.into(), options, alignment)
+        .and_then(|_| writer.write_all(&bytes).map_err(ZipError::from));
+    if let Err(e) = result {
+        interop::throw_zip_error(&mut env, "Failed to write entry", e);
+    }
+}
+
+/// Maps the values of `ZipCompression` to the compression methods that can be written
+fn compression_method(compression: jint) -> Option<CompressionMethod> {
+    match compression {
         0 => Some(CompressionMethod::Stored),
         1 => Some(CompressionMethod::Deflated),
         2 => Some(CompressionMethod::Bzip2),
         3 => Some(CompressionMethod::Zstd),
         _ => None,
-    };
+    }
+}
 
-    if compression.is_none() {
-        env.throw("Invalid compression type supplied!").unwrap();
-        return;
+/// Starts a new entry, with its data aligned to `alignment` bytes if it isn't 0
+fn start_entry(
+    writer: &mut ZipWriter<Box<dyn WriterTrait>>,
+    path: String,
+    options: FileOptions,
+    alignment: u16,
+) -> ZipResult<()> {
+    match alignment > 0 {
+        true => writer
+            .start_file_aligned(path, options, alignment)
+            .map(|_| ()),
+        false => writer.start_file(path, options),
     }
+}
+
+#[catch_panic]
+#[jni_fn("com.github.diamondminer88.zip.ZipWriter")]
+pub fn openEntry0(
+    mut env: JNIEnv,
+    class: JObject,
+    path: JString,
+    compression: jint,
+    alignment: jint,
+    large_file: jboolean,
+) {
+    let mut writer = obtain_writer!(get, &mut env, &class, ());
+    let path: String = env.get_string(&path).unwrap().into();
+    let compression = match compression_method(compression) {
+        Some(compression) => compression,
+        None => {
+            env.throw((
+                "java/lang/IllegalArgumentException",
+                "Invalid compression type supplied!",
+            ))
+            .unwrap();
+            return;
+        }
+    };
+    let alignment = match u16::try_from(alignment) {
+        Ok(alignment) => alignment,
+        Err(_) => {
+            env.throw((
+                "java/lang/IllegalArgumentException",
+                "Alignment must be between 0 and 65535!",
+            ))
+            .unwrap();
+            return;
+        }
+    };
 
     let options = FileOptions::default()
-        // .large_file(bytes.len() >= (1024 * 1024 * 1024 * 4)) // 4 GiB
-        .compression_method(compression.unwrap());
+        .compression_method(compression)
+        .large_file(large_file == 1);
+    if let Err(e) = start_entry(&mut writer, path, options, alignment) {
+        interop::throw_zip_error(&mut env, "Failed to write entry", e);
+    }
+}
 
-    if alignment > 0 {
-        writer.start_file_aligned(path, options, alignment).unwrap();
-    } else {
-        writer.start_file(path, options).unwrap()
+#[catch_panic]
+#[jni_fn("com.github.diamondminer88.zip.ZipWriter")]
+pub fn write0(mut env: JNIEnv, class: JObject, bytes: JByteArray, offset: jint, length: jint) {
+    let mut writer = obtain_writer!(get, &mut env, &class, ());
+
+    // Copy the data in chunks, so that large writes don't need as much native memory
+    let mut chunk = vec![0i8; (length as usize).min(1 << 16)];
+    let end = offset + length;
+    let mut position = offset;
+    while position < end {
+        let count = (end - position).min(chunk.len() as jint);
+        let chunk = &mut chunk[..count as usize];
+        env.get_byte_array_region(&bytes, position, chunk).unwrap();
+
+        // SAFETY: u8 and i8 have the same layout
+        let chunk = unsafe { &*(chunk as *const [i8] as *const [u8]) };
+        if let Err(e) = writer.write_all(chunk) {
+            interop::throw_zip_error(&mut env, "Failed to write entry", e);
+            return;
+        }
+        position += count;
+    }
+}
+
+#[catch_panic]
+#[jni_fn("com.github.diamondminer88.zip.ZipWriter")]
+pub fn closeEntry0(mut env: JNIEnv, class: JObject) {
+    let mut writer = obtain_writer!(get, &mut env, &class, ());
+
+    if let Err(e) = writer.end_file() {
+        interop::throw_zip_error(&mut env, "Failed to finish entry", e);
     }
-    writer.write_all(&bytes).unwrap();
 }
 
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipWriter")]
-pub fn writeDir(mut env: JNIEnv, class: JObject, path: JString) {
+pub fn writeDir0(mut env: JNIEnv, class: JObject, path: JString) {
     let mut writer = obtain_writer!(get, &mut env, &class, ());
     let path = env.get_string(&path).unwrap();
 
     let options = FileOptions::default().compression_method(CompressionMethod::Stored);
-    writer.add_directory(path, options).unwrap();
+    if let Err(e) = writer.add_directory(path, options) {
+        interop::throw_zip_error(&mut env, "Failed to write entry", e);
+    }
 }
 
 #[catch_panic(default = "JObject::null().into_raw()")]
 #[jni_fn("com.github.diamondminer88.zip.ZipWriter")]
-pub fn toByteArray(mut env: JNIEnv, class: JObject) -> jbyteArray {
+pub fn toByteArray0(mut env: JNIEnv, class: JObject) -> jbyteArray {
     let mut writer = obtain_writer!(take, &mut env, &class, JObject::null().into_raw());
 
     match writer.finish() {
         Err(e) => {
-            env.throw(format!("Failed to close zip: {:?}", e)).unwrap();
+            interop::throw_zip_error(&mut env, "Failed to close zip", e);
             JObject::null().into_raw()
         }
         Ok(writer) => match writer.into_bytes() {
             None => {
-                env.throw("Cannot convert an archive opened from a file into bytes!")
-                    .unwrap();
+                env.throw((
+                    "java/lang/IllegalStateException",
+                    "Cannot convert an archive opened from a file into bytes!",
+                ))
+                .unwrap();
                 JObject::null().into_raw()
             }
             Some(bytes) => env.byte_array_from_slice(&*bytes).unwrap().into_raw(),
@@ -214,7 +317,7 @@ pub fn toByteArray(mut env: JNIEnv, class: JObject) -> jbyteArray {
 
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipWriter")]
-pub fn close(mut env: JNIEnv, class: JObject) {
+pub fn close0(mut env: JNIEnv, class: JObject) {
     let writer = interop::take_field::<_, _, ZipWriter<Box<dyn WriterTrait>>>(
         &mut env,
         &class,
@@ -226,34 +329,26 @@ pub fn close(mut env: JNIEnv, class: JObject) {
     };
 
     match writer.finish() {
-        Err(e) => env.throw(format!("Failed to close zip: {:?}", e)).unwrap(),
+        Err(e) => interop::throw_zip_error(&mut env, "Failed to close zip", e),
         _ => {}
     }
 }
 
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipWriter")]
-pub fn deleteEntry(mut env: JNIEnv, class: JObject, path: JString, fill_void: jboolean) {
+pub fn deleteEntry0(mut env: JNIEnv, class: JObject, path: JString, fill_void: jboolean) {
     let mut writer = obtain_writer!(get, &mut env, &class, ());
     let path = env.get_string(&path).unwrap();
     let fill_void = fill_void == 1;
 
     if let Err(err) = writer.remove_file(path, fill_void) {
-        match err {
-            ZipError::FileNotFound => {
-                env.throw("Cannot find the target entry to delete!")
-                    .unwrap();
-            }
-            _ => {
-                env.throw("Unknown error trying to delete entry!").unwrap();
-            }
-        }
+        interop::throw_zip_error(&mut env, "Failed to delete entry", err);
     }
 }
 
 #[catch_panic]
 #[jni_fn("com.github.diamondminer88.zip.ZipWriter")]
-pub fn deleteEntries(mut env: JNIEnv, class: JObject, entries: JObjectArray) {
+pub fn deleteEntries0(mut env: JNIEnv, class: JObject, entries: JObjectArray) {
     let mut writer = obtain_writer!(get, &mut env, &class, ());
     let entries_len = env.get_array_length(&entries).unwrap() as usize;
     let entries: Vec<String> = (0..entries_len)
@@ -266,15 +361,8 @@ pub fn deleteEntries(mut env: JNIEnv, class: JObject, entries: JObjectArray) {
 
     for name in entries {
         if let Err(err) = writer.remove_file(name, false) {
-            match err {
-                ZipError::FileNotFound => {
-                    env.throw("Cannot find the target entry to delete!")
-                        .unwrap();
-                }
-                _ => {
-                    env.throw("Unknown error trying to delete entry!").unwrap();
-                }
-            }
+            interop::throw_zip_error(&mut env, "Failed to delete entry", err);
+            return;
         }
     }
 }
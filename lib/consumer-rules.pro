@@ -1,4 +1,8 @@
 # Preserve native method names, and classes/fields/methods that are used JNI-side
 -keep class com.github.diamondminer88.zip.* { private final long ptr; }
--keepclassmembers class com.github.diamondminer88.zip.ZipEntry { private <init>(); }
+-keepclassmembers class com.github.diamondminer88.zip.ZipEntry { private <init>(com.github.diamondminer88.zip.ZipReader, boolean); }
 -keepclasseswithmembernames class com.github.diamondminer88.zip.* { native <methods>; }
+-keep class com.github.diamondminer88.zip.*Exception { public <init>(java.lang.String); }
+-keep class com.github.diamondminer88.zip.ZipEntryMetadata { private <init>(int, java.lang.String, java.lang.String, long, boolean, boolean, int, int, long, long, int); }
+-keep class com.github.diamondminer88.zip.ZipVerifyProblem { private <init>(int, java.lang.String, int, java.lang.String); }
+-keep class com.github.diamondminer88.zip.ZipNameIssue { private <init>(int, java.lang.String, int, java.lang.String); }
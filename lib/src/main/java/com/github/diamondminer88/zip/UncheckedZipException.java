@@ -0,0 +1,19 @@
+package com.github.diamondminer88.zip;
+
+import org.jetbrains.annotations.NotNull;
+
+/**
+ * Wraps a {@link ZipException} where checked exceptions can't be thrown, such as in {@link ZipReader#iterator()}.
+ */
+@SuppressWarnings("unused")
+public class UncheckedZipException extends RuntimeException {
+    public UncheckedZipException(@NotNull ZipException cause) {
+        super(cause.getMessage(), cause);
+    }
+
+    @NotNull
+    @Override
+    public synchronized ZipException getCause() {
+        return (ZipException) super.getCause();
+    }
+}
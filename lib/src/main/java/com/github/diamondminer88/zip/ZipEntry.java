@@ -3,6 +3,13 @@ package com.github.diamondminer88.zip;
 import org.jetbrains.annotations.NotNull;
 import org.jetbrains.annotations.Nullable;
 
+import java.io.IOException;
+import java.io.InputStream;
+import java.io.RandomAccessFile;
+import java.nio.ByteBuffer;
+import java.nio.ReadOnlyBufferException;
+import java.nio.channels.FileChannel;
+
 @SuppressWarnings("unused")
 public class ZipEntry {
     /**
@@ -10,10 +17,22 @@ public class ZipEntry {
      */
     private final long ptr = 0;
 
+    /**
+     * Archive the entry was opened from
+     */
+    private final ZipReader reader;
+
+    /**
+     * Whether the entry was opened without decompressing it
+     */
+    private final boolean raw;
+
     /**
      * Called by JNI.
      */
-    private ZipEntry() {
+    private ZipEntry(ZipReader reader, boolean raw) {
+        this.reader = reader;
+        this.raw = raw;
     }
 
     /**
@@ -58,6 +77,11 @@ public class ZipEntry {
         return !isDir();
     }
 
+    /**
+     * Whether the entry is encrypted.
+     */
+    public native boolean isEncrypted();
+
     /**
      * Get the unix mode for this file.
      */
@@ -105,13 +129,110 @@ public class ZipEntry {
 
     /**
      * Reads this file entry's data (decompressed or not depending on how this entry was opened)
+     * @throws ZipPasswordException If the entry is encrypted.
+     * @throws ZipFormatException If the data is corrupt, or its CRC32 doesn't match.
+     * @throws ZipLimitException If the data is larger than the limits set on the archive.
+     * @throws ZipException If the data can't be read.
+     * @throws IllegalStateException If this entry is a directory.
+     */
+    public native byte[] read() throws ZipException;
+
+    /**
+     * Opens a stream reading this file entry's data incrementally (decompressed or not depending on how this entry was opened).
+     * Unlike {@link ZipEntry#read()}, the data doesn't need to fit in memory, so this also works for entries over 2 GiB.
+     * <br/>
+     * The stream reads from the archive directly, so it must be read fully or closed
+     * before another entry of the same {@link ZipReader} is opened or read.
+     * Closing the stream releases this entry, which can't be used anymore afterwards.
+     */
+    @NotNull
+    public InputStream openStream() {
+        return new ZipEntryInputStream(this, raw ? getCompressedSize() : getSize());
+    }
+
+    /**
+     * Maps the data of this entry into memory, without reading nor copying it.
+     * Only uncompressed, unencrypted entries of archives opened from a file can be mapped.
+     * The mapping stays valid after the reader is closed, until the buffer is garbage collected.
+     * @return A direct, read-only buffer over the data of the entry.
+     * @throws IllegalStateException If the entry is compressed or encrypted, or the archive was opened from memory.
+     * @throws IOException If the archive file can't be mapped.
+     */
+    @NotNull
+    public ByteBuffer map() throws IOException {
+        String path = reader.getPath();
+        if (path == null) {
+            throw new IllegalStateException("Cannot map an entry of an archive opened from memory!");
+        }
+        if (getCompression() != ZipCompression.NONE || isEncrypted()) {
+            throw new IllegalStateException("Cannot map a compressed or encrypted entry!");
+        }
+
+        try (RandomAccessFile file = new RandomAccessFile(path, "r")) {
+            return file.getChannel().map(FileChannel.MapMode.READ_ONLY, getDataOffset(), getCompressedSize());
+        }
+    }
+
+    /**
+     * Reads this file entry's data (decompressed or not depending on how this entry was opened) into a buffer,
+     * starting at its position. Direct buffers are written to natively without any intermediate copy.
+     * Reading continues where the previous read of this entry stopped.
+     * @param buffer Buffer to fill up to its limit, or until the end of the data. Its position is advanced past the bytes read.
+     * @return The number of bytes read, or -1 at the end of the data.
+     * @throws ZipException If the data can't be read, like {@link ZipEntry#read()}.
+     */
+    public int readInto(@NotNull ByteBuffer buffer) throws ZipException {
+        if (buffer.isReadOnly()) {
+            throw new ReadOnlyBufferException();
+        }
+
+        int position = buffer.position();
+        int count = 0;
+        if (buffer.isDirect()) {
+            count = readInto0(buffer, position, buffer.remaining());
+        } else {
+            // Heap buffers are backed by an array, that is filled in chunks
+            while (count < buffer.remaining()) {
+                int read = read0(buffer.array(), buffer.arrayOffset() + position + count, buffer.remaining() - count);
+                if (read == -1) {
+                    break;
+                }
+                count += read;
+            }
+            if (count == 0 && buffer.hasRemaining()) {
+                count = -1;
+            }
+        }
+
+        if (count > 0) {
+            buffer.position(position + count);
+        }
+        return count;
+    }
+
+    /**
+     * Internal method for decompressing into part of a direct buffer, used by {@link ZipEntry#readInto(ByteBuffer)}.
+     * @return The number of bytes read, or -1 at the end of the data.
+     */
+    private native int readInto0(ByteBuffer buffer, int position, int length) throws ZipException;
+
+    /**
+     * Internal method for reading data into part of a buffer, used by {@link ZipEntryInputStream}.
+     * @return The number of bytes read, or -1 at the end of the data.
+     */
+    native int read0(byte[] buffer, int offset, int length) throws ZipException;
+
+    /**
+     * Internal method for skipping data, used by {@link ZipEntryInputStream}.
+     * @return The number of bytes skipped, less than {@code count} at the end of the data.
      */
-    public native byte[] read();
+    native long skip0(long count) throws ZipException;
 
     /**
      * Drops the ZipFile struct internally to prevent a memory leak.
+     * Also called by {@link ZipEntryInputStream#close()}, this does nothing once the struct was dropped.
      */
-    private native void _finalize();
+    native void _finalize();
 
     @Override
     protected void finalize() throws Throwable {
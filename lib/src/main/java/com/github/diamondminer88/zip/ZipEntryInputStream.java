@@ -0,0 +1,85 @@
+package com.github.diamondminer88.zip;
+
+import org.jetbrains.annotations.NotNull;
+
+import java.io.IOException;
+import java.io.InputStream;
+
+/**
+ * Stream over the data of a {@link ZipEntry}, returned by {@link ZipEntry#openStream()}.
+ */
+class ZipEntryInputStream extends InputStream {
+    private final ZipEntry entry;
+    /**
+     * Length of the data, to estimate the available bytes
+     */
+    private final long length;
+    private long position = 0;
+    private boolean closed = false;
+
+    ZipEntryInputStream(@NotNull ZipEntry entry, long length) {
+        this.entry = entry;
+        this.length = length;
+    }
+
+    private void ensureOpen() throws IOException {
+        if (closed) {
+            throw new IOException("Stream closed");
+        }
+    }
+
+    @Override
+    public int read() throws IOException {
+        byte[] buffer = new byte[1];
+        return read(buffer, 0, 1) == -1 ? -1 : buffer[0] & 0xff;
+    }
+
+    @Override
+    public int read(byte @NotNull [] buffer, int offset, int length) throws IOException {
+        if (offset < 0 || length < 0 || length > buffer.length - offset) {
+            throw new IndexOutOfBoundsException();
+        }
+        ensureOpen();
+        if (length == 0) {
+            return 0;
+        }
+
+        int count = entry.read0(buffer, offset, length);
+        if (count > 0) {
+            position += count;
+        }
+        return count;
+    }
+
+    @Override
+    public long skip(long count) throws IOException {
+        ensureOpen();
+        if (count <= 0) {
+            return 0;
+        }
+
+        long skipped = entry.skip0(count);
+        position += skipped;
+        return skipped;
+    }
+
+    /**
+     * Estimates the number of bytes left, from the size of the entry in the central directory.
+     */
+    @Override
+    public int available() throws IOException {
+        ensureOpen();
+        return (int) Math.min(Math.max(length - position, 0), Integer.MAX_VALUE);
+    }
+
+    /**
+     * Closes this stream, and releases the entry along with its hold on the archive.
+     */
+    @Override
+    public void close() {
+        if (!closed) {
+            closed = true;
+            entry._finalize();
+        }
+    }
+}
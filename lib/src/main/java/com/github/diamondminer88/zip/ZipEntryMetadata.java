@@ -0,0 +1,139 @@
+package com.github.diamondminer88.zip;
+
+import org.jetbrains.annotations.NotNull;
+
+/**
+ * The metadata of an entry, read from the central directory without opening the entry.
+ * Obtained with {@link ZipReader#getEntriesMetadata()}.
+ */
+@SuppressWarnings("unused")
+public class ZipEntryMetadata {
+    private final int index;
+    private final String name;
+    private final String comment;
+    private final long lastModified;
+    private final boolean dir;
+    private final boolean encrypted;
+    private final int mode;
+    private final int crc32;
+    private final long size;
+    private final long compressedSize;
+    private final int compression;
+
+    /**
+     * Called by JNI.
+     */
+    private ZipEntryMetadata(
+        int index,
+        String name,
+        String comment,
+        long lastModified,
+        boolean dir,
+        boolean encrypted,
+        int mode,
+        int crc32,
+        long size,
+        long compressedSize,
+        int compression
+    ) {
+        this.index = index;
+        this.name = name;
+        this.comment = comment;
+        this.lastModified = lastModified;
+        this.dir = dir;
+        this.encrypted = encrypted;
+        this.mode = mode;
+        this.crc32 = crc32;
+        this.size = size;
+        this.compressedSize = compressedSize;
+        this.compression = compression;
+    }
+
+    /**
+     * Get the index of this file in the archive, to open it with {@link ZipReader#openEntry(int)}.
+     */
+    public int getIndex() {
+        return index;
+    }
+
+    /**
+     * Get the name of the file.
+     * See {@link ZipEntry#getName()} about using it when extracting an archive.
+     */
+    @NotNull
+    public String getName() {
+        return name;
+    }
+
+    /**
+     * Get the comment of the file
+     */
+    @NotNull
+    public String getComment() {
+        return comment;
+    }
+
+    /**
+     * Get the time the file was last modified, like {@link ZipEntry#getLastModified()}.
+     * @return UNIX timestamp (possibly UTC)
+     */
+    public long getLastModified() {
+        return lastModified;
+    }
+
+    /**
+     * Whether the entry is a directory.
+     */
+    public boolean isDir() {
+        return dir;
+    }
+
+    /**
+     * Whether the entry is a file.
+     */
+    public boolean isFile() {
+        return !dir;
+    }
+
+    /**
+     * Whether the entry is encrypted.
+     */
+    public boolean isEncrypted() {
+        return encrypted;
+    }
+
+    /**
+     * Get the unix mode for this file, or 0 if the archive doesn't store it.
+     */
+    public int getMode() {
+        return mode;
+    }
+
+    /**
+     * Get the CRC32 hash of the original file.
+     */
+    public int getCRC32() {
+        return crc32;
+    }
+
+    /**
+     * Get the size of the file (bytes) when uncompressed.
+     */
+    public long getSize() {
+        return size;
+    }
+
+    /**
+     * Get the size of the file (in bytes) in the archive.
+     */
+    public long getCompressedSize() {
+        return compressedSize;
+    }
+
+    /**
+     * Get the compression type that this entry is compressed with.
+     */
+    public ZipCompression getCompression() {
+        return ZipCompression.fromInternal(compression);
+    }
+}
@@ -0,0 +1,14 @@
+package com.github.diamondminer88.zip;
+
+/**
+ * Thrown when an entry does not exist in the archive.
+ */
+@SuppressWarnings("unused")
+public class ZipEntryNotFoundException extends ZipException {
+    /**
+     * Called by JNI.
+     */
+    public ZipEntryNotFoundException(String message) {
+        super(message);
+    }
+}
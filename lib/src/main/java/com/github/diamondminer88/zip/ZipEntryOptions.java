@@ -0,0 +1,46 @@
+package com.github.diamondminer88.zip;
+
+import org.jetbrains.annotations.NotNull;
+
+/**
+ * Options for writing an entry with {@link ZipWriter#openEntry(String, ZipEntryOptions)}.
+ */
+@SuppressWarnings("unused")
+public class ZipEntryOptions {
+    ZipCompression compression = ZipCompression.DEFLATE;
+    int alignment = 0;
+    boolean largeFile = false;
+
+    /**
+     * Sets the compression of the entry, {@link ZipCompression#DEFLATE} by default.
+     */
+    @NotNull
+    public ZipEntryOptions setCompression(@NotNull ZipCompression compression) {
+        this.compression = compression;
+        return this;
+    }
+
+    /**
+     * Sets the alignment of the entry data from the start of the zip, or 0 to leave it unaligned (the default).
+     * This is commonly used for zip-aligning .so's inside apks so extractNativeLibs can be set to false.
+     * @throws IllegalArgumentException If the alignment isn't between 0 and 65535.
+     */
+    @NotNull
+    public ZipEntryOptions setAlignment(int alignment) {
+        if (alignment < 0 || alignment > 65535) {
+            throw new IllegalArgumentException("Alignment must be between 0 and 65535!");
+        }
+        this.alignment = alignment;
+        return this;
+    }
+
+    /**
+     * Sets whether the entry may be 4 GiB or larger, which needs Zip64 extensions some readers don't support.
+     * Writing more than 4 GiB fails if this isn't set.
+     */
+    @NotNull
+    public ZipEntryOptions setLargeFile(boolean largeFile) {
+        this.largeFile = largeFile;
+        return this;
+    }
+}
@@ -0,0 +1,55 @@
+package com.github.diamondminer88.zip;
+
+import org.jetbrains.annotations.NotNull;
+
+import java.io.IOException;
+import java.io.OutputStream;
+
+/**
+ * Stream writing the data of an entry, returned by {@link ZipWriter#openEntry(String, ZipEntryOptions)}.
+ */
+class ZipEntryOutputStream extends OutputStream {
+    private final ZipWriter writer;
+    private boolean closed = false;
+
+    ZipEntryOutputStream(@NotNull ZipWriter writer) {
+        this.writer = writer;
+    }
+
+    private void ensureOpen() throws IOException {
+        if (closed) {
+            throw new IOException("Stream closed");
+        }
+    }
+
+    @Override
+    public void write(int b) throws IOException {
+        write(new byte[]{(byte) b}, 0, 1);
+    }
+
+    @Override
+    public void write(byte @NotNull [] buffer, int offset, int length) throws IOException {
+        if (offset < 0 || length < 0 || length > buffer.length - offset) {
+            throw new IndexOutOfBoundsException();
+        }
+        ensureOpen();
+        writer.write0(buffer, offset, length);
+    }
+
+    /**
+     * Called by the {@link ZipWriter} once the entry is finished, so that it isn't written to anymore.
+     */
+    void markClosed() {
+        closed = true;
+    }
+
+    /**
+     * Finishes the entry. This does not close the {@link ZipWriter}.
+     */
+    @Override
+    public void close() throws IOException {
+        if (!closed) {
+            writer.finishEntryStream();
+        }
+    }
+}
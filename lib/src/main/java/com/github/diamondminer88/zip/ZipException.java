@@ -0,0 +1,17 @@
+package com.github.diamondminer88.zip;
+
+import java.io.IOException;
+
+/**
+ * Thrown when an archive can't be read or written.
+ * The subclasses of this exception tell why, otherwise it was caused by an I/O error.
+ */
+@SuppressWarnings("unused")
+public class ZipException extends IOException {
+    /**
+     * Called by JNI.
+     */
+    public ZipException(String message) {
+        super(message);
+    }
+}
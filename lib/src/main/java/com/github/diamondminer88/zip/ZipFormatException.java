@@ -0,0 +1,15 @@
+package com.github.diamondminer88.zip;
+
+/**
+ * Thrown when an archive is corrupt or uses a feature that is not supported,
+ * such as an entry whose CRC32 doesn't match its data or an unknown compression method.
+ */
+@SuppressWarnings("unused")
+public class ZipFormatException extends ZipException {
+    /**
+     * Called by JNI.
+     */
+    public ZipFormatException(String message) {
+        super(message);
+    }
+}
@@ -0,0 +1,14 @@
+package com.github.diamondminer88.zip;
+
+/**
+ * Thrown when an archive exceeds the limits set with {@link ZipReader#setLimits}.
+ */
+@SuppressWarnings("unused")
+public class ZipLimitException extends ZipException {
+    /**
+     * Called by JNI.
+     */
+    public ZipLimitException(String message) {
+        super(message);
+    }
+}
@@ -0,0 +1,86 @@
+package com.github.diamondminer88.zip;
+
+import org.jetbrains.annotations.NotNull;
+
+/**
+ * A conflict between entry names found by {@link ZipReader#analyzeNames()}.
+ */
+@SuppressWarnings("unused")
+public class ZipNameIssue {
+    /**
+     * The kinds of conflicts, in the order used by JNI.
+     */
+    public enum Kind {
+        /**
+         * Two entries have the same name, so the later one replaces the earlier one.
+         */
+        DUPLICATE,
+        /**
+         * Two entry names only differ by case, so they collide on case-insensitive storage.
+         */
+        CASE_COLLISION,
+        /**
+         * Two entry names only differ by their Unicode normalization, so they collide on storage that normalizes names.
+         */
+        NORMALIZATION_COLLISION,
+        /**
+         * A file has the same path as a directory.
+         */
+        FILE_DIRECTORY_CONFLICT,
+        /**
+         * An entry name can't be created on FAT or exFAT storage.
+         */
+        INVALID_ON_FAT,
+    }
+
+    private final int index;
+    private final String name;
+    private final Kind kind;
+    private final String message;
+
+    /**
+     * Called by JNI.
+     */
+    private ZipNameIssue(int index, String name, int kind, String message) {
+        this.index = index;
+        this.name = name;
+        this.kind = Kind.values()[kind];
+        this.message = message;
+    }
+
+    /**
+     * Get the index of the entry with the conflict, which is the later one for collisions.
+     */
+    public int getIndex() {
+        return index;
+    }
+
+    /**
+     * Get the name of the entry with the conflict.
+     */
+    @NotNull
+    public String getName() {
+        return name;
+    }
+
+    /**
+     * Get the kind of the conflict.
+     */
+    @NotNull
+    public Kind getKind() {
+        return kind;
+    }
+
+    /**
+     * Get a description of the conflict.
+     */
+    @NotNull
+    public String getMessage() {
+        return message;
+    }
+
+    @Override
+    public String toString() {
+        return name + ": " + message;
+    }
+}
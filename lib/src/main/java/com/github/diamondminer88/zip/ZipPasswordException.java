@@ -0,0 +1,14 @@
+package com.github.diamondminer88.zip;
+
+/**
+ * Thrown when an entry is encrypted, and no password or a wrong one was given.
+ */
+@SuppressWarnings("unused")
+public class ZipPasswordException extends ZipException {
+    /**
+     * Called by JNI.
+     */
+    public ZipPasswordException(String message) {
+        super(message);
+    }
+}
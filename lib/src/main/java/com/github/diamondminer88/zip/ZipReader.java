@@ -20,39 +20,76 @@ public class ZipReader implements Closeable, Iterable<ZipEntry> {
      */
     private final long ptr = 0;
 
+    /**
+     * Path to the archive, or null if it was opened from memory
+     */
+    @Nullable
+    private final String path;
+
     /**
      * Open a zip file with readonly operations
      * @param path Path to the archive
+     * @throws ZipException If the archive can't be opened or is corrupt.
      */
-    public ZipReader(@NotNull String path) {
+    public ZipReader(@NotNull String path) throws ZipException {
+        this.path = path;
         open(path);
     }
 
     /**
      * Open a zip with readonly operations
      * @param file File of the archive
+     * @throws ZipException If the archive can't be opened or is corrupt.
      */
-    public ZipReader(@NotNull File file) {
-        open(file.getAbsolutePath());
+    public ZipReader(@NotNull File file) throws ZipException {
+        this.path = file.getAbsolutePath();
+        open(path);
+    }
+
+    /**
+     * Open a zip with readonly operations, reusing a previously parsed index of its entries.
+     * The index is (re)built automatically if it is missing or the archive has changed since.
+     * @param file File of the archive
+     * @param indexCache File to store the index of the archive in, for example in the app's cache dir
+     * @throws ZipException If the archive can't be opened or is corrupt.
+     */
+    public ZipReader(@NotNull File file, @NotNull File indexCache) throws ZipException {
+        this.path = file.getAbsolutePath();
+        open(path, indexCache.getAbsolutePath());
     }
 
     /**
      * Open a zip with readonly operations
      * @param data Zip file as a byte array
+     * @throws ZipException If the archive is corrupt.
      */
-    public ZipReader(byte @NotNull [] data) {
+    public ZipReader(byte @NotNull [] data) throws ZipException {
+        this.path = null;
         open(data);
     }
 
+    /**
+     * Path to the archive, or null if it was opened from memory.
+     */
+    @Nullable
+    String getPath() {
+        return path;
+    }
+
     /**
      * Opens an archive and sets {@link ZipReader#ptr} to the native data.
      */
-    private native void open(String path);
+    private native void open(String path) throws ZipException;
+
+    /**
+     * Opens an archive using a cached index and sets {@link ZipReader#ptr} to the native data.
+     */
+    private native void open(String path, String indexPath) throws ZipException;
 
     /**
      * Parses an archive and sets {@link ZipReader#ptr} to the native data.
      */
-    private native void open(byte[] data);
+    private native void open(byte[] data) throws ZipException;
 
     /**
      * Destructs the underlying native ZipArchive at {@link ZipReader#ptr}
@@ -64,27 +101,32 @@ public class ZipReader implements Closeable, Iterable<ZipEntry> {
     /**
      * Get a contained file by index. Returns null if entry not found.
      * @param index Index of the file.
+     * @throws ZipPasswordException If the entry is encrypted.
+     * @throws ZipException If the entry is corrupt.
      */
     @Nullable
-    public ZipEntry openEntry(int index) {
+    public ZipEntry openEntry(int index) throws ZipException {
         return openEntry0(index, null, false);
     }
 
     /**
      * Search for a file entry by name. Returns null if entry not found.
      * @param path Path to the file inside the archive.
+     * @throws ZipPasswordException If the entry is encrypted.
+     * @throws ZipException If the entry is corrupt.
      */
     @Nullable
-    public ZipEntry openEntry(@NotNull String path) {
+    public ZipEntry openEntry(@NotNull String path) throws ZipException {
         return openEntry0(-1, path, false);
     }
 
     /**
      * Get a contained file by index without decompressing it.
      * @param index Index of the file.
+     * @throws ZipException If the entry is corrupt.
      */
     @Nullable
-    public ZipEntry openEntryRaw(int index) {
+    public ZipEntry openEntryRaw(int index) throws ZipException {
         return openEntry0(index, null, true);
     }
 
@@ -92,13 +134,14 @@ public class ZipReader implements Closeable, Iterable<ZipEntry> {
      * Search for a file entry by name. Returns null if entry not found.
      * Gets the contained file without decompressing it.
      * @param path Path to the file inside the archive.
+     * @throws ZipException If the entry is corrupt.
      */
     @Nullable
-    public ZipEntry openEntryRaw(@NotNull String path) {
+    public ZipEntry openEntryRaw(@NotNull String path) throws ZipException {
         return openEntry0(-1, path, true);
     }
 
-    private native ZipEntry openEntry0(int index, @Nullable String name, boolean raw);
+    private native ZipEntry openEntry0(int index, @Nullable String name, boolean raw) throws ZipException;
 
     /**
      * Number of files contained in this archive.
@@ -126,9 +169,80 @@ public class ZipReader implements Closeable, Iterable<ZipEntry> {
         return new String(getRawComment(), StandardCharsets.UTF_8);
     }
 
+    /**
+     * Lists the paths of the files and directories directly inside a directory.
+     * Directories that only exist implicitly through the files they contain are included,
+     * and their paths end with a {@code /}.
+     * @param dir Path of the directory, with or without a trailing {@code /}. Use an empty string for the root.
+     * @return The paths ordered by name, or null if the directory does not exist.
+     */
+    @Nullable
+    public native String[] list(@NotNull String dir);
+
+    /**
+     * Finds the paths of all files and directories matching a glob pattern, ordered by path.
+     * Supports {@code ?}, {@code *} (not crossing {@code /}), {@code **}, character classes such as {@code [a-z]},
+     * and {@code \} escapes. Directories are matched without their trailing {@code /}.
+     * @param pattern Glob pattern, for example {@code *.dex} or {@code lib/*}{@code /*.so}
+     */
+    @NotNull
+    public native String[] glob(@NotNull String pattern);
+
+    /**
+     * Checks whether a directory exists, even if it has no entry of its own in the archive.
+     * @param path Path of the directory, with or without a trailing {@code /}.
+     */
+    public native boolean isDirectory(@NotNull String path);
+
+    /**
+     * Limits the resources used to read this archive, for archives from untrusted sources.
+     * Once limits are set, reading an entry also fails if it decompresses to more than its declared size.
+     * @param maxEntries Maximum number of entries, or -1 for no limit.
+     * @param maxTotalSize Maximum uncompressed size of all entries together, or -1 for no limit.
+     * @param maxEntrySize Maximum uncompressed size of an entry, or -1 for no limit.
+     * @param maxCompressionRatio Maximum ratio between the uncompressed and compressed size of an entry, or -1 for no limit.
+     * @param rejectOverlapping Whether to reject archives whose entries overlap each other.
+     * @throws ZipLimitException If the archive exceeds the limits, in which case they are not changed.
+     */
+    public native void setLimits(
+        long maxEntries,
+        long maxTotalSize,
+        long maxEntrySize,
+        long maxCompressionRatio,
+        boolean rejectOverlapping
+    ) throws ZipException;
+
+    /**
+     * Checks the integrity of the archive. Every entry is decompressed to check its CRC32 and size,
+     * its local header is compared with the central directory, and entries whose data overlaps
+     * another entry or the central directory are reported. Encrypted entries are only checked structurally.
+     * @return Every problem found, with the entry it was found in. Empty if the archive is intact.
+     * @throws ZipException If the archive can't be read.
+     */
+    @NotNull
+    public native ZipVerifyProblem[] verify() throws ZipException;
+
+    /**
+     * Finds entries whose names would conflict when extracted: names only differing by case or Unicode normalization,
+     * duplicate names, files with the same path as a directory, and names that can't be created on FAT or exFAT storage.
+     * @return Every conflict, with the entry it was found for. Empty if there is none.
+     * @throws ZipException If the archive can't be read.
+     */
+    @NotNull
+    public native ZipNameIssue[] analyzeNames() throws ZipException;
+
+    /**
+     * Get the metadata of every entry, in archive order, without opening any of them.
+     * This is much cheaper than iterating over the entries when their data isn't needed.
+     * @throws ZipException If the central directory of a lazily opened archive can't be read.
+     */
+    @NotNull
+    public native ZipEntryMetadata[] getEntriesMetadata() throws ZipException;
+
     /**
      * Loop over all the entries within this zip.
-     * <b>If you are trying to loop over names, use {@link ZipReader#getEntryNames()} instead.</b>
+     * <b>If you are trying to loop over names or metadata, use {@link ZipReader#getEntryNames()}
+     * or {@link ZipReader#getEntriesMetadata()} instead.</b>
      * @return {@link Iterator} on every zip entry.
      */
     public Iterator<ZipEntry> getEntries() {
@@ -137,7 +251,8 @@ public class ZipReader implements Closeable, Iterable<ZipEntry> {
 
     /**
      * Iterate over all the entries contained in this archive.
-     * Opens entry with decompressing.
+     * Opens entry with decompressing, see {@link ZipReader#getEntriesMetadata()} to only read metadata.
+     * An entry that can't be opened ends the iteration with an {@link UncheckedZipException}.
      */
     @NotNull
     @Override
@@ -154,7 +269,11 @@ public class ZipReader implements Closeable, Iterable<ZipEntry> {
             @NotNull
             @Override
             public ZipEntry next() {
-                return Objects.requireNonNull(openEntry(cursor++));
+                try {
+                    return Objects.requireNonNull(openEntry(cursor++));
+                } catch (ZipException e) {
+                    throw new UncheckedZipException(e);
+                }
             }
         };
     }
@@ -0,0 +1,94 @@
+package com.github.diamondminer88.zip;
+
+import org.jetbrains.annotations.NotNull;
+
+/**
+ * A problem found in an entry by {@link ZipReader#verify()}.
+ */
+@SuppressWarnings("unused")
+public class ZipVerifyProblem {
+    /**
+     * The kinds of problems, in the order used by JNI.
+     */
+    public enum Kind {
+        /**
+         * The entry is encrypted, so its data was not checked.
+         */
+        PASSWORD_REQUIRED,
+        /**
+         * The entry could not be opened or its data could not be decompressed.
+         */
+        UNREADABLE,
+        /**
+         * The CRC32 of the data differs from the one in the central directory.
+         */
+        CRC32_MISMATCH,
+        /**
+         * The size of the data differs from the one in the central directory.
+         */
+        SIZE_MISMATCH,
+        /**
+         * A field of the local header differs from the central directory.
+         */
+        LOCAL_HEADER_MISMATCH,
+        /**
+         * The entry does not end before the central directory.
+         */
+        OUT_OF_BOUNDS,
+        /**
+         * The data of the entry overlaps with another entry.
+         */
+        OVERLAP,
+    }
+
+    private final int index;
+    private final String name;
+    private final Kind kind;
+    private final String message;
+
+    /**
+     * Called by JNI.
+     */
+    private ZipVerifyProblem(int index, String name, int kind, String message) {
+        this.index = index;
+        this.name = name;
+        this.kind = Kind.values()[kind];
+        this.message = message;
+    }
+
+    /**
+     * Get the index of the entry with the problem.
+     */
+    public int getIndex() {
+        return index;
+    }
+
+    /**
+     * Get the name of the entry with the problem.
+     */
+    @NotNull
+    public String getName() {
+        return name;
+    }
+
+    /**
+     * Get the kind of the problem.
+     */
+    @NotNull
+    public Kind getKind() {
+        return kind;
+    }
+
+    /**
+     * Get a description of the problem.
+     */
+    @NotNull
+    public String getMessage() {
+        return message;
+    }
+
+    @Override
+    public String toString() {
+        return name + ": " + message;
+    }
+}
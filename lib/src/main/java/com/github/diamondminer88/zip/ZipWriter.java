@@ -1,7 +1,11 @@
 package com.github.diamondminer88.zip;
 
+import org.jetbrains.annotations.NotNull;
+import org.jetbrains.annotations.Nullable;
+
 import java.io.Closeable;
 import java.io.File;
+import java.io.OutputStream;
 import java.nio.charset.StandardCharsets;
 import java.util.Collection;
 
@@ -16,11 +20,18 @@ public class ZipWriter implements Closeable {
      */
     private final long ptr = 0;
 
+    /**
+     * Stream of the entry opened with {@link ZipWriter#openEntry(String, ZipEntryOptions)}, until it is finished
+     */
+    @Nullable
+    private ZipEntryOutputStream entryStream;
+
     /**
      * Creates an archive to write to. This overwrites any existing archive.
      * @param path Path to new archive
+     * @throws ZipException If the archive can't be created.
      */
-    public ZipWriter(String path) {
+    public ZipWriter(String path) throws ZipException {
         open(path, false);
     }
 
@@ -28,16 +39,18 @@ public class ZipWriter implements Closeable {
      * Opens/creates an archive to write to.
      * @param path   Path to archive
      * @param append Append to existing archive
+     * @throws ZipException If the archive can't be opened, or is corrupt when appending to it.
      */
-    public ZipWriter(String path, boolean append) {
+    public ZipWriter(String path, boolean append) throws ZipException {
         open(path, append);
     }
 
     /**
      * Creates an archive to write to. This overwrites any existing archive.
      * @param file Path to new archive
+     * @throws ZipException If the archive can't be created.
      */
-    public ZipWriter(File file) {
+    public ZipWriter(File file) throws ZipException {
         open(file.getAbsolutePath(), false);
     }
 
@@ -45,16 +58,18 @@ public class ZipWriter implements Closeable {
      * Opens/creates an archive to write to.
      * @param file   Path to archive
      * @param append Append to existing archive
+     * @throws ZipException If the archive can't be opened, or is corrupt when appending to it.
      */
-    public ZipWriter(File file, boolean append) {
+    public ZipWriter(File file, boolean append) throws ZipException {
         open(file.getAbsolutePath(), append);
     }
 
     /**
      * Parse and append to an archive in memory.
      * @param data Existing archive's bytes
+     * @throws ZipException If the archive is corrupt.
      */
-    public ZipWriter(byte[] data) {
+    public ZipWriter(byte[] data) throws ZipException {
         open(data);
     }
 
@@ -62,7 +77,12 @@ public class ZipWriter implements Closeable {
      * Starts a new empty appendable archive in memory.
      */
     public ZipWriter() {
-        open(null);
+        try {
+            open(null);
+        } catch (ZipException e) {
+            // Starting a new archive in memory can't fail
+            throw new UncheckedZipException(e);
+        }
     }
 
     /**
@@ -70,13 +90,13 @@ public class ZipWriter implements Closeable {
      * @param path   Path to archive
      * @param append Append to existing archive
      */
-    private native void open(String path, boolean append);
+    private native void open(String path, boolean append) throws ZipException;
 
     /**
      * Append to an existing archive.
      * @param input Existing archive's bytes
      */
-    private native void open(byte[] input);
+    private native void open(byte[] input) throws ZipException;
 
     /**
      * Sets the comment for the zip archive.
@@ -94,15 +114,16 @@ public class ZipWriter implements Closeable {
     /**
      * Internal method for writing an entry
      */
-    private native void writeEntry(String path, byte[] data, int compression, int alignment);
+    private native void writeEntry(String path, byte[] data, int compression, int alignment) throws ZipException;
 
     /**
      * Create a deflate-compressed unaligned entry and write bytes to it.
      * @param path Path to entry inside the archive
      * @param data Raw data
+     * @throws ZipException If the entry can't be written.
      */
-    public void writeEntry(String path, byte[] data) {
-        writeEntry(path, data, ZipCompression.DEFLATE.internal, 0);
+    public void writeEntry(String path, byte[] data) throws ZipException {
+        writeEntry(path, data, ZipCompression.DEFLATE, 0);
     }
 
     /**
@@ -110,9 +131,10 @@ public class ZipWriter implements Closeable {
      * @param path Path to entry inside the archive
      * @param data Raw data
      * @param compression The target compression for the entry
+     * @throws ZipException If the entry can't be written.
      */
-    public void writeEntry(String path, byte[] data, ZipCompression compression) {
-        writeEntry(path, data, compression.internal, 0);
+    public void writeEntry(String path, byte[] data, ZipCompression compression) throws ZipException {
+        writeEntry(path, data, compression, 0);
     }
 
     /**
@@ -121,8 +143,10 @@ public class ZipWriter implements Closeable {
      * @param data Raw data
      * @param compression The target compression for the entry
      * @param alignment The target alignment for the entry data from the start of the zip. This is commonly used for zip-aligning .so's inside apks so extractNativeLibs can be set to false.
+     * @throws ZipException If the entry can't be written.
      */
-    public void writeEntry(String path, byte[] data, ZipCompression compression, int alignment) {
+    public void writeEntry(String path, byte[] data, ZipCompression compression, int alignment) throws ZipException {
+        finishEntryStream();
         writeEntry(path, data, compression.internal, alignment);
     }
 
@@ -130,22 +154,77 @@ public class ZipWriter implements Closeable {
      * Create a deflate-compressed unaligned entry and write to it.
      * @param path    Path to entry inside the archive
      * @param content Content that will be encoded as UTF-8
+     * @throws ZipException If the entry can't be written.
      */
-    public void writeEntry(String path, String content) {
-        writeEntry(path, content.getBytes(StandardCharsets.UTF_8), ZipCompression.DEFLATE.internal, 0);
+    public void writeEntry(String path, String content) throws ZipException {
+        writeEntry(path, content.getBytes(StandardCharsets.UTF_8), ZipCompression.DEFLATE, 0);
     }
 
+    /**
+     * Create an entry and open a stream to write its data incrementally, for content too large to hold in memory.
+     * The entry is finished when the stream is closed, or when anything else is written to or deleted from
+     * the archive, after which writing to the stream fails.
+     * @param path    Path to entry inside the archive
+     * @param options Compression, alignment and size of the entry
+     * @throws ZipException If the entry can't be created.
+     */
+    @NotNull
+    public OutputStream openEntry(@NotNull String path, @NotNull ZipEntryOptions options) throws ZipException {
+        finishEntryStream();
+        openEntry0(path, options.compression.internal, options.alignment, options.largeFile);
+        entryStream = new ZipEntryOutputStream(this);
+        return entryStream;
+    }
+
+    /**
+     * Finishes the entry opened with {@link ZipWriter#openEntry(String, ZipEntryOptions)}, if it wasn't already,
+     * so that its stream can't write into the entries that follow.
+     */
+    void finishEntryStream() throws ZipException {
+        if (entryStream != null) {
+            entryStream.markClosed();
+            entryStream = null;
+            closeEntry0();
+        }
+    }
+
+    /**
+     * Internal method for creating an entry written with {@link ZipEntryOutputStream}
+     */
+    private native void openEntry0(String path, int compression, int alignment, boolean largeFile) throws ZipException;
+
+    /**
+     * Internal method for writing part of a buffer to the current entry, used by {@link ZipEntryOutputStream}
+     */
+    native void write0(byte[] data, int offset, int length) throws ZipException;
+
+    /**
+     * Internal method for finishing the current entry, used by {@link ZipWriter#finishEntryStream()}
+     */
+    private native void closeEntry0() throws ZipException;
+
     /**
      * Create a directory in the archive.
      * @param path Path to directory. Will automatically append a `/` if the path does not end with one already.
+     * @throws ZipException If the directory can't be written.
+     */
+    public void writeDir(String path) throws ZipException {
+        finishEntryStream();
+        writeDir0(path);
+    }
+
+    /**
+     * Internal method for creating a directory
      */
-    public native void writeDir(String path);
+    private native void writeDir0(String path) throws ZipException;
 
     /**
      * Delete an entry from this archive.
      * @param path Path to entry in the archive.
+     * @throws ZipEntryNotFoundException If the entry does not exist.
+     * @throws ZipException If the entry can't be deleted.
      */
-    public void deleteEntry(String path) {
+    public void deleteEntry(String path) throws ZipException {
         deleteEntry(path, false);
     }
 
@@ -153,20 +232,42 @@ public class ZipWriter implements Closeable {
      * Delete an entry from this archive.
      * @param path Path to entry in the archive.
      * @param fillVoid Keep other entries' alignment by only removing the entry from the central directory and replacing the file content with nulls.
+     * @throws ZipEntryNotFoundException If the entry does not exist.
+     * @throws ZipException If the entry can't be deleted.
      */
-    public native void deleteEntry(String path, boolean fillVoid);
+    public void deleteEntry(String path, boolean fillVoid) throws ZipException {
+        finishEntryStream();
+        deleteEntry0(path, fillVoid);
+    }
+
+    /**
+     * Internal method for deleting an entry
+     */
+    private native void deleteEntry0(String path, boolean fillVoid) throws ZipException;
 
     /**
      * Delete entries from this archive.
      * @param paths Target paths of entries
+     * @throws ZipEntryNotFoundException If an entry does not exist, in which case the following ones are not deleted.
+     * @throws ZipException If an entry can't be deleted.
      */
-    public native void deleteEntries(String... paths);
+    public void deleteEntries(String... paths) throws ZipException {
+        finishEntryStream();
+        deleteEntries0(paths);
+    }
+
+    /**
+     * Internal method for deleting entries
+     */
+    private native void deleteEntries0(String[] paths) throws ZipException;
 
     /**
      * Delete entries from this archive.
      * @param paths Target paths of entries
+     * @throws ZipEntryNotFoundException If an entry does not exist, in which case the following ones are not deleted.
+     * @throws ZipException If an entry can't be deleted.
      */
-    public void deleteEntries(Collection<String> paths) {
+    public void deleteEntries(Collection<String> paths) throws ZipException {
         var entriesArr = new String[paths.size()];
         paths.toArray(entriesArr);
 
@@ -176,13 +277,31 @@ public class ZipWriter implements Closeable {
     /**
      * Finalizes the writer and returns the bytes, assuming this writer was opened via bytes.
      * @throws IllegalStateException If this writer was not opened via {@link ZipWriter#ZipWriter(byte[])}.
+     * @throws ZipException If the archive can't be finalized.
+     */
+    public byte[] toByteArray() throws ZipException {
+        finishEntryStream();
+        return toByteArray0();
+    }
+
+    /**
+     * Internal method for finalizing the writer into bytes
      */
-    public native byte[] toByteArray();
+    private native byte[] toByteArray0() throws ZipException;
 
     /**
      * Finalizes the writer and saves to disk.
      * You cannot use this ZipWriter instance after closing it.
+     * @throws ZipException If the archive can't be finalized.
      */
     @Override
-    public native void close();
+    public void close() throws ZipException {
+        finishEntryStream();
+        close0();
+    }
+
+    /**
+     * Internal method for finalizing the writer
+     */
+    private native void close0() throws ZipException;
 }
@@ -14,7 +14,7 @@ use std::io::{self, Error, ErrorKind, Read};
 /// The length of the password verifcation value in bytes
 const PWD_VERIFY_LENGTH: usize = 2;
 /// The length of the authentication code in bytes
-const AUTH_CODE_LENGTH: usize = 10;
+pub(crate) const AUTH_CODE_LENGTH: usize = 10;
 /// The number of iterations used with PBKDF2
 const ITERATION_COUNT: u32 = 1000;
 
@@ -34,6 +34,11 @@ fn cipher_from_mode(aes_mode: AesMode, key: &[u8]) -> Box<dyn aes_ctr::AesCipher
     }
 }
 
+/// The length of the salt and password verification value before the encrypted data, in bytes
+pub(crate) fn header_length(aes_mode: AesMode) -> usize {
+    aes_mode.salt_length() + PWD_VERIFY_LENGTH
+}
+
 // An aes encrypted file starts with a salt, whose length depends on the used aes mode
 // followed by a 2 byte password verification value
 // then the variable length encrypted data
@@ -143,35 +148,14 @@ impl<R: Read> Read for AesReaderValid<R> {
         let read = self.reader.read(&mut buf[0..bytes_to_read])?;
         self.data_remaining -= read as u64;
 
-        // Update the hmac with the encrypted data
-        self.hmac.update(&buf[0..read]);
-
-        // decrypt the data
-        self.cipher.crypt_in_place(&mut buf[0..read]);
+        self.authenticate(&buf[0..read]);
+        self.decrypt(&mut buf[0..read]);
 
         // if there is no data left to read, check the integrity of the data
         if self.data_remaining == 0 {
-            assert!(
-                !self.finalized,
-                "Tried to use an already finalized HMAC. This is a bug!"
-            );
-            self.finalized = true;
-
-            // Zip uses HMAC-Sha1-80, which only uses the first half of the hash
-            // see https://www.winzip.com/win/en/aes_info.html#auth-faq
             let mut read_auth_code = [0; AUTH_CODE_LENGTH];
             self.reader.read_exact(&mut read_auth_code)?;
-            let computed_auth_code = &self.hmac.finalize_reset().into_bytes()[0..AUTH_CODE_LENGTH];
-
-            // use constant time comparison to mitigate timing attacks
-            if !constant_time_eq(computed_auth_code, &read_auth_code) {
-                return Err(
-                    Error::new(
-                        ErrorKind::InvalidData,
-                        "Invalid authentication code, this could be due to an invalid password or errors in the data"
-                    )
-                );
-            }
+            self.check_auth_code(&read_auth_code)?;
         }
 
         Ok(read)
@@ -179,8 +163,73 @@ impl<R: Read> Read for AesReaderValid<R> {
 }
 
 impl<R: Read> AesReaderValid<R> {
+    /// Update the hmac with encrypted data
+    pub(crate) fn authenticate(&mut self, data: &[u8]) {
+        self.hmac.update(data);
+    }
+
+    /// Decrypt data that follows the data decrypted so far, in place.
+    pub(crate) fn decrypt(&mut self, buf: &mut [u8]) {
+        self.cipher.crypt_in_place(buf);
+    }
+
+    /// Check the authentication code following the encrypted data against all of the data given to
+    /// [`AesReaderValid::authenticate`].
+    pub(crate) fn check_auth_code(&mut self, read_auth_code: &[u8]) -> io::Result<()> {
+        assert!(
+            !self.finalized,
+            "Tried to use an already finalized HMAC. This is a bug!"
+        );
+        self.finalized = true;
+
+        // Zip uses HMAC-Sha1-80, which only uses the first half of the hash
+        // see https://www.winzip.com/win/en/aes_info.html#auth-faq
+        let computed_auth_code = &self.hmac.finalize_reset().into_bytes()[0..AUTH_CODE_LENGTH];
+
+        // use constant time comparison to mitigate timing attacks
+        if !constant_time_eq(computed_auth_code, read_auth_code) {
+            return Err(
+                Error::new(
+                    ErrorKind::InvalidData,
+                    "Invalid authentication code, this could be due to an invalid password or errors in the data"
+                )
+            );
+        }
+        Ok(())
+    }
+
+    /// Continue decrypting from another reader, which holds the data following the data read so far.
+    pub(crate) fn with_reader<S: Read>(self, reader: S) -> AesReaderValid<S> {
+        AesReaderValid {
+            reader,
+            data_remaining: self.data_remaining,
+            cipher: self.cipher,
+            hmac: self.hmac,
+            finalized: self.finalized,
+        }
+    }
+
     /// Consumes this decoder, returning the underlying reader.
     pub fn into_inner(self) -> R {
         self.reader
     }
 }
+
+/// Encrypt data the way [`AesReader`] expects it, since there is no writer for AES encrypted files
+#[cfg(all(test, feature = "deflate"))]
+pub(crate) fn encrypt(aes_mode: AesMode, password: &[u8], data: &[u8]) -> Vec<u8> {
+    let key_length = aes_mode.key_length();
+    let mut salt = vec![7; aes_mode.salt_length()];
+    let mut derived_key = vec![0; 2 * key_length + PWD_VERIFY_LENGTH];
+    pbkdf2::pbkdf2::<Hmac<Sha1>>(password, &salt, ITERATION_COUNT, &mut derived_key).unwrap();
+
+    let mut encrypted = data.to_vec();
+    cipher_from_mode(aes_mode, &derived_key[..key_length]).crypt_in_place(&mut encrypted);
+    let mut hmac = Hmac::<Sha1>::new_from_slice(&derived_key[key_length..2 * key_length]).unwrap();
+    hmac.update(&encrypted);
+
+    salt.extend_from_slice(&derived_key[2 * key_length..]);
+    salt.extend_from_slice(&encrypted);
+    salt.extend_from_slice(&hmac.finalize().into_bytes()[..AUTH_CODE_LENGTH]);
+    salt
+}
@@ -5,6 +5,8 @@ use std::io::prelude::*;
 
 use crc32fast::Hasher;
 
+use crate::result::ZipError;
+
 /// Reader that validates the CRC32 when it reaches the EOF.
 pub struct Crc32Reader<R> {
     inner: R,
@@ -13,6 +15,10 @@ pub struct Crc32Reader<R> {
     /// Signals if `inner` stores aes encrypted data.
     /// AE-2 encrypted data doesn't use crc and sets the value to 0.
     ae2_encrypted: bool,
+    /// Name of the file, for errors
+    name: String,
+    /// Called with the actual checksum instead of failing when it doesn't match
+    on_mismatch: Option<Box<dyn FnOnce(u32)>>,
 }
 
 impl<R> Crc32Reader<R> {
@@ -24,9 +30,23 @@ impl<R> Crc32Reader<R> {
             hasher: Hasher::new(),
             check: checksum,
             ae2_encrypted,
+            name: String::new(),
+            on_mismatch: None,
         }
     }
 
+    /// Set the name of the file being read, to report it in errors.
+    pub(crate) fn name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    /// Call `on_mismatch` with the actual checksum instead of failing when it doesn't match.
+    pub(crate) fn on_mismatch(mut self, on_mismatch: Option<Box<dyn FnOnce(u32)>>) -> Self {
+        self.on_mismatch = on_mismatch;
+        self
+    }
+
     fn check_matches(&self) -> bool {
         self.check == self.hasher.clone().finalize()
     }
@@ -42,7 +62,23 @@ impl<R: Read> Read for Crc32Reader<R> {
 
         let count = match self.inner.read(buf) {
             Ok(0) if invalid_check => {
-                return Err(io::Error::new(io::ErrorKind::Other, "Invalid checksum"))
+                let actual = self.hasher.clone().finalize();
+                match self.on_mismatch.take() {
+                    Some(on_mismatch) => {
+                        // Only report the mismatch once
+                        self.check = actual;
+                        on_mismatch(actual);
+                        0
+                    }
+                    None => {
+                        return Err(ZipError::Crc32Mismatch {
+                            name: self.name.clone(),
+                            expected: self.check,
+                            actual,
+                        }
+                        .into())
+                    }
+                }
             }
             Ok(n) => n,
             Err(e) => return Err(e),
@@ -70,7 +106,7 @@ mod test {
             .read(&mut buf)
             .unwrap_err()
             .to_string()
-            .contains("Invalid checksum"));
+            .contains("invalid checksum"));
     }
 
     #[test]
@@ -13,7 +13,7 @@ use byteorder::{LittleEndian, ReadBytesExt};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::{self, prelude::*};
-use std::path::{Component, Path};
+use std::path::Path;
 use std::sync::Arc;
 
 #[cfg(any(
@@ -29,9 +29,49 @@ use bzip2::read::BzDecoder;
 #[cfg(feature = "zstd")]
 use zstd::stream::read::Decoder as ZstdDecoder;
 
+mod descriptor;
+mod extract;
+mod glob;
+mod index_cache;
+mod lazy;
+mod limits;
+mod metadata;
+mod missing_zip64;
+#[cfg(feature = "unicode-normalization")]
+mod names;
+mod parse;
+mod recover;
+mod repair;
+mod seek_index;
+mod seekable;
+#[cfg(test)]
+mod test_util;
+mod tree;
+mod verify;
+
+#[cfg(feature = "unicode-normalization")]
+pub use extract::Conflicts;
+pub use extract::{
+    extract_stream, ExtractOptions, ExtractReport, FailedEntry, Overwrite, Symlinks,
+};
+pub use index_cache::IndexKey;
+pub use limits::Limits;
+pub use metadata::FileMetadata;
+#[cfg(feature = "unicode-normalization")]
+pub use names::{NameIssue, NameReport};
+pub use parse::{Diagnostic, ParseMode, ParseOptions};
+pub use recover::Unrecoverable;
+pub use repair::{repair, RemovedFile, RepairOptions, RepairReport};
+pub use seek_index::{IndexedZipFile, SeekIndex, DEFAULT_CHECKPOINT_SPACING};
+pub use seekable::SeekableZipFile;
+pub use tree::{DirectoryTree, TreeEntry};
+pub use verify::{FileReport, HeaderField, Problem, VerifyReport};
+
 mod ffi {
     pub const S_IFDIR: u32 = 0o0040000;
     pub const S_IFREG: u32 = 0o0100000;
+    pub const S_IFLNK: u32 = 0o0120000;
+    pub const S_IFMT: u32 = 0o0170000;
 }
 
 // Put the struct declaration in a private module to convince rustdoc to display ZipArchive nicely
@@ -41,8 +81,16 @@ pub(crate) mod zip_archive {
     pub(crate) struct Shared {
         pub(super) files: Vec<super::ZipFileData>,
         pub(super) names_map: super::HashMap<String, usize>,
+        /// Set instead of `files` and `names_map` when the archive was opened lazily
+        pub(super) lazy: Option<super::lazy::LazyIndex>,
         pub(super) offset: u64,
         pub(super) comment: Vec<u8>,
+        /// Built on first use by `ZipArchive::tree`
+        pub(super) tree: std::sync::OnceLock<super::tree::DirectoryTree>,
+        /// Built by `ZipArchive::seek_index`, by file index
+        pub(super) seek_indexes: std::sync::Mutex<
+            super::HashMap<usize, super::Arc<super::seek_index::SeekIndex>>,
+        >,
     }
 
     /// ZIP archive reader
@@ -69,10 +117,35 @@ pub(crate) mod zip_archive {
     pub struct ZipArchive<R> {
         pub(super) reader: R,
         pub(super) shared: super::Arc<Shared>,
+        pub(super) limits: super::Limits,
+        pub(super) options: super::ParseOptions,
     }
 }
 
 pub use zip_archive::ZipArchive;
+
+impl zip_archive::Shared {
+    /// Get the index of the file with the given name
+    pub(crate) fn index_of(&self, name: &str) -> Option<usize> {
+        match &self.lazy {
+            Some(lazy) => lazy.index_of(name),
+            None => self.names_map.get(name).copied(),
+        }
+    }
+
+    /// Get the metadata of a file, decoding it from the central directory first if needed
+    pub(crate) fn file<R: Read + io::Seek>(
+        &self,
+        file_number: usize,
+        reader: &mut R,
+    ) -> ZipResult<&ZipFileData> {
+        match &self.lazy {
+            Some(lazy) => lazy.file(file_number, reader, self.offset),
+            None => self.files.get(file_number).ok_or(ZipError::FileNotFound),
+        }
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 enum CryptoReader<'a> {
     Plaintext(io::Take<&'a mut dyn Read>),
@@ -135,12 +208,14 @@ enum ZipFileReader<'a> {
     Bzip2(Crc32Reader<BzDecoder<CryptoReader<'a>>>),
     #[cfg(feature = "zstd")]
     Zstd(Crc32Reader<ZstdDecoder<'a, io::BufReader<CryptoReader<'a>>>>),
+    /// Streamed file whose sizes are only given by the data descriptor after its data
+    Descriptor(Box<descriptor::DescriptorReader<'a>>),
 }
 
 impl<'a> Read for ZipFileReader<'a> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self {
-            ZipFileReader::NoReader => panic!("ZipFileReader was in an invalid state"),
+            ZipFileReader::NoReader => Err(invalid_reader_state()),
             ZipFileReader::Raw(r) => r.read(buf),
             ZipFileReader::Stored(r) => r.read(buf),
             #[cfg(any(
@@ -153,15 +228,16 @@ impl<'a> Read for ZipFileReader<'a> {
             ZipFileReader::Bzip2(r) => r.read(buf),
             #[cfg(feature = "zstd")]
             ZipFileReader::Zstd(r) => r.read(buf),
+            ZipFileReader::Descriptor(r) => r.read(buf),
         }
     }
 }
 
 impl<'a> ZipFileReader<'a> {
     /// Consumes this decoder, returning the underlying reader.
-    pub fn into_inner(self) -> io::Take<&'a mut dyn Read> {
-        match self {
-            ZipFileReader::NoReader => panic!("ZipFileReader was in an invalid state"),
+    pub fn into_inner(self) -> io::Result<io::Take<&'a mut dyn Read>> {
+        Ok(match self {
+            ZipFileReader::NoReader => return Err(invalid_reader_state()),
             ZipFileReader::Raw(r) => r,
             ZipFileReader::Stored(r) => r.into_inner().into_inner(),
             #[cfg(any(
@@ -174,43 +250,72 @@ impl<'a> ZipFileReader<'a> {
             ZipFileReader::Bzip2(r) => r.into_inner().into_inner().into_inner(),
             #[cfg(feature = "zstd")]
             ZipFileReader::Zstd(r) => r.into_inner().finish().into_inner().into_inner(),
-        }
+            ZipFileReader::Descriptor(_) => {
+                return Err(io::Error::other(
+                    "The end of a file with a data descriptor is only found by reading it",
+                ))
+            }
+        })
     }
 }
 
+/// Error for a file whose reader was lost, because it failed to be created or the file was
+/// exhausted
+fn invalid_reader_state() -> io::Error {
+    io::Error::other("ZipFileReader was in an invalid state")
+}
+
 /// A struct for reading a zip file
 pub struct ZipFile<'a> {
     index: usize,
     data: Cow<'a, ZipFileData>,
     crypto_reader: Option<CryptoReader<'a>>,
     reader: ZipFileReader<'a>,
+    /// Amount of data left before the declared size is exceeded, when limits are enforced
+    remaining_size: Option<u64>,
+    /// Options to report a CRC32 mismatch to instead of failing, when parsing leniently
+    lenient: Option<ParseOptions>,
 }
 
 fn find_content<'a>(
     data: &ZipFileData,
     reader: &'a mut (impl Read + Seek),
 ) -> ZipResult<io::Take<&'a mut dyn Read>> {
+    let data_start = find_data_start(data, reader)?;
+    reader.seek(io::SeekFrom::Start(data_start))?;
+    Ok((reader as &mut dyn Read).take(data.compressed_size))
+}
+
+/// Parse the lengths in the local header of a file to find where its data starts, leaving the
+/// reader at the start of the local extra field.
+fn find_data_start(data: &ZipFileData, reader: &mut (impl Read + Seek)) -> ZipResult<u64> {
     // Parse local header
     reader.seek(io::SeekFrom::Start(data.header_start))?;
-    let signature = reader.read_u32::<LittleEndian>()?;
+    let mut header = [0u8; 30];
+    reader.read_exact(&mut header)?;
+    let mut header = &header[..];
+    let signature = header.read_u32::<LittleEndian>()?;
     if signature != spec::LOCAL_FILE_HEADER_SIGNATURE {
-        return Err(ZipError::InvalidArchive("Invalid local file header"));
+        return Err(ZipError::InvalidSignature {
+            record: "local file header",
+            offset: data.header_start,
+            found: signature,
+        });
     }
 
-    reader.seek(io::SeekFrom::Current(22))?;
-    let file_name_length = reader.read_u16::<LittleEndian>()? as u64;
-    let extra_field_length = reader.read_u16::<LittleEndian>()? as u64;
+    let mut lengths = &header[22..];
+    let file_name_length = lengths.read_u16::<LittleEndian>()? as u64;
+    let extra_field_length = lengths.read_u16::<LittleEndian>()? as u64;
     let magic_and_header = 4 + 22 + 2 + 2;
     let data_start = data.header_start + magic_and_header + file_name_length + extra_field_length;
     data.data_start.store(data_start);
 
-    reader.seek(io::SeekFrom::Start(data_start))?;
-    Ok((reader as &mut dyn Read).take(data.compressed_size))
+    reader.seek(io::SeekFrom::Current(file_name_length as i64))?;
+    Ok(data_start)
 }
 
 #[allow(clippy::too_many_arguments)]
 fn make_crypto_reader<'a>(
-    compression_method: crate::compression::CompressionMethod,
     crc32: u32,
     last_modified_time: DateTime,
     using_data_descriptor: bool,
@@ -219,13 +324,6 @@ fn make_crypto_reader<'a>(
     aes_info: Option<(AesMode, AesVendorVersion)>,
     #[cfg(feature = "aes-crypto")] compressed_size: u64,
 ) -> ZipResult<Result<CryptoReader<'a>, InvalidPassword>> {
-    #[allow(deprecated)]
-    {
-        if let CompressionMethod::Unsupported(_) = compression_method {
-            return unsupported_zip_error("Compression method not supported");
-        }
-    }
-
     let reader = match (password, aes_info) {
         #[cfg(not(feature = "aes-crypto"))]
         (Some(_), Some(_)) => {
@@ -260,16 +358,30 @@ fn make_crypto_reader<'a>(
     Ok(Ok(reader))
 }
 
-fn make_reader(
-    compression_method: CompressionMethod,
-    crc32: u32,
-    reader: CryptoReader,
-) -> ZipFileReader {
+/// Check that the compression method of a file can be decompressed
+fn check_compression_method(data: &ZipFileData) -> ZipResult<()> {
+    #[allow(deprecated)]
+    if let CompressionMethod::Unsupported(_) = data.compression_method {
+        return Err(ZipError::UnsupportedCompressionMethod {
+            name: data.file_name.clone(),
+            method: data.compression_method,
+        });
+    }
+    Ok(())
+}
+
+fn make_reader<'a>(
+    data: &ZipFileData,
+    reader: CryptoReader<'a>,
+    on_crc32_mismatch: Option<Box<dyn FnOnce(u32)>>,
+) -> ZipResult<ZipFileReader<'a>> {
     let ae2_encrypted = reader.is_ae2_encrypted();
+    let crc32 = data.crc32;
 
-    match compression_method {
+    let reader = match data.compression_method {
         CompressionMethod::Stored => {
-            ZipFileReader::Stored(Crc32Reader::new(reader, crc32, ae2_encrypted))
+            let crc32_reader = Crc32Reader::new(reader, crc32, ae2_encrypted).name(&data.file_name);
+            ZipFileReader::Stored(crc32_reader.on_mismatch(on_crc32_mismatch))
         }
         #[cfg(any(
             feature = "deflate",
@@ -278,20 +390,32 @@ fn make_reader(
         ))]
         CompressionMethod::Deflated => {
             let deflate_reader = DeflateDecoder::new(reader);
-            ZipFileReader::Deflated(Crc32Reader::new(deflate_reader, crc32, ae2_encrypted))
+            let crc32_reader =
+                Crc32Reader::new(deflate_reader, crc32, ae2_encrypted).name(&data.file_name);
+            ZipFileReader::Deflated(crc32_reader.on_mismatch(on_crc32_mismatch))
         }
         #[cfg(feature = "bzip2")]
         CompressionMethod::Bzip2 => {
             let bzip2_reader = BzDecoder::new(reader);
-            ZipFileReader::Bzip2(Crc32Reader::new(bzip2_reader, crc32, ae2_encrypted))
+            let crc32_reader =
+                Crc32Reader::new(bzip2_reader, crc32, ae2_encrypted).name(&data.file_name);
+            ZipFileReader::Bzip2(crc32_reader.on_mismatch(on_crc32_mismatch))
         }
         #[cfg(feature = "zstd")]
         CompressionMethod::Zstd => {
-            let zstd_reader = ZstdDecoder::new(reader).unwrap();
-            ZipFileReader::Zstd(Crc32Reader::new(zstd_reader, crc32, ae2_encrypted))
+            let zstd_reader = ZstdDecoder::new(reader)?;
+            let crc32_reader =
+                Crc32Reader::new(zstd_reader, crc32, ae2_encrypted).name(&data.file_name);
+            ZipFileReader::Zstd(crc32_reader.on_mismatch(on_crc32_mismatch))
         }
-        _ => panic!("Compression method not supported"),
-    }
+        _ => {
+            return Err(ZipError::UnsupportedCompressionMethod {
+                name: data.file_name.clone(),
+                method: data.compression_method,
+            })
+        }
+    };
+    Ok(reader)
 }
 
 impl<R: Read + io::Seek> ZipArchive<R> {
@@ -314,7 +438,7 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         {
             match spec::Zip64CentralDirectoryEndLocator::parse(reader) {
                 Ok(loc) => Some(loc),
-                Err(ZipError::InvalidArchive(_)) => {
+                Err(ZipError::InvalidSignature { .. }) => {
                     // No ZIP64 header; that's actually fine. We're done here.
                     None
                 }
@@ -343,6 +467,13 @@ impl<R: Read + io::Seek> ZipArchive<R> {
                     ))?;
 
                 let directory_start = footer.central_directory_offset as u64 + archive_offset;
+                let (archive_offset, directory_start) = missing_zip64::locate_directory(
+                    reader,
+                    footer,
+                    cde_start_pos,
+                    archive_offset,
+                    directory_start,
+                )?;
                 let number_of_files = footer.number_of_files_on_this_disk as usize;
                 Ok((archive_offset, directory_start, number_of_files))
             }
@@ -396,18 +527,19 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         }
     }
 
-    /// Read a ZIP archive, collecting the files it contains
-    ///
-    /// This uses the central directory record of the ZIP file, and ignores local file headers
-    pub fn new(mut reader: R) -> ZipResult<ZipArchive<R>> {
-        let (footer, cde_start_pos) = spec::CentralDirectoryEnd::find_and_parse(&mut reader)?;
+    /// Locate the central directory, returning its footer, the archive offset, the directory start,
+    /// the number of files and a safe capacity to preallocate for them.
+    fn find_central_directory(
+        reader: &mut R,
+    ) -> ZipResult<(spec::CentralDirectoryEnd, u64, u64, usize, usize)> {
+        let (footer, cde_start_pos) = spec::CentralDirectoryEnd::find_and_parse(reader)?;
 
         if footer.disk_number != footer.disk_with_central_directory {
             return unsupported_zip_error("Support for multi-disk files is not implemented");
         }
 
         let (archive_offset, directory_start, number_of_files) =
-            Self::get_directory_counts(&mut reader, &footer, cde_start_pos)?;
+            Self::get_directory_counts(reader, &footer, cde_start_pos)?;
 
         // If the parsed number of files is greater than the offset then
         // something fishy is going on and we shouldn't trust number_of_files.
@@ -417,29 +549,166 @@ impl<R: Read + io::Seek> ZipArchive<R> {
             number_of_files
         };
 
-        let mut files = Vec::with_capacity(file_capacity);
-        let mut names_map = HashMap::with_capacity(file_capacity);
-
         if reader.seek(io::SeekFrom::Start(directory_start)).is_err() {
             return Err(ZipError::InvalidArchive(
                 "Could not seek to start of central directory",
             ));
         }
 
+        Ok((
+            footer,
+            archive_offset,
+            directory_start,
+            number_of_files,
+            file_capacity,
+        ))
+    }
+
+    /// Read a ZIP archive, collecting the files it contains
+    ///
+    /// This uses the central directory record of the ZIP file, and ignores local file headers
+    pub fn new(reader: R) -> ZipResult<ZipArchive<R>> {
+        Self::open(reader, Limits::default(), ParseOptions::default())
+    }
+
+    /// Read a ZIP archive like [`ZipArchive::new`], failing with [`ZipError::LimitExceeded`] if it
+    /// exceeds `limits`, which are then enforced when reading files.
+    ///
+    /// The number of files is checked before the central directory is read.
+    pub fn with_limits(reader: R, limits: Limits) -> ZipResult<ZipArchive<R>> {
+        Self::open(reader, limits, ParseOptions::default())
+    }
+
+    /// Read a ZIP archive like [`ZipArchive::new`], checking it according to `options`.
+    ///
+    /// In [`ParseMode::Strict`] and [`ParseMode::Lenient`], the local header of every file is read
+    /// to compare it with the central directory.
+    ///
+    /// ```no_run
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let options = zip::read::ParseOptions::lenient()
+    ///     .diagnostics(|diagnostic| eprintln!("warning: {}", diagnostic));
+    /// let file = std::fs::File::open("odd.zip")?;
+    /// let archive = zip::ZipArchive::with_options(file, options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_options(reader: R, options: ParseOptions) -> ZipResult<ZipArchive<R>> {
+        Self::open(reader, Limits::default(), options)
+    }
+
+    fn open(mut reader: R, limits: Limits, options: ParseOptions) -> ZipResult<ZipArchive<R>> {
+        let (footer, archive_offset, directory_start, number_of_files, file_capacity) =
+            Self::find_central_directory(&mut reader)?;
+        limits.check_entries(number_of_files)?;
+
+        let mut files = Vec::with_capacity(file_capacity);
+        let mut names_map = HashMap::with_capacity(file_capacity);
+
         for _ in 0..number_of_files {
-            let file = central_header_to_zip_file(&mut reader, archive_offset)?;
+            let file = central_header_to_zip_file(&mut reader, archive_offset, &options)?;
             names_map.insert(file.file_name.clone(), files.len());
             files.push(file);
         }
 
+        // Archives with more than 65535 files written without Zip64 only declare the number of
+        // files modulo 65536, so the headers past the declared number are read as well
+        while missing_zip64::next_is_central_header(&mut reader)? {
+            limits.check_entries(files.len() + 1)?;
+            let file = central_header_to_zip_file(&mut reader, archive_offset, &options)?;
+            names_map.insert(file.file_name.clone(), files.len());
+            files.push(file);
+        }
+        if files.len() != number_of_files {
+            options.check(Diagnostic::EntryCountMismatch {
+                declared: number_of_files as u64,
+                found: files.len() as u64,
+            })?;
+        }
+
+        if missing_zip64::offsets_truncated(&footer, archive_offset, directory_start) {
+            missing_zip64::fix_header_offsets(&mut reader, &mut files, directory_start)?;
+        }
+
+        if options.checks_records() {
+            for file in &files {
+                let diagnostics = match verify::local_header_mismatches(file, &mut reader) {
+                    Ok(fields) => fields
+                        .into_iter()
+                        .map(|field| Diagnostic::LocalHeaderMismatch {
+                            name: file.file_name.clone(),
+                            field,
+                        })
+                        .collect(),
+                    Err(error) => vec![Diagnostic::UnreadableLocalHeader {
+                        name: file.file_name.clone(),
+                        error,
+                    }],
+                };
+                for diagnostic in diagnostics {
+                    options.check(diagnostic)?;
+                }
+            }
+        }
+
         let shared = Arc::new(zip_archive::Shared {
             files,
             names_map,
+            lazy: None,
+            offset: archive_offset,
+            comment: footer.zip_file_comment,
+            tree: Default::default(),
+            seek_indexes: Default::default(),
+        });
+        limits.check_files(shared.files.iter())?;
+
+        Ok(ZipArchive {
+            reader,
+            shared,
+            limits,
+            options,
+        })
+    }
+
+    /// Read a ZIP archive without decoding the metadata of the files it contains up front
+    ///
+    /// Only the position and name of every central directory record is kept. The rest of a file's
+    /// metadata is decoded from the central directory the first time the file is accessed, so the
+    /// memory used stays proportional to the files actually opened. This is meant for archives
+    /// with a very large number of entries, of which only a few are needed.
+    ///
+    /// [`ZipArchive::file_names`] returns the names in central directory order for these archives.
+    pub fn new_lazy(mut reader: R) -> ZipResult<ZipArchive<R>> {
+        let (footer, archive_offset, directory_start, number_of_files, file_capacity) =
+            Self::find_central_directory(&mut reader)?;
+
+        let mut lazy =
+            lazy::LazyIndex::build(&mut reader, directory_start, number_of_files, file_capacity)?;
+        if missing_zip64::offsets_truncated(&footer, archive_offset, directory_start) {
+            lazy.fix_header_offsets(directory_start);
+        }
+
+        let shared = Arc::new(zip_archive::Shared {
+            files: Vec::new(),
+            names_map: HashMap::new(),
+            lazy: Some(lazy),
             offset: archive_offset,
             comment: footer.zip_file_comment,
+            tree: Default::default(),
+            seek_indexes: Default::default(),
         });
 
-        Ok(ZipArchive { reader, shared })
+        Ok(ZipArchive {
+            reader,
+            shared,
+            limits: Limits::default(),
+            options: ParseOptions::default(),
+        })
+    }
+
+    /// Whether this archive was opened with [`ZipArchive::new_lazy`]
+    pub fn is_lazy(&self) -> bool {
+        self.shared.lazy.is_some()
     }
     /// Extract a Zip archive into a directory, overwriting files if they
     /// already exist. Paths are sanitized with [`ZipFile::enclosed_name`].
@@ -482,7 +751,10 @@ impl<R: Read + io::Seek> ZipArchive<R> {
 
     /// Number of files contained in this zip.
     pub fn len(&self) -> usize {
-        self.shared.files.len()
+        match &self.shared.lazy {
+            Some(lazy) => lazy.len(),
+            None => self.shared.files.len(),
+        }
     }
 
     /// Whether this zip archive contains no files
@@ -505,7 +777,81 @@ impl<R: Read + io::Seek> ZipArchive<R> {
 
     /// Returns an iterator over all the file and directory names in this archive.
     pub fn file_names(&self) -> impl Iterator<Item = &str> {
-        self.shared.names_map.keys().map(|s| s.as_str())
+        let lazy_names = self.shared.lazy.iter().flat_map(|lazy| lazy.names());
+        self.shared
+            .names_map
+            .keys()
+            .map(|s| s.as_str())
+            .chain(lazy_names)
+    }
+
+    /// Iterate over the metadata of the files in this archive, in archive order.
+    ///
+    /// This only uses the central directory, so no file is opened and nothing is read from the archive.
+    /// For archives opened with [`ZipArchive::new_lazy`], only the files whose metadata was already
+    /// decoded are included, see [`ZipArchive::decode_metadata`].
+    pub fn entries(&self) -> impl Iterator<Item = FileMetadata<'_>> {
+        let shared = &*self.shared;
+        (0..self.len()).filter_map(move |index| {
+            let data = match &shared.lazy {
+                Some(lazy) => lazy.decoded(index)?,
+                None => &shared.files[index],
+            };
+            Some(FileMetadata::new(index, data))
+        })
+    }
+
+    /// Decode the metadata of every file of an archive opened with [`ZipArchive::new_lazy`], so
+    /// [`ZipArchive::entries`] includes all of them. This does nothing for other archives.
+    pub fn decode_metadata(&mut self) -> ZipResult<()> {
+        for index in 0..self.len() {
+            self.shared.file(index, &mut self.reader)?;
+        }
+        Ok(())
+    }
+
+    /// Find the exact data offset of every file by reading the lengths in its local header, so
+    /// [`FileMetadata::data_start`] and [`ZipFile::data_start`] are exact.
+    ///
+    /// Only the fixed size part of each local header is read, in archive order. For archives
+    /// opened with [`ZipArchive::new_lazy`], the metadata of every file is decoded first.
+    pub fn resolve_data_offsets(&mut self) -> ZipResult<()> {
+        self.decode_metadata()?;
+        let mut indices = (0..self.len()).collect::<Vec<_>>();
+        let shared = &*self.shared;
+        let file = |index: usize| match &shared.lazy {
+            Some(lazy) => lazy.decoded(index).unwrap(),
+            None => &shared.files[index],
+        };
+        indices.sort_by_key(|&index| file(index).header_start);
+
+        for index in indices {
+            find_data_start(file(index), &mut self.reader)?;
+        }
+        Ok(())
+    }
+
+    /// Read the extra field of the local header of a file, which may differ from the extra field in
+    /// the central directory returned by [`ZipFile::extra_data`].
+    pub fn local_extra_data(&mut self, file_number: usize) -> ZipResult<Vec<u8>> {
+        let data = self.shared.file(file_number, &mut self.reader)?;
+        let data_start = find_data_start(data, &mut self.reader)?;
+        let extra_field_start = self.reader.stream_position()?;
+
+        let mut extra_field = vec![0; (data_start - extra_field_start) as usize];
+        self.reader.read_exact(&mut extra_field)?;
+        Ok(extra_field)
+    }
+
+    /// Get the directory tree of this archive, which supports listing directories, prefix and glob
+    /// queries, and directories that only exist implicitly through the files they contain.
+    ///
+    /// The tree is built from the file names the first time this is called.
+    pub fn tree(&self) -> &DirectoryTree {
+        self.shared.tree.get_or_init(|| match &self.shared.lazy {
+            Some(lazy) => DirectoryTree::build(lazy.names()),
+            None => DirectoryTree::build(self.shared.files.iter().map(|f| f.file_name.as_str())),
+        })
     }
 
     /// Search for a file entry by name, decrypt with given password
@@ -531,12 +877,12 @@ impl<R: Read + io::Seek> ZipArchive<R> {
 
     /// Search for a file entry by name
     pub fn by_name<'a>(&'a mut self, name: &str) -> ZipResult<ZipFile<'a>> {
-        Ok(self.by_name_with_optional_password(name, None, false)?.unwrap())
+        Ok(self.by_name_with_optional_password(name, None, false)??)
     }
 
     /// Search for a file entry by name without decompressing it
     pub fn by_name_raw<'a>(&'a mut self, name: &str) -> ZipResult<ZipFile<'a>> {
-        Ok(self.by_name_with_optional_password(name, None, true)?.unwrap())
+        Ok(self.by_name_with_optional_password(name, None, true)??)
     }
 
     fn by_name_with_optional_password<'a>(
@@ -545,8 +891,8 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         password: Option<&[u8]>,
         raw: bool,
     ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
-        let index = match self.shared.names_map.get(name) {
-            Some(index) => *index,
+        let index = match self.shared.index_of(name) {
+            Some(index) => index,
             None => {
                 return Err(ZipError::FileNotFound);
             }
@@ -577,26 +923,20 @@ impl<R: Read + io::Seek> ZipArchive<R> {
 
     /// Get a contained file by index
     pub fn by_index(&mut self, file_number: usize) -> ZipResult<ZipFile<'_>> {
-        Ok(self
-            .by_index_with_optional_password(file_number, None, false)?
-            .unwrap())
+        Ok(self.by_index_with_optional_password(file_number, None, false)??)
     }
 
     /// Get a contained file by index without decompressing it
     pub fn by_index_raw(&mut self, file_number: usize) -> ZipResult<ZipFile<'_>> {
-        let reader = &mut self.reader;
-        self.shared
-            .files
-            .get(file_number)
-            .ok_or(ZipError::FileNotFound)
-            .and_then(move |data| {
-                Ok(ZipFile {
-                    index: file_number,
-                    crypto_reader: None,
-                    reader: ZipFileReader::Raw(find_content(data, reader)?),
-                    data: Cow::Borrowed(data),
-                })
-            })
+        let data = self.shared.file(file_number, &mut self.reader)?;
+        Ok(ZipFile {
+            index: file_number,
+            crypto_reader: None,
+            reader: ZipFileReader::Raw(find_content(data, &mut self.reader)?),
+            data: Cow::Borrowed(data),
+            remaining_size: None,
+            lenient: None,
+        })
     }
 
     fn by_index_with_optional_password<'a>(
@@ -605,11 +945,7 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         mut password: Option<&[u8]>,
         raw: bool,
     ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
-        let data = self
-            .shared
-            .files
-            .get(file_number)
-            .ok_or(ZipError::FileNotFound)?;
+        let data = self.shared.file(file_number, &mut self.reader)?;
 
         if raw {
             return Ok(Ok(ZipFile {
@@ -617,18 +953,25 @@ impl<R: Read + io::Seek> ZipArchive<R> {
                 crypto_reader: None,
                 reader: ZipFileReader::Raw(find_content(data, &mut self.reader)?),
                 data: Cow::Borrowed(data),
+                remaining_size: None,
+                lenient: None,
             }))
         }
 
+        if data.aes_mode.is_some() && !data.encrypted {
+            return Err(ZipError::InvalidArchive(
+                "AES extra data field on a file that is not encrypted",
+            ));
+        }
         match (password, data.encrypted) {
             (None, true) => return Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)),
             (Some(_), false) => password = None, //Password supplied, but none needed! Discard.
             _ => {}
         }
+        check_compression_method(data)?;
         let limit_reader = find_content(data, &mut self.reader)?;
 
         match make_crypto_reader(
-            data.compression_method,
             data.crc32,
             data.last_modified_time,
             data.using_data_descriptor,
@@ -643,6 +986,14 @@ impl<R: Read + io::Seek> ZipArchive<R> {
                 crypto_reader: Some(crypto_reader),
                 reader: ZipFileReader::NoReader,
                 data: Cow::Borrowed(data),
+                remaining_size: match self.limits.is_set() {
+                    true => Some(data.uncompressed_size),
+                    false => None,
+                },
+                lenient: match self.options.is_lenient() {
+                    true => Some(self.options.clone()),
+                    false => None,
+                },
             })),
             Err(e) => Err(e),
             Ok(Err(e)) => Ok(Err(e)),
@@ -661,16 +1012,22 @@ fn unsupported_zip_error<T>(detail: &'static str) -> ZipResult<T> {
     Err(ZipError::UnsupportedArchive(detail))
 }
 
-/// Parse a central directory entry to collect the information for the file.
+/// Parse a central directory entry to collect the information for the file, checking it according
+/// to `options`.
 pub(crate) fn central_header_to_zip_file<R: Read + io::Seek>(
     reader: &mut R,
     archive_offset: u64,
+    options: &ParseOptions,
 ) -> ZipResult<ZipFileData> {
     let central_header_start = reader.stream_position()?;
     // Parse central header
     let signature = reader.read_u32::<LittleEndian>()?;
     if signature != spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE {
-        return Err(ZipError::InvalidArchive("Invalid Central Directory header"));
+        return Err(ZipError::InvalidSignature {
+            record: "central directory header",
+            offset: central_header_start,
+            found: signature,
+        });
     }
 
     let version_made_by = reader.read_u16::<LittleEndian>()?;
@@ -735,16 +1092,18 @@ pub(crate) fn central_header_to_zip_file<R: Read + io::Seek>(
         aes_mode: None,
     };
 
-    match parse_extra_field(&mut result) {
-        Ok(..) | Err(ZipError::Io(..)) => {}
-        Err(e) => return Err(e),
+    if let Err(error) = parse_extra_field(&mut result) {
+        options.check(Diagnostic::MalformedExtraField {
+            name: result.file_name.clone(),
+            error,
+        })?;
     }
 
     let aes_enabled = result.compression_method == CompressionMethod::AES;
     if aes_enabled && result.aes_mode.is_none() {
-        return Err(ZipError::InvalidArchive(
-            "AES encryption without AES extra data field",
-        ));
+        options.check(Diagnostic::MissingAesExtraField {
+            name: result.file_name.clone(),
+        })?;
     }
 
     // Account for shifted zip offsets.
@@ -753,7 +1112,9 @@ pub(crate) fn central_header_to_zip_file<R: Read + io::Seek>(
         .checked_add(archive_offset)
         .ok_or(ZipError::InvalidArchive("Archive header is too large"))?;
 
-    let magic_and_header = 4 + 22 + 2 + 2 + file_name_length + file_comment_length;
+    // This assumes the local extra field is the same as the central one, which is usually but
+    // not always the case. The exact position is only known once the local header is read.
+    let magic_and_header = 4 + 22 + 2 + 2 + file_name_length + extra_field_length;
     result.data_start.store(result.header_start + magic_and_header as u64);
 
     Ok(result)
@@ -830,29 +1191,70 @@ fn parse_extra_field(file: &mut ZipFileData) -> ZipResult<()> {
 
 /// Methods for retrieving information on zip files
 impl<'a> ZipFile<'a> {
-    fn get_reader(&mut self) -> &mut ZipFileReader<'a> {
+    fn get_reader(&mut self) -> ZipResult<&mut ZipFileReader<'a>> {
         if let ZipFileReader::NoReader = self.reader {
             let data = &self.data;
-            let crypto_reader = self.crypto_reader.take().expect("Invalid reader state");
-            self.reader = make_reader(data.compression_method, data.crc32, crypto_reader)
+            let crypto_reader = self.crypto_reader.take().ok_or_else(invalid_reader_state)?;
+            let on_crc32_mismatch = self.lenient.take().map(|options| {
+                let name = data.file_name.clone();
+                let expected = data.crc32;
+                Box::new(move |actual| {
+                    options.report(&Diagnostic::Crc32Mismatch {
+                        name,
+                        expected,
+                        actual,
+                    })
+                }) as Box<dyn FnOnce(u32)>
+            });
+            self.reader = make_reader(data, crypto_reader, on_crc32_mismatch)?
         }
-        &mut self.reader
+        Ok(&mut self.reader)
     }
 
     pub(crate) fn get_raw_reader(&mut self) -> &mut dyn Read {
         if let ZipFileReader::NoReader = self.reader {
-            let crypto_reader = self.crypto_reader.take().expect("Invalid reader state");
-            self.reader = ZipFileReader::Raw(crypto_reader.into_inner())
+            if let Some(crypto_reader) = self.crypto_reader.take() {
+                self.reader = ZipFileReader::Raw(crypto_reader.into_inner())
+            }
         }
         &mut self.reader
     }
 
+    /// Read the rest of the data of a streamed file, so that the next file can be read.
+    ///
+    /// This is done when the file is dropped, but any error is then ignored and only shows up
+    /// when reading the next file.
+    pub fn exhaust(&mut self) -> ZipResult<()> {
+        // The end of the data is only found by decompressing it when there is a data descriptor.
+        // If the data is invalid, the position of the next file is unknown, and reading it fails.
+        if let ZipFileReader::Descriptor(reader) = &mut self.reader {
+            io::copy(reader, &mut io::sink())?;
+            return Ok(());
+        }
+
+        // Get the inner `Take` reader so all decryption, decompression and CRC calculation is skipped.
+        let mut reader: std::io::Take<&mut dyn std::io::Read> = match &mut self.reader {
+            ZipFileReader::NoReader => match self.crypto_reader.take() {
+                Some(innerreader) => innerreader.into_inner(),
+                None => return Ok(()),
+            },
+            reader => {
+                let innerreader = ::std::mem::replace(reader, ZipFileReader::NoReader);
+                innerreader.into_inner()?
+            }
+        };
+        io::copy(&mut reader, &mut io::sink())?;
+        Ok(())
+    }
+
+    /// Get the metadata of the file, as read from the central directory
+    pub fn metadata(&self) -> FileMetadata<'_> {
+        FileMetadata::new(self.index, &self.data)
+    }
+
     /// Get the version of the file
     pub fn version_made_by(&self) -> (u8, u8) {
-        (
-            self.data.version_made_by / 10,
-            self.data.version_made_by % 10,
-        )
+        self.metadata().version_made_by()
     }
 
     /// Get the index of this file starting from 0 in the archive.
@@ -922,20 +1324,7 @@ impl<'a> ZipFile<'a> {
     /// to path-based exploits. It is recommended over
     /// [`ZipFile::mangled_name`].
     pub fn enclosed_name(&self) -> Option<&Path> {
-        if self.data.file_name.contains('\0') {
-            return None;
-        }
-        let path = Path::new(&self.data.file_name);
-        let mut depth = 0usize;
-        for component in path.components() {
-            match component {
-                Component::Prefix(_) | Component::RootDir => return None,
-                Component::ParentDir => depth = depth.checked_sub(1)?,
-                Component::Normal(_) => depth += 1,
-                Component::CurDir => (),
-            }
-        }
-        Some(path)
+        self.metadata().enclosed_name()
     }
 
     /// Get the comment of the file
@@ -964,11 +1353,7 @@ impl<'a> ZipFile<'a> {
     }
     /// Returns whether the file is actually a directory
     pub fn is_dir(&self) -> bool {
-        self.name()
-            .chars()
-            .rev()
-            .next()
-            .map_or(false, |c| c == '/' || c == '\\')
+        self.metadata().is_dir()
     }
 
     /// Returns whether the file is a regular file
@@ -978,27 +1363,7 @@ impl<'a> ZipFile<'a> {
 
     /// Get unix mode for the file
     pub fn unix_mode(&self) -> Option<u32> {
-        if self.data.external_attributes == 0 {
-            return None;
-        }
-
-        match self.data.system {
-            System::Unix => Some(self.data.external_attributes >> 16),
-            System::Dos => {
-                // Interpret MS-DOS directory bit
-                let mut mode = if 0x10 == (self.data.external_attributes & 0x10) {
-                    ffi::S_IFDIR | 0o0775
-                } else {
-                    ffi::S_IFREG | 0o0664
-                };
-                if 0x01 == (self.data.external_attributes & 0x01) {
-                    // Read-only bit; strip write permissions
-                    mode &= 0o0555;
-                }
-                Some(mode)
-            }
-            _ => None,
-        }
+        self.metadata().unix_mode()
     }
 
     /// Get the CRC32 hash of the original file
@@ -1006,12 +1371,16 @@ impl<'a> ZipFile<'a> {
         self.data.crc32
     }
 
-    /// Get the extra data of the zip header for this file
+    /// Get the extra data of the central directory header for this file.
+    ///
+    /// The extra data of the local header can be read with [`ZipArchive::local_extra_data`].
     pub fn extra_data(&self) -> &[u8] {
         &self.data.extra_field
     }
 
     /// Get the starting offset of the data of the compressed file
+    ///
+    /// This is exact, since the local header has to be read to open the file.
     pub fn data_start(&self) -> u64 {
         self.data.data_start.load()
     }
@@ -1028,7 +1397,22 @@ impl<'a> ZipFile<'a> {
 
 impl<'a> Read for ZipFile<'a> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.get_reader().read(buf)
+        let count = self.get_reader()?.read(buf)?;
+        if let Some(remaining_size) = &mut self.remaining_size {
+            *remaining_size = remaining_size.checked_sub(count as u64).ok_or(
+                ZipError::LimitExceeded("File is larger than its declared size"),
+            )?;
+        }
+        if let ZipFileReader::Descriptor(reader) = &self.reader {
+            // Fill in the values that were missing from the local header
+            if let (0, Some(descriptor)) = (count, reader.descriptor()) {
+                let data = self.data.to_mut();
+                data.crc32 = descriptor.crc32;
+                data.compressed_size = descriptor.compressed_size;
+                data.uncompressed_size = descriptor.uncompressed_size;
+            }
+        }
+        Ok(count)
     }
 }
 
@@ -1036,31 +1420,10 @@ impl<'a> Drop for ZipFile<'a> {
     fn drop(&mut self) {
         // self.data is Owned, this reader is constructed by a streaming reader.
         // In this case, we want to exhaust the reader so that the next file is accessible.
+        // If that fails, the stream is left in the middle of the file and reading the next file
+        // fails instead.
         if let Cow::Owned(_) = self.data {
-            let mut buffer = [0; 1 << 16];
-
-            // Get the inner `Take` reader so all decryption, decompression and CRC calculation is skipped.
-            let mut reader: std::io::Take<&mut dyn std::io::Read> = match &mut self.reader {
-                ZipFileReader::NoReader => {
-                    let innerreader = ::std::mem::replace(&mut self.crypto_reader, None);
-                    innerreader.expect("Invalid reader state").into_inner()
-                }
-                reader => {
-                    let innerreader = ::std::mem::replace(reader, ZipFileReader::NoReader);
-                    innerreader.into_inner()
-                }
-            };
-
-            loop {
-                match reader.read(&mut buffer) {
-                    Ok(0) => break,
-                    Ok(_) => (),
-                    Err(e) => panic!(
-                        "Could not consume all of the output of the current ZipFile: {:?}",
-                        e
-                    ),
-                }
-            }
+            let _ = self.exhaust();
         }
     }
 }
@@ -1077,6 +1440,10 @@ impl<'a> Drop for ZipFile<'a> {
 /// The Drop implementation of ZipFile ensures that the reader will be correctly positioned after
 /// the structure is done.
 ///
+/// Files whose sizes are only given by a data descriptor after their data are supported, but since
+/// their data must not be read past its end, it is read one byte at a time. Use
+/// [`read_zipfile_from_bufread`] to read them efficiently.
+///
 /// Missing fields are:
 /// * `comment`: set to an empty string
 /// * `data_start`: set to 0
@@ -1084,14 +1451,148 @@ impl<'a> Drop for ZipFile<'a> {
 pub fn read_zipfile_from_stream<'a, R: io::Read>(
     reader: &'a mut R,
 ) -> ZipResult<Option<ZipFile<'a>>> {
+    let result = match read_local_header(reader)? {
+        Some(result) => result,
+        None => return Ok(None),
+    };
+    if result.encrypted {
+        return unsupported_zip_error(ZipError::PASSWORD_REQUIRED);
+    }
+
+    // There is no password to be invalid
+    stream_zipfile(result, reader, None, unbuffered_source).map(Result::ok)
+}
+
+/// Read ZipFile structures from a non-seekable reader, decrypting encrypted files.
+///
+/// This works like [`read_zipfile_from_stream`], except that `password` is called with the name
+/// of every encrypted file to get the password to decrypt it with. ZipCrypto and AES encryption
+/// are supported.
+///
+/// If `password` returns `None` or the password is wrong, `Ok(Err(InvalidPassword))` is returned.
+/// The data of the file is skipped in that case, so the next file can be read, unless the file has
+/// a data descriptor, since the end of its data can't be found without decrypting it.
+///
+/// ```no_run
+/// # fn main() -> zip::result::ZipResult<()> {
+/// let mut stdin = std::io::stdin();
+/// while let Ok(Some(file)) = zip::read::read_zipfile_from_stream_decrypt(&mut stdin, |_| Some(b"password"))? {
+///     println!("{}", file.name());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_zipfile_from_stream_decrypt<'a, R: io::Read, P: AsRef<[u8]>>(
+    reader: &'a mut R,
+    password: impl FnOnce(&str) -> Option<P>,
+) -> ZipResult<Result<Option<ZipFile<'a>>, InvalidPassword>> {
+    let result = match read_local_header(reader)? {
+        Some(result) => result,
+        None => return Ok(Ok(None)),
+    };
+    let password = match result.encrypted {
+        true => password(&result.file_name),
+        false => None,
+    };
+
+    let file = stream_zipfile(
+        result,
+        reader,
+        password.as_ref().map(AsRef::as_ref),
+        unbuffered_source,
+    )?;
+    Ok(file.map(Some))
+}
+
+/// Read ZipFile structures from a buffered non-seekable reader.
+///
+/// This works like [`read_zipfile_from_stream`], but files whose sizes are only given by a data
+/// descriptor after their data, as written by Java's `ZipOutputStream` for example, can be read
+/// efficiently. Their data is decompressed until the compressed stream ends, or scanned for the
+/// data descriptor if it is stored, and the data descriptor is checked against the data.
+///
+/// The same reader must be used for every file, since it may have buffered the following ones.
+///
+/// The CRC32 and sizes of such files are 0 until they have been read to the end.
+pub fn read_zipfile_from_bufread<'a, R: io::BufRead>(
+    reader: &'a mut R,
+) -> ZipResult<Option<ZipFile<'a>>> {
+    let result = match read_local_header(reader)? {
+        Some(result) => result,
+        None => return Ok(None),
+    };
+    if result.encrypted {
+        return unsupported_zip_error(ZipError::PASSWORD_REQUIRED);
+    }
+
+    // There is no password to be invalid
+    stream_zipfile(result, reader, None, buffered_source).map(Result::ok)
+}
+
+/// Read ZipFile structures from a buffered non-seekable reader, decrypting encrypted files.
+///
+/// This combines [`read_zipfile_from_bufread`] and [`read_zipfile_from_stream_decrypt`].
+pub fn read_zipfile_from_bufread_decrypt<'a, R: io::BufRead, P: AsRef<[u8]>>(
+    reader: &'a mut R,
+    password: impl FnOnce(&str) -> Option<P>,
+) -> ZipResult<Result<Option<ZipFile<'a>>, InvalidPassword>> {
+    let result = match read_local_header(reader)? {
+        Some(result) => result,
+        None => return Ok(Ok(None)),
+    };
+    let password = match result.encrypted {
+        true => password(&result.file_name),
+        false => None,
+    };
+
+    let file = stream_zipfile(
+        result,
+        reader,
+        password.as_ref().map(AsRef::as_ref),
+        buffered_source,
+    )?;
+    Ok(file.map(Some))
+}
+
+fn unbuffered_source<'a, R: io::Read>(reader: &'a mut R) -> Box<dyn io::BufRead + 'a> {
+    // Bytes past the end of the data can't be put back into `reader`
+    Box::new(io::BufReader::with_capacity(
+        1,
+        reader as &'a mut dyn io::Read,
+    ))
+}
+
+fn buffered_source<'a, R: io::BufRead>(reader: &'a mut R) -> Box<dyn io::BufRead + 'a> {
+    Box::new(reader)
+}
+
+/// Read the local header of a file in a stream, or `None` at the start of the central directory
+fn read_local_header<R: io::Read>(reader: &mut R) -> ZipResult<Option<ZipFileData>> {
     let signature = reader.read_u32::<LittleEndian>()?;
 
     match signature {
-        spec::LOCAL_FILE_HEADER_SIGNATURE => (),
-        spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE => return Ok(None),
-        _ => return Err(ZipError::InvalidArchive("Invalid local file header")),
+        spec::LOCAL_FILE_HEADER_SIGNATURE => read_local_header_fields(reader).map(Some),
+        spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE => Ok(None),
+        _ => Err(ZipError::InvalidArchive("Invalid local file header")),
+    }
+}
+
+/// Read the local header of a file at `offset`
+fn read_local_header_at<R: Read + Seek>(reader: &mut R, offset: u64) -> ZipResult<ZipFileData> {
+    reader.seek(io::SeekFrom::Start(offset))?;
+    let signature = reader.read_u32::<LittleEndian>()?;
+    if signature != spec::LOCAL_FILE_HEADER_SIGNATURE {
+        return Err(ZipError::InvalidSignature {
+            record: "local file header",
+            offset,
+            found: signature,
+        });
     }
+    read_local_header_fields(reader)
+}
 
+/// Read the local header of a file, after its signature
+fn read_local_header_fields<R: io::Read>(reader: &mut R) -> ZipResult<ZipFileData> {
     let version_made_by = reader.read_u16::<LittleEndian>()?;
     let flags = reader.read_u16::<LittleEndian>()?;
     let encrypted = flags & 1 == 1;
@@ -1149,39 +1650,218 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         Ok(..) | Err(ZipError::Io(..)) => {}
         Err(e) => return Err(e),
     }
+    Ok(result)
+}
 
-    if encrypted {
-        return unsupported_zip_error("Encrypted files are not supported");
-    }
-    if using_data_descriptor {
-        return unsupported_zip_error("The file length is not available in the local header");
-    }
+/// Open the data of a file in a stream, after its local header.
+///
+/// `password` must be given if the file is encrypted.
+fn stream_zipfile<'a, R: io::Read>(
+    result: ZipFileData,
+    reader: &'a mut R,
+    password: Option<&[u8]>,
+    into_source: impl FnOnce(&'a mut R) -> Box<dyn io::BufRead + 'a>,
+) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
+    check_compression_method(&result)?;
+    let header_length = match result.encrypted {
+        true => encryption_header_length(&result),
+        false => 0,
+    };
 
-    let limit_reader = (reader as &'a mut dyn io::Read).take(result.compressed_size as u64);
+    if result.using_data_descriptor {
+        let mut source = descriptor::DataSource::new(into_source(reader));
+        match (result.encrypted, password) {
+            (true, Some(password)) => {
+                match StreamDecryptor::new(&result, &mut source, password, None)? {
+                    Ok(decryptor) => source.set_decryptor(decryptor),
+                    Err(e) => return Ok(Err(e)),
+                }
+            }
+            (true, None) => return Ok(Err(InvalidPassword)),
+            (false, _) => {}
+        }
+        let reader = descriptor::DescriptorReader::new(&result, source)?;
+        return Ok(Ok(ZipFile {
+            index: 0,
+            data: Cow::Owned(result),
+            crypto_reader: None,
+            reader: ZipFileReader::Descriptor(Box::new(reader)),
+            remaining_size: None,
+            lenient: None,
+        }));
+    }
+
+    let data_length = result
+        .compressed_size
+        .checked_sub(header_length)
+        .ok_or(ZipError::InvalidArchive("Encrypted file is too small"))?;
+    let decryptor = match (result.encrypted, password) {
+        (true, Some(password)) => {
+            match StreamDecryptor::new(&result, reader, password, Some(data_length))? {
+                Ok(decryptor) => Some(decryptor),
+                Err(e) => {
+                    // Skip the data so that the next file can be read
+                    io::copy(&mut reader.take(data_length), &mut io::sink())?;
+                    return Ok(Err(e));
+                }
+            }
+        }
+        (true, None) => {
+            io::copy(&mut reader.take(result.compressed_size), &mut io::sink())?;
+            return Ok(Err(InvalidPassword));
+        }
+        (false, _) => None,
+    };
 
-    let result_crc32 = result.crc32;
-    let result_compression_method = result.compression_method;
-    let crypto_reader = make_crypto_reader(
-        result_compression_method,
-        result_crc32,
-        result.last_modified_time,
-        result.using_data_descriptor,
-        limit_reader,
-        None,
-        None,
+    let limit_reader = (reader as &'a mut dyn io::Read).take(data_length);
+    let crypto_reader = match decryptor {
+        None => CryptoReader::Plaintext(limit_reader),
+        Some(StreamDecryptor::ZipCrypto(r)) => CryptoReader::ZipCrypto(r.with_reader(limit_reader)),
         #[cfg(feature = "aes-crypto")]
-        result.compressed_size,
-    )?
-    .unwrap();
+        Some(StreamDecryptor::Aes {
+            reader,
+            vendor_version,
+        }) => CryptoReader::Aes {
+            reader: reader.with_reader(limit_reader),
+            vendor_version,
+        },
+    };
 
-    Ok(Some(ZipFile {
+    let reader = make_reader(&result, crypto_reader, None)?;
+    Ok(Ok(ZipFile {
         index: 0,
         data: Cow::Owned(result),
         crypto_reader: None,
-        reader: make_reader(result_compression_method, result_crc32, crypto_reader),
+        reader,
+        remaining_size: None,
+        lenient: None,
     }))
 }
 
+/// Get the length of the header before the encrypted data of a file
+fn encryption_header_length(data: &ZipFileData) -> u64 {
+    match data.aes_mode {
+        #[cfg(feature = "aes-crypto")]
+        Some((aes_mode, _)) => crate::aes::header_length(aes_mode) as u64,
+        _ => 12,
+    }
+}
+
+/// Decryption of the data of a file in a stream, which is split from the reader it decrypts so the
+/// password can be checked before committing to reading the file.
+pub(crate) enum StreamDecryptor {
+    ZipCrypto(ZipCryptoReaderValid<io::Empty>),
+    #[cfg(feature = "aes-crypto")]
+    Aes {
+        reader: AesReaderValid<io::Empty>,
+        vendor_version: AesVendorVersion,
+    },
+}
+
+impl StreamDecryptor {
+    /// Read the encryption header of a file and check the password against it.
+    ///
+    /// `data_length` is the length of the data after the encryption header, if it is known.
+    #[cfg_attr(not(feature = "aes-crypto"), allow(unused_variables))]
+    fn new(
+        data: &ZipFileData,
+        reader: &mut dyn io::Read,
+        password: &[u8],
+        data_length: Option<u64>,
+    ) -> ZipResult<Result<StreamDecryptor, InvalidPassword>> {
+        let mut header = vec![0; encryption_header_length(data) as usize];
+        reader.read_exact(&mut header)?;
+
+        let decryptor = match data.aes_mode {
+            #[cfg(not(feature = "aes-crypto"))]
+            Some(_) => {
+                return Err(ZipError::UnsupportedArchive(
+                    "AES encrypted files cannot be decrypted without the aes-crypto feature.",
+                ))
+            }
+            #[cfg(feature = "aes-crypto")]
+            Some((aes_mode, vendor_version)) => {
+                if data_length.is_some_and(|length| length < crate::aes::AUTH_CODE_LENGTH as u64) {
+                    return Err(ZipError::InvalidArchive("Encrypted file is too small"));
+                }
+                // The length includes the encryption header, but it is also unknown with a data descriptor
+                let compressed_size = match data_length {
+                    Some(data_length) => data_length + header.len() as u64,
+                    None => u64::MAX,
+                };
+                match AesReader::new(&header[..], aes_mode, compressed_size).validate(password)? {
+                    None => return Ok(Err(InvalidPassword)),
+                    Some(r) => StreamDecryptor::Aes {
+                        reader: r.with_reader(io::empty()),
+                        vendor_version,
+                    },
+                }
+            }
+            None => {
+                let validator = if data.using_data_descriptor {
+                    ZipCryptoValidator::InfoZipMsdosTime(data.last_modified_time.timepart())
+                } else {
+                    ZipCryptoValidator::PkzipCrc32(data.crc32)
+                };
+                match ZipCryptoReader::new(&header[..], password).validate(validator)? {
+                    None => return Ok(Err(InvalidPassword)),
+                    Some(r) => StreamDecryptor::ZipCrypto(r.with_reader(io::empty())),
+                }
+            }
+        };
+        Ok(Ok(decryptor))
+    }
+
+    /// Decrypt data that follows the data decrypted so far, in place.
+    pub(crate) fn decrypt(&mut self, buf: &mut [u8]) {
+        match self {
+            StreamDecryptor::ZipCrypto(r) => r.decrypt(buf),
+            #[cfg(feature = "aes-crypto")]
+            StreamDecryptor::Aes { reader, .. } => reader.decrypt(buf),
+        }
+    }
+
+    /// Add encrypted data to the authentication code, once it is known to be part of the file.
+    pub(crate) fn authenticate(&mut self, _data: &[u8]) {
+        #[cfg(feature = "aes-crypto")]
+        if let StreamDecryptor::Aes { reader, .. } = self {
+            reader.authenticate(_data);
+        }
+    }
+
+    /// Length of the authentication code after the encrypted data
+    pub(crate) fn auth_code_length(&self) -> usize {
+        match self {
+            StreamDecryptor::ZipCrypto(_) => 0,
+            #[cfg(feature = "aes-crypto")]
+            StreamDecryptor::Aes { .. } => crate::aes::AUTH_CODE_LENGTH,
+        }
+    }
+
+    /// Check the authentication code after the encrypted data
+    pub(crate) fn check_auth_code(&mut self, _auth_code: &[u8]) -> io::Result<()> {
+        match self {
+            StreamDecryptor::ZipCrypto(_) => Ok(()),
+            #[cfg(feature = "aes-crypto")]
+            StreamDecryptor::Aes { reader, .. } => reader.check_auth_code(_auth_code),
+        }
+    }
+
+    /// Whether the CRC32 is not used, as for AE-2
+    pub(crate) fn is_ae2_encrypted(&self) -> bool {
+        #[cfg(feature = "aes-crypto")]
+        return matches!(
+            self,
+            StreamDecryptor::Aes {
+                vendor_version: AesVendorVersion::Ae2,
+                ..
+            }
+        );
+        #[cfg(not(feature = "aes-crypto"))]
+        false
+    }
+}
+
 #[cfg(test)]
 mod test {
     #[test]
@@ -1336,4 +2016,120 @@ mod test {
         let reader = ZipArchive::new(io::Cursor::new(v));
         assert!(reader.is_err());
     }
+
+    #[test]
+    fn exact_data_offsets() {
+        use super::ZipArchive;
+        use crate::write::{FileOptions, ZipWriter};
+        use crate::CompressionMethod;
+        use std::io::{self, Write};
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("a.txt", options).unwrap();
+        writer.write_all(b"a").unwrap();
+        writer.start_file_aligned("b.so", options, 4096).unwrap();
+        writer.write_all(b"b").unwrap();
+        let v = writer.finish().unwrap().into_inner();
+
+        let mut reader = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        // The padding only exists in the local extra field, so the estimate is off
+        let estimated = reader.entries().nth(1).unwrap().data_start();
+        assert_ne!(estimated % 4096, 0);
+
+        reader.resolve_data_offsets().unwrap();
+        let resolved = reader.entries().map(|e| e.data_start()).collect::<Vec<_>>();
+        assert_eq!(resolved[1] % 4096, 0);
+        for (i, data_start) in resolved.into_iter().enumerate() {
+            assert_eq!(reader.by_index(i).unwrap().data_start(), data_start);
+        }
+
+        assert!(reader.by_index(1).unwrap().extra_data().is_empty());
+        let local_extra_data = reader.local_extra_data(1).unwrap();
+        assert_eq!(&local_extra_data[..2], b"za");
+        assert!(reader.local_extra_data(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn aes_extra_field_without_encryption() {
+        use super::ZipArchive;
+        use crate::result::ZipError;
+        use crate::write::{FileOptions, ZipWriter};
+        use crate::CompressionMethod;
+        use byteorder::{LittleEndian, WriteBytesExt};
+        use std::io::{self, Write};
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file_with_extra_data("a.txt", options).unwrap();
+        writer.write_u16::<LittleEndian>(0xbeef).unwrap();
+        writer.write_u16::<LittleEndian>(7).unwrap();
+        // AE-2, "AE", AES-256, stored
+        writer.write_all(&[2, 0, b'A', b'E', 3, 0, 0]).unwrap();
+        writer.end_extra_data().unwrap();
+        writer.write_all(b"a").unwrap();
+        let mut v = writer.finish().unwrap().into_inner();
+
+        // Turn the placeholder fields into AES extra data, without setting the encryption flag
+        for i in 0..v.len() - 1 {
+            if v[i..i + 2] == [0xef, 0xbe] {
+                v[i..i + 2].copy_from_slice(&[0x01, 0x99]);
+            }
+        }
+
+        let mut reader = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        assert!(matches!(
+            reader.by_index(0),
+            Err(ZipError::InvalidArchive(_))
+        ));
+        assert!(matches!(
+            reader.by_name("a.txt"),
+            Err(ZipError::InvalidArchive(_))
+        ));
+        assert!(matches!(
+            reader.by_index_decrypt(0, b"password"),
+            Err(ZipError::InvalidArchive(_))
+        ));
+    }
+
+    #[test]
+    fn zip_read_streaming_failed_drain() {
+        use super::read_zipfile_from_stream;
+        use crate::write::{FileOptions, ZipWriter};
+        use crate::CompressionMethod;
+        use std::io::{self, Read, Write};
+
+        /// A reader that fails once `limit` bytes were read
+        struct FailAfter(io::Take<io::Cursor<Vec<u8>>>);
+
+        impl Read for FailAfter {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.read(buf)? {
+                    0 => Err(io::Error::other("broken stream")),
+                    count => Ok(count),
+                }
+            }
+        }
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("a.txt", options).unwrap();
+        writer.write_all(&[b'a'; 100]).unwrap();
+        let v = writer.finish().unwrap().into_inner();
+
+        // Cut the stream in the middle of the data of the file
+        let mut reader = FailAfter(io::Cursor::new(v.clone()).take(30 + 5 + 50));
+        let file = read_zipfile_from_stream(&mut reader).unwrap().unwrap();
+        assert_eq!(file.name(), "a.txt");
+        // Dropping the file can't skip the rest of its data, but doesn't panic
+        drop(file);
+        assert!(read_zipfile_from_stream(&mut reader).is_err());
+
+        // Exhausting the file explicitly reports the error
+        let mut reader = FailAfter(io::Cursor::new(v).take(30 + 5 + 50));
+        let mut file = read_zipfile_from_stream(&mut reader).unwrap().unwrap();
+        assert!(file.exhaust().is_err());
+        // The file can't be read anymore, but doesn't panic either
+        assert!(file.read(&mut [0; 10]).is_err());
+    }
 }
@@ -0,0 +1,781 @@
+//! Streaming reads of files whose sizes and CRC32 are only given by a data descriptor after their data
+//!
+//! Since the size of the data is unknown, compressed data is decompressed until the compressed
+//! stream ends, and stored data is scanned for a data descriptor that matches what was read so far.
+//! Either way, the data must not be read past its end, so it is read through a [`BufRead`] of which
+//! only the bytes that are actually used get consumed.
+
+use super::StreamDecryptor;
+use crate::compression::CompressionMethod;
+use crate::result::{ZipError, ZipResult};
+use crate::spec;
+use crate::types::ZipFileData;
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{self, prelude::*};
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+use flate2::bufread::DeflateDecoder;
+
+#[cfg(feature = "bzip2")]
+use bzip2::bufread::BzDecoder;
+
+#[cfg(feature = "zstd")]
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+/// Values of a data descriptor, once it has been read and checked against the data
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DataDescriptor {
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+}
+
+/// Data of a file in a stream, which counts the bytes consumed from it, and decrypts them if needed
+pub(crate) struct DataSource<'a> {
+    inner: Box<dyn BufRead + 'a>,
+    consumed: u64,
+    decryptor: Option<StreamDecryptor>,
+    /// Part of the buffer of `inner` that was returned by `fill_buf`, and its decrypted copy
+    encrypted: Vec<u8>,
+    decrypted: Vec<u8>,
+}
+
+impl<'a> DataSource<'a> {
+    pub(crate) fn new(inner: Box<dyn BufRead + 'a>) -> DataSource<'a> {
+        DataSource {
+            inner,
+            consumed: 0,
+            decryptor: None,
+            encrypted: Vec::new(),
+            decrypted: Vec::new(),
+        }
+    }
+
+    /// Decrypt the data from now on, after the encryption header
+    pub(crate) fn set_decryptor(&mut self, decryptor: StreamDecryptor) {
+        self.decryptor = Some(decryptor);
+    }
+
+    /// Stop decrypting at the end of the encrypted data, and check the authentication code after it
+    fn finish_decryption(&mut self) -> io::Result<()> {
+        if let Some(mut decryptor) = self.decryptor.take() {
+            // Data that was decrypted ahead is not part of the file
+            self.encrypted.clear();
+            self.decrypted.clear();
+            let mut auth_code = vec![0; decryptor.auth_code_length()];
+            self.read_exact(&mut auth_code)?;
+            decryptor.check_auth_code(&auth_code)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Read for DataSource<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl<'a> BufRead for DataSource<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let decryptor = match &mut self.decryptor {
+            Some(decryptor) => decryptor,
+            None => return self.inner.fill_buf(),
+        };
+
+        // Only the bytes that are consumed are part of the file, so `inner` is not consumed yet
+        let chunk = self.inner.fill_buf()?;
+        if chunk.len() > self.encrypted.len() {
+            let start = self.encrypted.len();
+            self.encrypted.extend_from_slice(&chunk[start..]);
+            self.decrypted.extend_from_slice(&chunk[start..]);
+            decryptor.decrypt(&mut self.decrypted[start..]);
+        }
+        Ok(&self.decrypted)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Some(decryptor) = &mut self.decryptor {
+            decryptor.authenticate(&self.encrypted[..amt]);
+            self.encrypted.drain(..amt);
+            self.decrypted.drain(..amt);
+        }
+        self.inner.consume(amt);
+        self.consumed += amt as u64;
+    }
+}
+
+enum Decoder<'a> {
+    Stored(DataSource<'a>),
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    Deflated(DeflateDecoder<DataSource<'a>>),
+    #[cfg(feature = "bzip2")]
+    Bzip2(BzDecoder<DataSource<'a>>),
+    #[cfg(feature = "zstd")]
+    Zstd(ZstdDecoder<'a, DataSource<'a>>),
+}
+
+impl<'a> Decoder<'a> {
+    fn source(&mut self) -> &mut DataSource<'a> {
+        match self {
+            Decoder::Stored(r) => r,
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            Decoder::Deflated(r) => r.get_mut(),
+            #[cfg(feature = "bzip2")]
+            Decoder::Bzip2(r) => r.get_mut(),
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(r) => r.get_mut(),
+        }
+    }
+}
+
+/// Reader for the data of a file that is followed by a data descriptor.
+///
+/// The CRC32 and sizes are checked against the data descriptor at the end of the data, and the
+/// stream is left right after the data descriptor.
+pub(crate) struct DescriptorReader<'a> {
+    decoder: Decoder<'a>,
+    /// Name of the file, for errors
+    name: String,
+    hasher: crc32fast::Hasher,
+    /// Whether the CRC32 is used, which is not the case for AE-2 encryption
+    check_crc32: bool,
+    uncompressed_size: u64,
+    /// Whether the sizes in the data descriptor are 8 bytes long
+    zip64: bool,
+    /// Stored data read while looking for the data descriptor, but not returned yet
+    lookahead: Vec<u8>,
+    descriptor: Option<DataDescriptor>,
+}
+
+impl<'a> DescriptorReader<'a> {
+    pub(crate) fn new(
+        data: &ZipFileData,
+        source: DataSource<'a>,
+    ) -> ZipResult<DescriptorReader<'a>> {
+        let check_crc32 = !source
+            .decryptor
+            .as_ref()
+            .is_some_and(StreamDecryptor::is_ae2_encrypted);
+        let decoder = match data.compression_method {
+            CompressionMethod::Stored if source.decryptor.is_some() => {
+                return Err(ZipError::UnsupportedArchive(
+                    "Encrypted stored files with a data descriptor cannot be streamed",
+                ))
+            }
+            CompressionMethod::Stored => Decoder::Stored(source),
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            CompressionMethod::Deflated => Decoder::Deflated(DeflateDecoder::new(source)),
+            #[cfg(feature = "bzip2")]
+            CompressionMethod::Bzip2 => Decoder::Bzip2(BzDecoder::new(source)),
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => {
+                Decoder::Zstd(ZstdDecoder::with_buffer(source)?.single_frame())
+            }
+            _ => {
+                return Err(ZipError::UnsupportedCompressionMethod {
+                    name: data.file_name.clone(),
+                    method: data.compression_method,
+                })
+            }
+        };
+
+        Ok(DescriptorReader {
+            decoder,
+            name: data.file_name.clone(),
+            hasher: crc32fast::Hasher::new(),
+            check_crc32,
+            uncompressed_size: 0,
+            zip64: data.large_file || has_zip64_extra_field(&data.extra_field),
+            lookahead: Vec::new(),
+            descriptor: None,
+        })
+    }
+
+    /// Get the values of the data descriptor, once all of the data has been read
+    pub(crate) fn descriptor(&self) -> Option<DataDescriptor> {
+        self.descriptor
+    }
+
+    /// Whether a data descriptor for the data read so far has sizes of 8 bytes
+    fn is_zip64(&mut self, compressed_size: u64) -> bool {
+        self.zip64
+            || compressed_size > spec::ZIP64_BYTES_THR
+            || self.uncompressed_size > spec::ZIP64_BYTES_THR
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+        self.uncompressed_size += data.len() as u64;
+    }
+
+    /// Read the data descriptor following compressed data, with or without its signature
+    fn read_descriptor(&mut self) -> io::Result<()> {
+        self.decoder.source().finish_decryption()?;
+        let crc32 = self.hasher.clone().finalize();
+        let compressed_size = self.decoder.source().consumed;
+        let zip64 = self.is_zip64(compressed_size);
+        let source = self.decoder.source();
+
+        let mut descriptor_crc32 = source.read_u32::<LittleEndian>()?;
+        if descriptor_crc32 == spec::DATA_DESCRIPTOR_SIGNATURE && descriptor_crc32 != crc32 {
+            descriptor_crc32 = source.read_u32::<LittleEndian>()?;
+        }
+        let (descriptor_compressed_size, descriptor_uncompressed_size) = match zip64 {
+            true => (
+                source.read_u64::<LittleEndian>()?,
+                source.read_u64::<LittleEndian>()?,
+            ),
+            false => (
+                source.read_u32::<LittleEndian>()? as u64,
+                source.read_u32::<LittleEndian>()? as u64,
+            ),
+        };
+
+        if self.check_crc32 && descriptor_crc32 != crc32 {
+            return Err(ZipError::Crc32Mismatch {
+                name: self.name.clone(),
+                expected: descriptor_crc32,
+                actual: crc32,
+            }
+            .into());
+        }
+        if descriptor_compressed_size != compressed_size
+            || descriptor_uncompressed_size != self.uncompressed_size
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Data descriptor does not match the size of the data",
+            ));
+        }
+        self.descriptor = Some(DataDescriptor {
+            crc32: descriptor_crc32,
+            compressed_size,
+            uncompressed_size: self.uncompressed_size,
+        });
+        Ok(())
+    }
+
+    /// Read stored data up to the next possible start of a data descriptor, which must have a
+    /// signature since nothing else marks the end of the data.
+    fn read_stored(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.lookahead.is_empty() {
+            let source = self.decoder.source();
+            let chunk = source.fill_buf()?;
+            if chunk.is_empty() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let count = descriptor_candidate(chunk).min(buf.len());
+            if count > 0 {
+                buf[..count].copy_from_slice(&chunk[..count]);
+                source.consume(count);
+                self.update(&buf[..count]);
+                return Ok(count);
+            }
+        } else {
+            let count = descriptor_candidate(&self.lookahead).min(buf.len());
+            if count > 0 {
+                buf[..count].copy_from_slice(&self.lookahead[..count]);
+                self.lookahead.drain(..count);
+                self.update(&buf[..count]);
+                return Ok(count);
+            }
+        }
+
+        // A data descriptor might start here, read just enough of it to check
+        let compressed_size = self.uncompressed_size;
+        let size_length = if self.is_zip64(compressed_size) { 8 } else { 4 };
+        let length = 8 + 2 * size_length;
+        let source = self.decoder.source();
+        while self.lookahead.len() < length {
+            let chunk = source.fill_buf()?;
+            if chunk.is_empty() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let count = chunk.len().min(length - self.lookahead.len());
+            self.lookahead.extend_from_slice(&chunk[..count]);
+            source.consume(count);
+        }
+
+        let mut descriptor = &self.lookahead[4..];
+        let crc32 = descriptor.read_u32::<LittleEndian>()?;
+        let (descriptor_compressed_size, descriptor_uncompressed_size) = match size_length {
+            8 => (
+                descriptor.read_u64::<LittleEndian>()?,
+                descriptor.read_u64::<LittleEndian>()?,
+            ),
+            _ => (
+                descriptor.read_u32::<LittleEndian>()? as u64,
+                descriptor.read_u32::<LittleEndian>()? as u64,
+            ),
+        };
+        if self
+            .lookahead
+            .starts_with(&spec::DATA_DESCRIPTOR_SIGNATURE.to_le_bytes())
+            && crc32 == self.hasher.clone().finalize()
+            && descriptor_compressed_size == compressed_size
+            && descriptor_uncompressed_size == compressed_size
+        {
+            self.lookahead.clear();
+            self.descriptor = Some(DataDescriptor {
+                crc32,
+                compressed_size,
+                uncompressed_size: compressed_size,
+            });
+            return Ok(0);
+        }
+
+        // Not a data descriptor after all, so its first byte is part of the data
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.lookahead.remove(0);
+        self.update(&buf[..1]);
+        Ok(1)
+    }
+}
+
+impl<'a> Read for DescriptorReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.descriptor.is_some() || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let count = match &mut self.decoder {
+            Decoder::Stored(_) => None,
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            Decoder::Deflated(r) => Some(r.read(buf)?),
+            #[cfg(feature = "bzip2")]
+            Decoder::Bzip2(r) => Some(r.read(buf)?),
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(r) => Some(r.read(buf)?),
+        };
+        let count = match count {
+            Some(count) => count,
+            None => return self.read_stored(buf),
+        };
+        if count == 0 {
+            self.read_descriptor()?;
+        }
+        self.update(&buf[..count]);
+        Ok(count)
+    }
+}
+
+/// Get the position of the first byte that could start a data descriptor signature
+fn descriptor_candidate(data: &[u8]) -> usize {
+    let signature = spec::DATA_DESCRIPTOR_SIGNATURE.to_le_bytes();
+    (0..data.len())
+        .find(|&i| {
+            let length = (data.len() - i).min(signature.len());
+            data[i..i + length] == signature[..length]
+        })
+        .unwrap_or(data.len())
+}
+
+/// Whether a Zip64 extended information extra field is present, which means that the data
+/// descriptor has 8 byte sizes, even when the sizes in the local header are not set
+fn has_zip64_extra_field(mut extra_field: &[u8]) -> bool {
+    while extra_field.len() >= 4 {
+        let kind = u16::from_le_bytes([extra_field[0], extra_field[1]]);
+        let len = u16::from_le_bytes([extra_field[2], extra_field[3]]) as usize;
+        if kind == 0x0001 {
+            return true;
+        }
+        extra_field = &extra_field[(4 + len).min(extra_field.len())..];
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "deflate")]
+    use crate::read::read_zipfile_from_bufread_decrypt;
+    use crate::read::{
+        read_zipfile_from_bufread, read_zipfile_from_stream, read_zipfile_from_stream_decrypt,
+    };
+    use crate::result::ZipError;
+    use crate::spec;
+    #[cfg(all(feature = "aes-crypto", feature = "deflate"))]
+    use crate::types::{AesMode, AesVendorVersion};
+    #[cfg(any(feature = "deflate", feature = "bzip2"))]
+    use std::io::Write;
+    use std::io::{self, Read};
+
+    const PASSWORD: &[u8] = b"password";
+
+    enum Encryption {
+        ZipCrypto,
+        #[cfg(all(feature = "aes-crypto", feature = "deflate"))]
+        Aes(AesMode, AesVendorVersion),
+    }
+
+    struct Entry<'a> {
+        name: &'a str,
+        method: u16,
+        content: &'a [u8],
+        compressed: Vec<u8>,
+        descriptor: bool,
+        signature: bool,
+        zip64: bool,
+        encryption: Option<Encryption>,
+    }
+
+    impl<'a> Entry<'a> {
+        fn new(name: &'a str, method: u16, content: &'a [u8]) -> Entry<'a> {
+            let compressed = match method {
+                0 => content.to_vec(),
+                #[cfg(feature = "deflate")]
+                8 => {
+                    let mut encoder =
+                        flate2::write::DeflateEncoder::new(Vec::new(), Default::default());
+                    encoder.write_all(content).unwrap();
+                    encoder.finish().unwrap()
+                }
+                #[cfg(feature = "bzip2")]
+                12 => {
+                    let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), Default::default());
+                    encoder.write_all(content).unwrap();
+                    encoder.finish().unwrap()
+                }
+                #[cfg(feature = "zstd")]
+                93 => zstd::encode_all(content, 3).unwrap(),
+                _ => unimplemented!(),
+            };
+            Entry {
+                name,
+                method,
+                content,
+                compressed,
+                descriptor: true,
+                signature: true,
+                zip64: false,
+                encryption: None,
+            }
+        }
+
+        fn encrypted(mut self, encryption: Encryption, descriptor: bool) -> Entry<'a> {
+            self.encryption = Some(encryption);
+            self.descriptor = descriptor;
+            self
+        }
+
+        fn crc32(&self) -> u32 {
+            match self.encryption {
+                #[cfg(all(feature = "aes-crypto", feature = "deflate"))]
+                Some(Encryption::Aes(_, AesVendorVersion::Ae2)) => 0,
+                _ => crc32fast::hash(self.content),
+            }
+        }
+
+        /// The data of the file as stored in the archive
+        fn data(&self) -> Vec<u8> {
+            match self.encryption {
+                None => self.compressed.clone(),
+                Some(Encryption::ZipCrypto) => {
+                    // The last modification time is 0
+                    let check_byte = if self.descriptor {
+                        0
+                    } else {
+                        (self.crc32() >> 24) as u8
+                    };
+                    crate::zipcrypto::encrypt(PASSWORD, check_byte, &self.compressed)
+                }
+                #[cfg(all(feature = "aes-crypto", feature = "deflate"))]
+                Some(Encryption::Aes(aes_mode, _)) => {
+                    crate::aes::encrypt(aes_mode, PASSWORD, &self.compressed)
+                }
+            }
+        }
+
+        fn write(&self, out: &mut Vec<u8>) {
+            let data = self.data();
+            let flags = (self.descriptor as u16) << 3 | self.encryption.is_some() as u16;
+            let (method, aes_extra_field) = match self.encryption {
+                #[cfg(all(feature = "aes-crypto", feature = "deflate"))]
+                Some(Encryption::Aes(aes_mode, vendor_version)) => {
+                    let mut extra_field = vec![0x01, 0x99, 7, 0];
+                    extra_field.extend_from_slice(&(vendor_version as u16 + 1).to_le_bytes());
+                    extra_field.extend_from_slice(b"AE");
+                    extra_field.push(aes_mode as u8 + 1);
+                    extra_field.extend_from_slice(&self.method.to_le_bytes());
+                    (99, extra_field)
+                }
+                _ => (self.method, Vec::new()),
+            };
+
+            out.extend_from_slice(&spec::LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+            out.extend_from_slice(&20u16.to_le_bytes());
+            out.extend_from_slice(&flags.to_le_bytes());
+            out.extend_from_slice(&method.to_le_bytes());
+            out.extend_from_slice(&[0; 4]);
+            if self.descriptor {
+                out.extend_from_slice(&[0; 12]);
+            } else {
+                out.extend_from_slice(&self.crc32().to_le_bytes());
+                out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                out.extend_from_slice(&(self.content.len() as u32).to_le_bytes());
+            }
+            out.extend_from_slice(&(self.name.len() as u16).to_le_bytes());
+            let zip64_extra_field_length = if self.zip64 { 20 } else { 0 };
+            let extra_field_length = zip64_extra_field_length + aes_extra_field.len() as u16;
+            out.extend_from_slice(&extra_field_length.to_le_bytes());
+            out.extend_from_slice(self.name.as_bytes());
+            if self.zip64 {
+                out.extend_from_slice(&[1, 0, 16, 0]);
+                out.extend_from_slice(&[0; 16]);
+            }
+            out.extend_from_slice(&aes_extra_field);
+            out.extend_from_slice(&data);
+
+            if !self.descriptor {
+                return;
+            }
+            if self.signature {
+                out.extend_from_slice(&spec::DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+            }
+            out.extend_from_slice(&self.crc32().to_le_bytes());
+            for size in [data.len(), self.content.len()].iter() {
+                match self.zip64 {
+                    true => out.extend_from_slice(&(*size as u64).to_le_bytes()),
+                    false => out.extend_from_slice(&(*size as u32).to_le_bytes()),
+                }
+            }
+        }
+    }
+
+    fn stream(entries: &[Entry]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for entry in entries {
+            entry.write(&mut out);
+        }
+        out.extend_from_slice(&spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE.to_le_bytes());
+        out
+    }
+
+    fn check(entries: &[Entry]) {
+        let v = stream(entries);
+
+        let mut reader = io::Cursor::new(&v);
+        for entry in entries {
+            let mut file = read_zipfile_from_stream(&mut reader).unwrap().unwrap();
+            assert_eq!(file.name(), entry.name);
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).unwrap();
+            assert_eq!(content, entry.content);
+            assert_eq!(file.size(), entry.content.len() as u64);
+            assert_eq!(file.compressed_size(), entry.data().len() as u64);
+            assert_eq!(file.crc32(), entry.crc32());
+        }
+        assert!(read_zipfile_from_stream(&mut reader).unwrap().is_none());
+
+        // Files that aren't read are skipped over when dropped
+        let mut reader = io::BufReader::with_capacity(7, io::Cursor::new(&v));
+        for (i, entry) in entries.iter().enumerate() {
+            let mut file = read_zipfile_from_bufread(&mut reader).unwrap().unwrap();
+            assert_eq!(file.name(), entry.name);
+            if i % 2 == 1 {
+                let mut content = Vec::new();
+                file.read_to_end(&mut content).unwrap();
+                assert_eq!(content, entry.content);
+            }
+        }
+        assert!(read_zipfile_from_bufread(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn stored() {
+        let mut without_signature = Entry::new("b.txt", 0, b"second");
+        without_signature.signature = false;
+        check(&[
+            Entry::new("a.txt", 0, b"first"),
+            Entry::new("empty.txt", 0, b""),
+            // Data that looks like the start of a data descriptor
+            Entry::new("c.txt", 0, b"PK\x07\x08PK\x07\x08\0\0\0\0\0\0\0\0PK\x07"),
+            Entry::new("d.txt", 0, b"last"),
+        ]);
+
+        // Stored data without a signature cannot be told apart from the data descriptor
+        let mut reader = io::Cursor::new(stream(&[without_signature]));
+        let mut file = read_zipfile_from_stream(&mut reader).unwrap().unwrap();
+        assert!(file.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn deflated() {
+        let content = b"zip".repeat(10_000);
+        let mut without_signature = Entry::new("b.txt", 8, &content);
+        without_signature.signature = false;
+        let mut zip64 = Entry::new("c.txt", 8, b"zip64");
+        zip64.zip64 = true;
+        check(&[
+            Entry::new("a.txt", 8, b"first"),
+            without_signature,
+            zip64,
+            Entry::new("d.txt", 0, b"stored"),
+            Entry::new("empty.txt", 8, b""),
+        ]);
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn bzip2() {
+        let content = b"bzip2".repeat(10_000);
+        check(&[
+            Entry::new("a.txt", 12, &content),
+            Entry::new("b.txt", 12, b"second"),
+        ]);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        let content = b"zstd".repeat(10_000);
+        let mut without_signature = Entry::new("a.txt", 93, &content);
+        without_signature.signature = false;
+        check(&[without_signature, Entry::new("b.txt", 93, b"second")]);
+    }
+
+    #[cfg(feature = "deflate")]
+    fn check_encrypted(entries: &[Entry]) {
+        let v = stream(entries);
+
+        let mut reader = io::Cursor::new(&v);
+        for entry in entries {
+            let mut file = read_zipfile_from_stream_decrypt(&mut reader, |_| Some(PASSWORD))
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            assert_eq!(file.name(), entry.name);
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).unwrap();
+            assert_eq!(content, entry.content);
+        }
+        assert!(read_zipfile_from_stream(&mut reader).unwrap().is_none());
+
+        let mut reader = io::BufReader::with_capacity(7, io::Cursor::new(&v));
+        for (i, entry) in entries.iter().enumerate() {
+            let mut file = read_zipfile_from_bufread_decrypt(&mut reader, |name| {
+                assert_eq!(name, entry.name);
+                Some(PASSWORD.to_vec())
+            })
+            .unwrap()
+            .unwrap()
+            .unwrap();
+            if i % 2 == 1 {
+                let mut content = Vec::new();
+                file.read_to_end(&mut content).unwrap();
+                assert_eq!(content, entry.content);
+            }
+        }
+        assert!(read_zipfile_from_bufread(&mut reader).unwrap().is_none());
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn zipcrypto() {
+        let content = b"zipcrypto".repeat(1000);
+        check_encrypted(&[
+            Entry::new("a.txt", 0, b"sized").encrypted(Encryption::ZipCrypto, false),
+            Entry::new("b.txt", 8, &content).encrypted(Encryption::ZipCrypto, true),
+            Entry::new("c.txt", 8, b"plain"),
+            Entry::new("d.txt", 8, &content).encrypted(Encryption::ZipCrypto, false),
+        ]);
+    }
+
+    #[cfg(all(feature = "aes-crypto", feature = "deflate"))]
+    #[test]
+    fn aes() {
+        let content = b"aes".repeat(1000);
+        check_encrypted(&[
+            Entry::new("a.txt", 8, &content).encrypted(
+                Encryption::Aes(AesMode::Aes128, AesVendorVersion::Ae1),
+                false,
+            ),
+            Entry::new("b.txt", 8, &content).encrypted(
+                Encryption::Aes(AesMode::Aes256, AesVendorVersion::Ae2),
+                true,
+            ),
+            Entry::new("c.txt", 0, b"stored").encrypted(
+                Encryption::Aes(AesMode::Aes192, AesVendorVersion::Ae2),
+                false,
+            ),
+            Entry::new("d.txt", 8, b"descriptor").encrypted(
+                Encryption::Aes(AesMode::Aes128, AesVendorVersion::Ae1),
+                true,
+            ),
+        ]);
+    }
+
+    #[test]
+    fn invalid_password() {
+        let v = stream(&[
+            Entry::new("a.txt", 0, b"first").encrypted(Encryption::ZipCrypto, false),
+            Entry::new("b.txt", 0, b"second").encrypted(Encryption::ZipCrypto, false),
+            Entry::new("c.txt", 0, b"plain"),
+        ]);
+        let mut reader = io::Cursor::new(&v);
+        match read_zipfile_from_stream(&mut reader) {
+            Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => {}
+            _ => panic!("a password should be required"),
+        }
+
+        // Files whose password is wrong or missing are skipped
+        let mut reader = io::Cursor::new(&v);
+        assert!(
+            read_zipfile_from_stream_decrypt(&mut reader, |_| Some(b"wrong"))
+                .unwrap()
+                .is_err()
+        );
+        assert!(
+            read_zipfile_from_stream_decrypt(&mut reader, |_| None::<&[u8]>)
+                .unwrap()
+                .is_err()
+        );
+        let mut file = read_zipfile_from_stream_decrypt(&mut reader, |_| None::<&[u8]>)
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "plain");
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn invalid_descriptor() {
+        let mut v = stream(&[Entry::new("a.txt", 8, b"first")]);
+        // Corrupt the CRC32 in the data descriptor
+        let crc32 = v.len() - 4 - 12;
+        v[crc32] ^= 1;
+        let mut reader = io::Cursor::new(v);
+        let mut file = read_zipfile_from_stream(&mut reader).unwrap().unwrap();
+        let error = file.read_to_end(&mut Vec::new()).unwrap_err();
+        assert!(error.to_string().contains("invalid checksum"));
+    }
+}
@@ -0,0 +1,1357 @@
+//! Configurable extraction of archives to a directory, from seekable or streamed input
+
+#[cfg(feature = "unicode-normalization")]
+use super::names::Names;
+use super::{
+    central_header_to_zip_file, ffi, glob, read_zipfile_from_bufread_decrypt, FileMetadata, Limits,
+    ParseOptions, ZipArchive, ZipFile,
+};
+use crate::result::{InvalidPassword, ZipError, ZipResult};
+use crate::spec;
+use crate::types::DateTime;
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, prelude::*};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What to do when a file to extract already exists
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Replace the existing file
+    Always,
+    /// Keep the existing file, and don't extract the new one
+    Skip,
+    /// Fail the extraction
+    Error,
+    /// Replace the existing file only if it was last modified before the new one
+    IfNewer,
+}
+
+/// What to do with the symlinks in an archive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symlinks {
+    /// Fail the extraction
+    Refuse,
+    /// Create the symlinks, wherever they point to
+    Create,
+    /// Create the symlinks, but fail the extraction if one points outside the destination
+    Contained,
+}
+
+/// What to do with files whose names conflict, see [`ExtractOptions::conflicts`]
+#[cfg(feature = "unicode-normalization")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Conflicts {
+    /// Extract the files as they are, so they may replace each other or fail to be created
+    Ignore,
+    /// Fail the extraction
+    Error,
+    /// Rename the files, replacing the characters FAT doesn't allow and numbering the names that
+    /// conflict with a previous file, like `a (1).png`
+    Rename,
+}
+
+type PathMapper = Arc<dyn Fn(&Path) -> Option<PathBuf> + Send + Sync>;
+
+/// Options for extracting an archive with [`ZipArchive::extract_with`] or [`extract_stream`]
+#[derive(Clone)]
+pub struct ExtractOptions {
+    password: Option<Vec<u8>>,
+    permissions: bool,
+    timestamps: bool,
+    limits: Limits,
+    overwrite: Overwrite,
+    symlinks: Symlinks,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    strip_components: usize,
+    map_path: Option<PathMapper>,
+    #[cfg(feature = "unicode-normalization")]
+    conflicts: Conflicts,
+}
+
+impl Default for ExtractOptions {
+    /// Construct options that extract unencrypted archives, replace existing files, restore unix
+    /// permissions and modification times, and only create symlinks pointing inside the destination
+    fn default() -> ExtractOptions {
+        ExtractOptions {
+            password: None,
+            permissions: true,
+            timestamps: true,
+            limits: Limits::default(),
+            overwrite: Overwrite::Always,
+            symlinks: Symlinks::Contained,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            strip_components: 0,
+            map_path: None,
+            #[cfg(feature = "unicode-normalization")]
+            conflicts: Conflicts::Ignore,
+        }
+    }
+}
+
+impl fmt::Debug for ExtractOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("ExtractOptions");
+        debug
+            .field("permissions", &self.permissions)
+            .field("timestamps", &self.timestamps)
+            .field("limits", &self.limits)
+            .field("overwrite", &self.overwrite)
+            .field("symlinks", &self.symlinks)
+            .field("include", &self.include)
+            .field("exclude", &self.exclude)
+            .field("strip_components", &self.strip_components)
+            .field("map_path", &self.map_path.is_some());
+        #[cfg(feature = "unicode-normalization")]
+        debug.field("conflicts", &self.conflicts);
+        debug.finish_non_exhaustive()
+    }
+}
+
+impl ExtractOptions {
+    /// Set the password used to decrypt encrypted files
+    pub fn password(mut self, password: &[u8]) -> ExtractOptions {
+        self.password = Some(password.to_vec());
+        self
+    }
+
+    /// Set whether the unix permissions of the files are restored, on unix platforms.
+    ///
+    /// This is enabled by default.
+    pub fn permissions(mut self, permissions: bool) -> ExtractOptions {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Set whether the modification times of the files are restored.
+    ///
+    /// Archives don't record the time zone of these times, so they are taken as UTC. This is
+    /// enabled by default.
+    pub fn timestamps(mut self, timestamps: bool) -> ExtractOptions {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Set the limits on the files to extract.
+    ///
+    /// The limits are checked as the files are extracted, and the files extracted before a limit
+    /// is exceeded are left in place.
+    pub fn limits(mut self, limits: Limits) -> ExtractOptions {
+        self.limits = limits;
+        self
+    }
+
+    /// Set what to do when a file already exists. Files are replaced by default.
+    pub fn overwrite(mut self, overwrite: Overwrite) -> ExtractOptions {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Set what to do with symlinks. By default, they are only created if they point inside the
+    /// destination.
+    ///
+    /// On platforms other than unix, symlinks are written as regular files containing their target.
+    pub fn symlinks(mut self, symlinks: Symlinks) -> ExtractOptions {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Only extract the files whose name matches a glob pattern. When called several times, the
+    /// files matching any of the patterns are extracted.
+    ///
+    /// Directories are matched without their trailing `/`, see
+    /// [`DirectoryTree::glob`](super::DirectoryTree::glob) for the supported syntax.
+    pub fn include(mut self, pattern: &str) -> ExtractOptions {
+        self.include.push(pattern.to_owned());
+        self
+    }
+
+    /// Don't extract the files whose name matches a glob pattern, even if they are included
+    pub fn exclude(mut self, pattern: &str) -> ExtractOptions {
+        self.exclude.push(pattern.to_owned());
+        self
+    }
+
+    /// Remove the first `count` components from the paths of the files, like `tar
+    /// --strip-components`. Files with nothing left of their path are not extracted.
+    pub fn strip_components(mut self, count: usize) -> ExtractOptions {
+        self.strip_components = count;
+        self
+    }
+
+    /// Map the path of every file, after [`ExtractOptions::strip_components`] is applied, to the
+    /// path to extract it to, relative to the destination. Files mapped to `None` are not extracted.
+    ///
+    /// The mapped paths may not be absolute or contain `..`.
+    pub fn map_path<F>(mut self, map_path: F) -> ExtractOptions
+    where
+        F: Fn(&Path) -> Option<PathBuf> + Send + Sync + 'static,
+    {
+        self.map_path = Some(Arc::new(map_path));
+        self
+    }
+
+    /// Set what to do with files whose names conflict with each other or can't be created on
+    /// FAT and exFAT file systems, as reported by [`ZipArchive::analyze_names`]. Such files are
+    /// extracted as they are by default.
+    ///
+    /// The names are checked after [`ExtractOptions::strip_components`] and
+    /// [`ExtractOptions::map_path`] are applied.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn conflicts(mut self, conflicts: Conflicts) -> ExtractOptions {
+        self.conflicts = conflicts;
+        self
+    }
+
+    fn is_selected(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('/');
+        (self.include.is_empty() || self.include.iter().any(|p| glob::matches(p, name)))
+            && !self.exclude.iter().any(|p| glob::matches(p, name))
+    }
+}
+
+impl<R: Read + io::Seek> ZipArchive<R> {
+    /// Extract the archive into a directory, as configured by `options`.
+    ///
+    /// Unlike [`ZipArchive::extract`], no file is ever written through a symlink leading outside
+    /// of the destination, whether it already existed or was extracted from the archive. The
+    /// permissions and modification times of directories are restored once all files are written.
+    ///
+    /// ```no_run
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// use zip::read::{ExtractOptions, Overwrite};
+    ///
+    /// let mut archive = zip::ZipArchive::new(std::fs::File::open("content.zip")?)?;
+    /// let options = ExtractOptions::default()
+    ///     .include("assets/**")
+    ///     .strip_components(1)
+    ///     .overwrite(Overwrite::IfNewer);
+    /// archive.extract_with("content", options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_with<P: AsRef<Path>>(
+        &mut self,
+        directory: P,
+        options: ExtractOptions,
+    ) -> ZipResult<()> {
+        let mut extractor = Extractor::new(directory.as_ref(), &options)?;
+        for i in 0..self.len() {
+            let mut file = self.open_for_extraction(i, &options)?;
+            let mode = file.unix_mode();
+            extractor.extract(&mut file, Some(mode))?;
+        }
+        extractor.finish(self.entries())
+    }
+
+    /// Extract the archive into a directory like [`ZipArchive::extract_with`], decompressing the
+    /// files on `threads` threads, each reading from its own clone of the archive.
+    ///
+    /// Directories and symlinks are created first, in archive order, then the other files are
+    /// extracted in parallel. A file that can't be extracted doesn't stop the extraction, the
+    /// errors are reported for each file instead. This only fails if the destination can't be
+    /// created, the archive has more files than allowed by the limits, or restoring the
+    /// permissions and modification times fails.
+    ///
+    /// ```no_run
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let data = std::fs::read("assets.zip")?;
+    /// let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data.as_slice()))?;
+    /// let report = archive.extract_parallel("assets", Default::default(), 8)?;
+    /// for file in report.failed() {
+    ///     eprintln!("{}: {}", file.name(), file.error());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_parallel<P: AsRef<Path>>(
+        &mut self,
+        directory: P,
+        options: ExtractOptions,
+        threads: usize,
+    ) -> ZipResult<ExtractReport>
+    where
+        R: Clone + Send,
+    {
+        options.limits.check_entries(self.len())?;
+        self.decode_metadata()?;
+        let mut extractor = Extractor::new(directory.as_ref(), &options)?;
+        let mut failed = Vec::new();
+
+        // The paths are found in archive order, so that conflicting names are renamed the same
+        // way as when extracting sequentially
+        let mut entries = Vec::with_capacity(self.len());
+        for metadata in self.entries() {
+            let path = match extractor.output_path(metadata.index(), &metadata) {
+                Ok(path) => path,
+                Err(error) => {
+                    failed.push(FailedEntry {
+                        index: metadata.index(),
+                        name: metadata.name().to_owned(),
+                        error,
+                    });
+                    None
+                }
+            };
+            let is_symlink = metadata.unix_mode().is_some_and(is_symlink);
+            let sequential = metadata.is_dir() || is_symlink;
+            entries.push((metadata.name().to_owned(), path, sequential));
+        }
+        let mut record = |extractor: &mut Extractor, index: usize, result| match result {
+            Ok(Some(path)) => extractor.record(&entries[index].0, path, false),
+            Ok(None) => {}
+            Err(error) => failed.push(FailedEntry {
+                index,
+                name: entries[index].0.clone(),
+                error,
+            }),
+        };
+
+        // No file is written before the symlinks it could be written through exist
+        let mut files = Vec::new();
+        for (index, (_, path, sequential)) in entries.iter().enumerate() {
+            let path = match path {
+                Some(path) => path,
+                None => continue,
+            };
+            if !sequential {
+                files.push((index, path));
+                continue;
+            }
+            let result = self
+                .open_for_extraction(index, &options)
+                .and_then(|mut file| {
+                    let mode = file.unix_mode();
+                    extractor.write(&mut file, Some(mode), path.clone())
+                });
+            record(&mut extractor, index, result);
+        }
+
+        let next = AtomicUsize::new(0);
+        let threads = threads.clamp(1, files.len().max(1));
+        let results = std::thread::scope(|scope| {
+            let workers = (0..threads)
+                .map(|_| {
+                    let mut archive = self.clone();
+                    let (extractor, options, files, next) = (&extractor, &options, &files, &next);
+                    scope.spawn(move || {
+                        let mut results = Vec::new();
+                        while let Some(&(index, path)) =
+                            files.get(next.fetch_add(1, Ordering::Relaxed))
+                        {
+                            let result =
+                                archive
+                                    .open_for_extraction(index, options)
+                                    .and_then(|mut file| {
+                                        let mode = file.unix_mode();
+                                        extractor.write(&mut file, Some(mode), path.clone())
+                                    });
+                            results.push((index, result));
+                        }
+                        results
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                })
+                .collect::<Vec<_>>()
+        });
+        for (index, result) in results {
+            record(&mut extractor, index, result);
+        }
+
+        failed.sort_by_key(|file| file.index);
+        extractor.finish(self.entries())?;
+        Ok(ExtractReport { failed })
+    }
+
+    /// Open a file to extract, decrypting it if a password is set
+    fn open_for_extraction(
+        &mut self,
+        index: usize,
+        options: &ExtractOptions,
+    ) -> ZipResult<ZipFile<'_>> {
+        match &options.password {
+            Some(password) => Ok(self.by_index_decrypt(index, password)??),
+            None => self.by_index(index),
+        }
+    }
+}
+
+/// The outcome of extracting an archive with [`ZipArchive::extract_parallel`]
+#[derive(Debug)]
+pub struct ExtractReport {
+    failed: Vec<FailedEntry>,
+}
+
+impl ExtractReport {
+    /// Whether every file was extracted
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// The files that couldn't be extracted, in archive order
+    pub fn failed(&self) -> &[FailedEntry] {
+        &self.failed
+    }
+}
+
+/// A file that couldn't be extracted
+#[derive(Debug)]
+pub struct FailedEntry {
+    index: usize,
+    name: String,
+    error: ZipError,
+}
+
+impl FailedEntry {
+    /// Get the index of the file in the archive
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the name of the file
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the error that prevented extracting the file
+    pub fn error(&self) -> &ZipError {
+        &self.error
+    }
+}
+
+/// Extract an archive from a non-seekable reader into a directory, as it is read.
+///
+/// This works like [`ZipArchive::extract_with`], and files whose data is followed by a data
+/// descriptor are supported. Since the unix modes of the files are only stored in the central
+/// directory, at the end of the archive, symlinks are first written as regular files, and
+/// permissions are restored once all files have been written.
+///
+/// `reader` is buffered internally, so it should not be buffered already.
+///
+/// ```no_run
+/// # fn main() -> zip::result::ZipResult<()> {
+/// zip::read::extract_stream(std::io::stdin(), "content", zip::read::ExtractOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn extract_stream<R: Read, P: AsRef<Path>>(
+    reader: R,
+    directory: P,
+    options: ExtractOptions,
+) -> ZipResult<()> {
+    let mut extractor = Extractor::new(directory.as_ref(), &options)?;
+    let mut reader = io::BufReader::new(reader);
+
+    loop {
+        let file = read_zipfile_from_bufread_decrypt(&mut reader, |_| options.password.as_deref())?;
+        let mut file = match file {
+            Ok(Some(file)) => file,
+            Ok(None) => break,
+            Err(InvalidPassword) if options.password.is_none() => {
+                return Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED))
+            }
+            Err(InvalidPassword) => return Err(ZipError::InvalidPassword),
+        };
+        extractor.extract(&mut file, None)?;
+    }
+
+    // The signature of the first central directory header was read to find the end of the files.
+    // The headers are read one at a time, so the rest of the stream is never buffered as a whole.
+    let mut files = Vec::new();
+    loop {
+        let header = read_central_header(&mut reader)?;
+        files.push(central_header_to_zip_file(
+            &mut io::Cursor::new(header),
+            0,
+            &ParseOptions::default(),
+        )?);
+        options.limits.check_entries(files.len())?;
+        match reader.read_u32::<LittleEndian>() {
+            Ok(spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE) => {}
+            Ok(_) => break,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    extractor.finish(
+        files
+            .iter()
+            .enumerate()
+            .map(|(index, data)| FileMetadata::new(index, data)),
+    )
+}
+
+/// Read a central directory header whose signature was already read, including the signature
+fn read_central_header<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut header = spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE
+        .to_le_bytes()
+        .to_vec();
+    header.resize(46, 0);
+    reader.read_exact(&mut header[4..])?;
+    // The file name, extra field and comment follow the fixed size part
+    let variable_length = [28, 30, 32]
+        .iter()
+        .map(|&offset| LittleEndian::read_u16(&header[offset..]) as usize)
+        .sum::<usize>();
+    header.resize(46 + variable_length, 0);
+    reader.read_exact(&mut header[46..])?;
+    Ok(header)
+}
+
+/// Where a file was extracted to
+struct Extracted {
+    path: PathBuf,
+    /// Whether it was written as a regular file because its unix mode wasn't known yet
+    mode_pending: bool,
+}
+
+/// The state of an extraction, common to seekable and streamed archives
+struct Extractor<'a> {
+    options: &'a ExtractOptions,
+    /// The destination, with all symlinks resolved
+    root: PathBuf,
+    entries: usize,
+    /// The size of the data written so far, which workers extracting in parallel add to
+    total_size: AtomicU64,
+    /// The extracted files by name
+    extracted: HashMap<String, Extracted>,
+    /// The paths used so far, to find conflicting names
+    #[cfg(feature = "unicode-normalization")]
+    names: Names,
+}
+
+impl<'a> Extractor<'a> {
+    fn new(directory: &Path, options: &'a ExtractOptions) -> ZipResult<Extractor<'a>> {
+        fs::create_dir_all(directory)?;
+        Ok(Extractor {
+            options,
+            root: directory.canonicalize()?,
+            entries: 0,
+            total_size: AtomicU64::new(0),
+            extracted: HashMap::new(),
+            #[cfg(feature = "unicode-normalization")]
+            names: Names::default(),
+        })
+    }
+
+    /// Extract a file, given its unix mode if it is known at this point
+    fn extract(&mut self, file: &mut ZipFile, mode: Option<Option<u32>>) -> ZipResult<()> {
+        self.entries += 1;
+        self.options.limits.check_entries(self.entries)?;
+        let outpath = match self.output_path(self.entries - 1, &file.metadata())? {
+            Some(outpath) => outpath,
+            None => return Ok(()),
+        };
+        if let Some(path) = self.write(file, mode, outpath)? {
+            let mode_pending = mode.is_none() && !file.is_dir();
+            self.record(file.name(), path, mode_pending);
+        }
+        Ok(())
+    }
+
+    /// Write a file to the destination, and return where it was written if it was
+    fn write(
+        &self,
+        file: &mut ZipFile,
+        mode: Option<Option<u32>>,
+        outpath: PathBuf,
+    ) -> ZipResult<Option<PathBuf>> {
+        if file.is_dir() {
+            self.check_contained(&outpath)?;
+            fs::create_dir_all(&outpath)?;
+        } else {
+            if let Some(parent) = outpath.parent() {
+                self.check_contained(parent)?;
+                fs::create_dir_all(parent)?;
+            }
+            if !self.replace_existing(&outpath, file.last_modified())? {
+                return Ok(None);
+            }
+
+            let limits = &self.options.limits;
+            if mode.flatten().is_some_and(is_symlink) {
+                let mut target = Vec::new();
+                copy_limited(file, &mut target, limits, &self.total_size)?;
+                self.create_symlink(&outpath, &target)?;
+            } else {
+                let mut outfile = fs::File::create(&outpath)?;
+                copy_limited(file, &mut outfile, limits, &self.total_size)?;
+            }
+        }
+        Ok(Some(outpath))
+    }
+
+    fn record(&mut self, name: &str, path: PathBuf, mode_pending: bool) {
+        let extracted = Extracted { path, mode_pending };
+        self.extracted.insert(name.to_owned(), extracted);
+    }
+
+    /// Get the path to extract the file at `index` to, or `None` if it is not extracted. This
+    /// must be called for the files in archive order, so that conflicting names are renamed
+    /// consistently.
+    #[cfg_attr(not(feature = "unicode-normalization"), allow(unused_variables))]
+    fn output_path(&mut self, index: usize, file: &FileMetadata) -> ZipResult<Option<PathBuf>> {
+        if !self.options.is_selected(file.name()) {
+            return Ok(None);
+        }
+        let path = file
+            .enclosed_name()
+            .ok_or(ZipError::InvalidArchive("Invalid file path"))?
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .skip(self.options.strip_components)
+            .collect::<PathBuf>();
+        if path.as_os_str().is_empty() {
+            return Ok(None);
+        }
+
+        let path = match &self.options.map_path {
+            Some(map_path) => match map_path(&path) {
+                Some(path) => path,
+                None => return Ok(None),
+            },
+            None => path,
+        };
+        let is_relative = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !is_relative {
+            return Err(ZipError::InvalidArchive("Invalid file path"));
+        }
+
+        #[cfg(feature = "unicode-normalization")]
+        let path = match self.options.conflicts {
+            Conflicts::Ignore => path,
+            Conflicts::Error => {
+                let mut issues = Vec::new();
+                self.names
+                    .check(index, &path_to_name(&path), file.is_dir(), &mut issues);
+                if !issues.is_empty() {
+                    return Err(ZipError::InvalidArchive("File names conflict"));
+                }
+                path
+            }
+            Conflicts::Rename => PathBuf::from(self.names.rename(
+                index,
+                &path_to_name(&path),
+                file.is_dir(),
+            )),
+        };
+        Ok(Some(self.root.join(path)))
+    }
+
+    /// Make sure that a path doesn't lead outside the destination through a symlink
+    fn check_contained(&self, path: &Path) -> ZipResult<()> {
+        match resolve(path)?.starts_with(&self.root) {
+            true => Ok(()),
+            false => Err(ZipError::InvalidArchive(
+                "Path leads outside the destination through a symlink",
+            )),
+        }
+    }
+
+    /// Apply the overwrite policy to the file at `path`, returning whether it should be written
+    fn replace_existing(&self, path: &Path, last_modified: DateTime) -> ZipResult<bool> {
+        let existing = match fs::symlink_metadata(path) {
+            Ok(existing) => existing,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+            Err(e) => return Err(e.into()),
+        };
+
+        let replace = match self.options.overwrite {
+            Overwrite::Always => true,
+            Overwrite::Skip => false,
+            Overwrite::Error => {
+                return Err(
+                    io::Error::new(io::ErrorKind::AlreadyExists, "File already exists").into(),
+                )
+            }
+            Overwrite::IfNewer => match (system_time(last_modified), existing.modified()) {
+                (Some(new), Ok(existing)) => new > existing,
+                _ => true,
+            },
+        };
+        // Never write through an existing symlink
+        if replace && existing.file_type().is_symlink() {
+            fs::remove_file(path)?;
+        }
+        Ok(replace)
+    }
+
+    fn create_symlink(&self, path: &Path, target: &[u8]) -> ZipResult<()> {
+        let target = std::str::from_utf8(target)
+            .map_err(|_| ZipError::InvalidArchive("Invalid symlink target"))?;
+        match self.options.symlinks {
+            Symlinks::Refuse => {
+                return Err(ZipError::UnsupportedArchive("Symlinks are not allowed"));
+            }
+            Symlinks::Create => {}
+            Symlinks::Contained => {
+                let parent = path.parent().unwrap_or(&self.root);
+                if !resolve(&parent.join(target))?.starts_with(&self.root) {
+                    return Err(ZipError::InvalidArchive(
+                        "Symlink points outside the destination",
+                    ));
+                }
+            }
+        }
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(target, path)?;
+        #[cfg(not(unix))]
+        fs::write(path, target)?;
+        Ok(())
+    }
+
+    /// Turn the files written before their unix mode was known into symlinks where needed, then
+    /// restore the permissions and modification times of all extracted files.
+    ///
+    /// Directories are done last and deepest first, so writing their files neither changes their
+    /// modification time nor is prevented by their permissions.
+    fn finish<'b>(mut self, files: impl Iterator<Item = FileMetadata<'b>>) -> ZipResult<()> {
+        let mut directories = Vec::new();
+        for metadata in files {
+            let extracted = match self.extracted.remove(metadata.name()) {
+                Some(extracted) => extracted,
+                None => continue,
+            };
+            let mode = metadata.unix_mode();
+            if mode.is_some_and(is_symlink) {
+                if extracted.mode_pending {
+                    let target = fs::read(&extracted.path)?;
+                    fs::remove_file(&extracted.path)?;
+                    self.create_symlink(&extracted.path, &target)?;
+                }
+                continue;
+            }
+
+            match metadata.is_dir() {
+                true => directories.push((extracted.path, mode, metadata.last_modified())),
+                false => self.restore_metadata(&extracted.path, mode, metadata.last_modified())?,
+            }
+        }
+
+        directories.sort_by(|(a, _, _), (b, _, _)| b.cmp(a));
+        for (path, mode, last_modified) in directories {
+            self.restore_metadata(&path, mode, last_modified)?;
+        }
+        Ok(())
+    }
+
+    fn restore_metadata(
+        &self,
+        path: &Path,
+        mode: Option<u32>,
+        last_modified: DateTime,
+    ) -> ZipResult<()> {
+        if self.options.timestamps {
+            if let Some(time) = system_time(last_modified) {
+                set_modified(path, time)?;
+            }
+        }
+        if let (true, Some(mode)) = (self.options.permissions, mode) {
+            set_mode(path, mode)?;
+        }
+        Ok(())
+    }
+}
+
+/// Copy the data of a file, failing as soon as it exceeds the limits
+fn copy_limited(
+    file: &mut ZipFile,
+    outfile: &mut impl Write,
+    limits: &Limits,
+    total_size: &AtomicU64,
+) -> ZipResult<()> {
+    // The sizes are only known once the file has been read if it has a data descriptor
+    let declared_size = match file.metadata().using_data_descriptor() {
+        true => None,
+        false => {
+            limits.check_size(file.compressed_size(), file.size())?;
+            limits.is_set().then_some(file.size())
+        }
+    };
+
+    let mut buf = [0; 1 << 16];
+    let mut size = 0;
+    loop {
+        let count = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(count) => count,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        size += count as u64;
+        if declared_size.is_some_and(|declared_size| size > declared_size) {
+            return Err(ZipError::LimitExceeded(
+                "File is larger than its declared size",
+            ));
+        }
+        limits.check_entry_size(size)?;
+        // Counted as it is read, since other threads may be extracting files at the same time
+        let total = total_size.fetch_add(count as u64, Ordering::Relaxed) + count as u64;
+        limits.check_total_size(total)?;
+        outfile.write_all(&buf[..count])?;
+    }
+
+    limits.check_size(file.compressed_size(), size)
+}
+
+/// Join the components of a relative path with `/`, like the names in an archive
+#[cfg(feature = "unicode-normalization")]
+fn path_to_name(path: &Path) -> String {
+    let components = path.components().filter_map(|component| match component {
+        Component::Normal(component) => Some(component.to_string_lossy()),
+        _ => None,
+    });
+    components.collect::<Vec<_>>().join("/")
+}
+
+/// Resolve a path like the file system would, following the symlinks in the part of it that exists
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            component => {
+                resolved.push(component);
+                if fs::symlink_metadata(&resolved).is_ok() {
+                    resolved = resolved.canonicalize()?;
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+fn is_symlink(mode: u32) -> bool {
+    mode & ffi::S_IFMT == ffi::S_IFLNK
+}
+
+/// Convert the modification time of a file to a system time, taking it as UTC
+fn system_time(datetime: DateTime) -> Option<SystemTime> {
+    let (year, month, day) = (
+        datetime.year() as u64,
+        datetime.month() as u64,
+        datetime.day() as u64,
+    );
+    if !(1..=12).contains(&month) || day == 0 {
+        return None;
+    }
+
+    // Count the days since 1970-01-01 with years starting in March, so leap days end them
+    let (year, month) = match month {
+        1 | 2 => (year - 1, month + 9),
+        _ => (year, month - 3),
+    };
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let days = year * 365 + year / 4 - year / 100 + year / 400 + day_of_year - 719_468;
+
+    let seconds = days * 86400
+        + datetime.hour() as u64 * 3600
+        + datetime.minute() as u64 * 60
+        + datetime.second() as u64;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+#[cfg(unix)]
+fn set_modified(path: &Path, time: SystemTime) -> ZipResult<()> {
+    fs::File::open(path)?.set_modified(time)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_modified(path: &Path, time: SystemTime) -> ZipResult<()> {
+    // Directories can't be opened as files
+    if !path.is_dir() {
+        fs::OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_modified(time)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> ZipResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> ZipResult<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "unicode-normalization")]
+    use super::Conflicts;
+    use super::{extract_stream, system_time, ExtractOptions, Limits, Overwrite, Symlinks};
+    use crate::read::test_util::zip;
+    use crate::result::{ZipError, ZipResult};
+    use crate::write::FileOptions;
+    use crate::{CompressionMethod, DateTime, ZipArchive, ZipWriter};
+    use std::fs;
+    use std::io::{self, Read, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Barrier};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn archive() -> Vec<u8> {
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .unix_permissions(0o600);
+        zip(&[
+            ("dir/", b"", options.unix_permissions(0o700)),
+            ("dir/a.txt", b"first", options),
+            ("b/c.txt", b"second", options.unix_permissions(0o755)),
+        ])
+    }
+
+    fn directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("zip-extract-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn extract_with(v: &[u8], directory: &Path, options: ExtractOptions) -> ZipResult<()> {
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        archive.extract_with(directory, options)
+    }
+
+    #[test]
+    fn extract() {
+        let directory = directory("extract");
+        extract_stream(archive().as_slice(), &directory, ExtractOptions::default()).unwrap();
+
+        assert_eq!(fs::read(directory.join("dir/a.txt")).unwrap(), b"first");
+        assert_eq!(fs::read(directory.join("b/c.txt")).unwrap(), b"second");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path| {
+                fs::metadata(directory.join(path))
+                    .unwrap()
+                    .permissions()
+                    .mode()
+            };
+            assert_eq!(mode("dir/a.txt") & 0o777, 0o600);
+            assert_eq!(mode("b/c.txt") & 0o777, 0o755);
+            assert_eq!(mode("dir") & 0o777, 0o700);
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn invalid_path() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .start_file("../escape.txt", FileOptions::default())
+            .unwrap();
+        let v = writer.finish().unwrap().into_inner();
+
+        let directory = directory("invalid");
+        match extract_stream(v.as_slice(), &directory, ExtractOptions::default()) {
+            Err(ZipError::InvalidArchive("Invalid file path")) => {}
+            _ => panic!("the path should be rejected"),
+        }
+        assert!(!directory.join("../escape.txt").exists());
+    }
+
+    #[test]
+    fn limits() {
+        let v = archive();
+        let extract = |limits| {
+            let directory = directory("limits");
+            let result = extract_stream(
+                v.as_slice(),
+                &directory,
+                ExtractOptions::default().limits(limits),
+            );
+            let _ = fs::remove_dir_all(&directory);
+            result
+        };
+
+        match extract(Limits::default().max_entries(2)) {
+            Err(ZipError::LimitExceeded("Too many files")) => {}
+            _ => panic!("there should be too many files"),
+        }
+        match extract(Limits::default().max_total_size(10)) {
+            Err(ZipError::LimitExceeded("Files are too large in total")) => {}
+            _ => panic!("the files should be too large"),
+        }
+        extract(Limits::default().max_entries(3).max_total_size(11)).unwrap();
+    }
+
+    #[test]
+    fn wrong_password() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("a.txt", options).unwrap();
+        let data = crate::zipcrypto::encrypt(b"password", 0, b"data");
+        writer.write_all(&data).unwrap();
+        let mut v = writer.finish().unwrap().into_inner();
+        // Mark the file as encrypted in both of its headers
+        let local = v.windows(4).position(|w| w == b"PK\x03\x04").unwrap();
+        v[local + 6] |= 1;
+        let central = v.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        v[central + 8] |= 1;
+
+        let directory = directory("password");
+        let options = ExtractOptions::default().password(b"wrong");
+        let mut archive = ZipArchive::new(io::Cursor::new(v.as_slice())).unwrap();
+        match archive.extract_with(&directory, options.clone()) {
+            Err(ZipError::InvalidPassword) => {}
+            _ => panic!("the password should be rejected"),
+        }
+        let report = archive
+            .extract_parallel(&directory, options.clone(), 2)
+            .unwrap();
+        assert!(matches!(
+            report.failed()[0].error(),
+            ZipError::InvalidPassword
+        ));
+        match extract_stream(v.as_slice(), &directory, options) {
+            Err(ZipError::InvalidPassword) => {}
+            _ => panic!("the password should be rejected"),
+        }
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn endless_stream() {
+        let v = archive();
+        let directory = directory("endless");
+        // The data after the central directory is never read
+        let stream = v.as_slice().chain(io::repeat(0));
+        extract_stream(stream, &directory, ExtractOptions::default()).unwrap();
+        assert_eq!(fs::read(directory.join("dir/a.txt")).unwrap(), b"first");
+
+        // Central directory headers are counted as they are read
+        let headers: Vec<_> = v
+            .windows(4)
+            .enumerate()
+            .filter(|(_, w)| *w == b"PK\x01\x02")
+            .map(|(i, _)| i)
+            .collect();
+        let mut stream = v[..headers[1]].to_vec();
+        for _ in 0..100 {
+            stream.extend_from_slice(&v[headers[0]..headers[1]]);
+        }
+        let options = ExtractOptions::default().limits(Limits::default().max_entries(3));
+        match extract_stream(stream.as_slice(), &directory, options) {
+            Err(ZipError::LimitExceeded("Too many files")) => {}
+            _ => panic!("there should be too many files"),
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn filter_and_map_paths() {
+        let directory = directory("filter");
+        let options = ExtractOptions::default()
+            .include("dir/**")
+            .include("b/*")
+            .exclude("*/a.txt")
+            .strip_components(1)
+            .map_path(|path| Some(Path::new("mapped").join(path)));
+        extract_with(&archive(), &directory, options).unwrap();
+
+        assert_eq!(fs::read(directory.join("mapped/c.txt")).unwrap(), b"second");
+        assert!(!directory.join("mapped/a.txt").exists());
+        assert!(!directory.join("dir").exists());
+
+        let options = ExtractOptions::default().map_path(|_| Some(PathBuf::from("../escape")));
+        match extract_with(&archive(), &directory, options) {
+            Err(ZipError::InvalidArchive("Invalid file path")) => {}
+            _ => panic!("the mapped path should be rejected"),
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn overwrite() {
+        let directory = directory("overwrite");
+        let path = directory.join("dir/a.txt");
+        let extract = |overwrite| {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, b"existing").unwrap();
+            let options = ExtractOptions::default()
+                .overwrite(overwrite)
+                .permissions(false);
+            let result = extract_with(&archive(), &directory, options);
+            (result, fs::read(&path).unwrap())
+        };
+
+        let (result, content) = extract(Overwrite::Skip);
+        assert!(result.is_ok());
+        assert_eq!(content, b"existing");
+        assert!(extract(Overwrite::Error).0.is_err());
+        let (result, content) = extract(Overwrite::Always);
+        assert!(result.is_ok());
+        assert_eq!(content, b"first");
+        // The existing file was just written, after the file in the archive was last modified
+        let (result, content) = extract(Overwrite::IfNewer);
+        assert!(result.is_ok());
+        assert_eq!(content, b"existing");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn timestamps() {
+        let last_modified = DateTime::from_date_and_time(2020, 3, 1, 12, 30, 10).unwrap();
+        let time = UNIX_EPOCH + Duration::from_secs(1_583_065_810);
+        assert_eq!(system_time(last_modified), Some(time));
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().last_modified_time(last_modified);
+        writer.add_directory("dir/", options).unwrap();
+        writer.start_file("dir/a.txt", options).unwrap();
+        let v = writer.finish().unwrap().into_inner();
+
+        let directory = directory("timestamps");
+        extract_stream(v.as_slice(), &directory, ExtractOptions::default()).unwrap();
+        let modified = |path| {
+            fs::metadata(directory.join(path))
+                .unwrap()
+                .modified()
+                .unwrap()
+        };
+        assert_eq!(modified("dir/a.txt"), time);
+        #[cfg(unix)]
+        assert_eq!(modified("dir"), time);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn parallel() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        for i in 0..20 {
+            writer
+                .add_directory(format!("{}/", i % 4), options)
+                .unwrap();
+            writer
+                .start_file(format!("{}/{}.txt", i % 4, i), options)
+                .unwrap();
+            writer.write_all(i.to_string().as_bytes()).unwrap();
+        }
+        let mut v = writer.finish().unwrap().into_inner();
+        // Corrupt the data of the last file
+        let position = v.windows(4).rposition(|w| w == b"PK\x03\x04").unwrap();
+        v[position + 30 + "3/19.txt".len()] ^= 0xff;
+
+        let directory = directory("parallel");
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        let report = archive
+            .extract_parallel(&directory, ExtractOptions::default(), 4)
+            .unwrap();
+        let failed = report
+            .failed()
+            .iter()
+            .map(|file| (file.index(), file.name()))
+            .collect::<Vec<_>>();
+        assert_eq!(failed, [(39, "3/19.txt")]);
+        for i in 0..19 {
+            let path = directory.join(format!("{}/{}.txt", i % 4, i));
+            assert_eq!(fs::read(path).unwrap(), i.to_string().as_bytes());
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn parallel_total_size() {
+        /// A reader that reads the data of a file in two halves, waiting for every thread before the
+        /// first one, and slowly reading the second one, so that the threads extract their files
+        /// at the same time
+        #[derive(Clone)]
+        struct Synchronized(io::Cursor<Vec<u8>>, Arc<Barrier>);
+
+        impl Read for Synchronized {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match buf.len() {
+                    0x10000 => {
+                        self.1.wait();
+                        self.0.read(&mut buf[..0x8000])
+                    }
+                    0x8000 => {
+                        std::thread::sleep(Duration::from_millis(50));
+                        self.0.read(buf)
+                    }
+                    _ => self.0.read(buf),
+                }
+            }
+        }
+
+        impl io::Seek for Synchronized {
+            fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+                self.0.seek(pos)
+            }
+        }
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        for i in 0..8 {
+            writer.start_file(format!("{}.bin", i), options).unwrap();
+            writer.write_all(&[i as u8; 1 << 16]).unwrap();
+        }
+        let v = writer.finish().unwrap().into_inner();
+
+        let directory = directory("parallel-total");
+        let reader = Synchronized(io::Cursor::new(v), Arc::new(Barrier::new(4)));
+        let mut archive = ZipArchive::new(reader).unwrap();
+        let options = ExtractOptions::default().limits(Limits::default().max_total_size(100 << 10));
+        let report = archive.extract_parallel(&directory, options, 4).unwrap();
+        // Only the data counted towards the limit is written, although the files are extracted
+        // at the same time
+        let written = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().metadata().unwrap().len())
+            .sum::<u64>();
+        assert!(written <= 100 << 10);
+        assert!(report.failed().len() >= 7);
+        for file in report.failed() {
+            assert!(matches!(
+                file.error(),
+                ZipError::LimitExceeded("Files are too large in total")
+            ));
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn conflicts() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (name, content) in [("Res/a.png", "1"), ("res/a.png", "2"), ("aux.txt", "3")].iter() {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        let v = writer.finish().unwrap().into_inner();
+
+        let directory = directory("conflicts");
+        let options = ExtractOptions::default().conflicts(Conflicts::Error);
+        match extract_stream(v.as_slice(), &directory, options) {
+            Err(ZipError::InvalidArchive("File names conflict")) => {}
+            _ => panic!("the names should conflict"),
+        }
+        fs::remove_dir_all(&directory).unwrap();
+
+        let options = ExtractOptions::default().conflicts(Conflicts::Rename);
+        extract_with(&v, &directory, options).unwrap();
+        assert_eq!(fs::read(directory.join("Res/a.png")).unwrap(), b"1");
+        assert_eq!(fs::read(directory.join("Res/a (1).png")).unwrap(), b"2");
+        assert_eq!(fs::read(directory.join("_aux.txt")).unwrap(), b"3");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    fn symlink_archive(target: &str, through: bool) -> Vec<u8> {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .add_symlink("link", target, FileOptions::default())
+            .unwrap();
+        if through {
+            writer
+                .start_file("link/file.txt", FileOptions::default())
+                .unwrap();
+            writer.write_all(b"through the link").unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks() {
+        let directory = directory("symlinks");
+        fs::create_dir_all(directory.join("target")).unwrap();
+        extract_with(
+            &symlink_archive("target", true),
+            &directory,
+            ExtractOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_link(directory.join("link")).unwrap(),
+            Path::new("target")
+        );
+        assert_eq!(
+            fs::read(directory.join("target/file.txt")).unwrap(),
+            b"through the link"
+        );
+
+        // Symlinks are created once their mode is known from the central directory
+        let v = symlink_archive("target/file.txt", false);
+        extract_stream(v.as_slice(), &directory, ExtractOptions::default()).unwrap();
+        assert_eq!(
+            fs::read_link(directory.join("link")).unwrap(),
+            Path::new("target/file.txt")
+        );
+
+        let options = ExtractOptions::default().symlinks(Symlinks::Refuse);
+        match extract_stream(v.as_slice(), &directory, options) {
+            Err(ZipError::UnsupportedArchive("Symlinks are not allowed")) => {}
+            _ => panic!("the symlink should be refused"),
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_escape() {
+        let outside = directory("outside");
+        fs::create_dir_all(&outside).unwrap();
+        let v = symlink_archive(outside.to_str().unwrap(), true);
+
+        let directory = directory("escape");
+        match extract_with(&v, &directory, ExtractOptions::default()) {
+            Err(ZipError::InvalidArchive("Symlink points outside the destination")) => {}
+            _ => panic!("the symlink should be rejected"),
+        }
+        let v_stream = symlink_archive("../../..", false);
+        match extract_stream(v_stream.as_slice(), &directory, ExtractOptions::default()) {
+            Err(ZipError::InvalidArchive("Symlink points outside the destination")) => {}
+            _ => panic!("the symlink should be rejected"),
+        }
+        fs::remove_dir_all(&directory).unwrap();
+
+        // The symlink itself may be created, but not written through
+        let options = ExtractOptions::default().symlinks(Symlinks::Create);
+        match extract_with(&v, &directory, options) {
+            Err(ZipError::InvalidArchive(
+                "Path leads outside the destination through a symlink",
+            )) => {}
+            _ => panic!("writing through the symlink should be rejected"),
+        }
+        assert!(!outside.join("file.txt").exists());
+        fs::remove_dir_all(&directory).unwrap();
+
+        // Existing symlinks are not followed either
+        fs::create_dir_all(&directory).unwrap();
+        std::os::unix::fs::symlink(&outside, directory.join("dir")).unwrap();
+        assert!(extract_with(&archive(), &directory, ExtractOptions::default()).is_err());
+        assert!(!outside.join("a.txt").exists());
+        fs::remove_dir_all(&directory).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+    }
+}
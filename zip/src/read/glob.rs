@@ -0,0 +1,184 @@
+//! Shell style patterns for matching the names of files in an archive
+
+/// Check whether `name` matches the glob `pattern`.
+///
+/// - `?` matches any single character except `/`
+/// - `*` matches any sequence of characters except `/`
+/// - `**` matches any sequence of characters, and `**/` also matches no directory at all
+/// - `[abc]`, `[a-z]` and `[!abc]` match a single character (not) in the set
+/// - `\` matches the character following it literally
+pub(crate) fn matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    Matcher::new(&pattern, &name).matches(0, 0)
+}
+
+/// Get the part of `pattern` before its first special character, which every match must start with.
+pub(crate) fn literal_prefix(pattern: &str) -> &str {
+    let end = pattern
+        .find(['?', '*', '[', '\\'])
+        .unwrap_or(pattern.len());
+    &pattern[..end]
+}
+
+/// Matches a pattern against a name, remembering the positions in both that don't match, so that
+/// patterns with many wildcards don't take exponential time on names from untrusted archives.
+struct Matcher<'a> {
+    pattern: &'a [char],
+    name: &'a [char],
+    /// Whether the rest of the pattern is known not to match the rest of the name, by position
+    failed: Vec<bool>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(pattern: &'a [char], name: &'a [char]) -> Matcher<'a> {
+        Matcher {
+            pattern,
+            name,
+            failed: vec![false; (pattern.len() + 1) * (name.len() + 1)],
+        }
+    }
+
+    /// Check whether the pattern from `p` matches the name from `n`
+    fn matches(&mut self, p: usize, n: usize) -> bool {
+        let key = p * (self.name.len() + 1) + n;
+        if self.failed[key] {
+            return false;
+        }
+        let matched = self.matches_uncached(p, n);
+        self.failed[key] = !matched;
+        matched
+    }
+
+    fn matches_uncached(&mut self, p: usize, n: usize) -> bool {
+        let (pattern, name) = (self.pattern, self.name);
+        match &pattern[p..] {
+            [] => n == name.len(),
+            ['*', '*', rest @ ..] => {
+                if let ['/', ..] = rest {
+                    if self.matches(p + 3, n) {
+                        return true;
+                    }
+                }
+                (n..=name.len()).any(|i| self.matches(p + 2, i))
+            }
+            ['*', ..] => {
+                for i in n..=name.len() {
+                    if self.matches(p + 1, i) {
+                        return true;
+                    }
+                    if name.get(i) == Some(&'/') {
+                        break;
+                    }
+                }
+                false
+            }
+            ['?', ..] => match name.get(n) {
+                Some(c) if *c != '/' => self.matches(p + 1, n + 1),
+                _ => false,
+            },
+            ['[', class @ ..] => match (name.get(n), parse_class(class)) {
+                (Some(&c), Some((matched, rest))) if c != '/' && matched(c) => {
+                    self.matches(pattern.len() - rest.len(), n + 1)
+                }
+                // An unterminated class is matched literally
+                (Some('['), None) => self.matches(p + 1, n + 1),
+                _ => false,
+            },
+            ['\\', literal, ..] => self.matches_literal(*literal, p + 2, n),
+            [literal, ..] => self.matches_literal(*literal, p + 1, n),
+        }
+    }
+
+    /// Check whether the name from `n` starts with `literal`, and the pattern from `next` matches
+    /// the rest of it
+    fn matches_literal(&mut self, literal: char, next: usize, n: usize) -> bool {
+        match self.name.get(n) {
+            Some(&c) if c == literal => self.matches(next, n + 1),
+            _ => false,
+        }
+    }
+}
+
+/// Parse a character class following a `[`, returning a predicate for it and the rest of the pattern
+#[allow(clippy::type_complexity)]
+fn parse_class(class: &[char]) -> Option<(impl Fn(char) -> bool + '_, &[char])> {
+    let (negated, body) = match class {
+        ['!', body @ ..] | ['^', body @ ..] => (true, body),
+        body => (false, body),
+    };
+    // A `]` right at the start is part of the set
+    let end = body.iter().skip(1).position(|&c| c == ']')? + 1;
+    let (set, rest) = (&body[..end], &body[end + 1..]);
+
+    let matched = move |c: char| {
+        let mut found = false;
+        let mut i = 0;
+        while i < set.len() {
+            if i + 2 < set.len() && set[i + 1] == '-' {
+                found |= (set[i]..=set[i + 2]).contains(&c);
+                i += 3;
+            } else {
+                found |= set[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matched, rest))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{literal_prefix, matches};
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*.dex", "classes.dex"));
+        assert!(matches("*.dex", "classes2.dex"));
+        assert!(!matches("*.dex", "lib/classes.dex"));
+        assert!(matches("lib/*/*.so", "lib/arm64-v8a/libziprs.so"));
+        assert!(!matches("lib/*.so", "lib/arm64-v8a/libziprs.so"));
+        assert!(matches("classes?.dex", "classes2.dex"));
+        assert!(!matches("classes?.dex", "classes.dex"));
+        assert!(matches("*", ""));
+        assert!(!matches("a?b", "a/b"));
+    }
+
+    #[test]
+    fn recursive_wildcards() {
+        assert!(matches("**/*.so", "lib/arm64-v8a/libziprs.so"));
+        assert!(matches("**/*.so", "libziprs.so"));
+        assert!(matches("assets/**", "assets/a/b/c.txt"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(!matches("a/**/b", "a/x/y/c"));
+    }
+
+    #[test]
+    fn classes_and_escapes() {
+        assert!(matches("res/[a-c].xml", "res/b.xml"));
+        assert!(!matches("res/[a-c].xml", "res/d.xml"));
+        assert!(matches("res/[!a-c].xml", "res/d.xml"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a", "[a"));
+        assert!(matches("\\*.txt", "*.txt"));
+        assert!(!matches("\\*.txt", "a.txt"));
+    }
+
+    #[test]
+    fn many_wildcards() {
+        let pattern = "*a".repeat(7) + "b";
+        let name = "a".repeat(60);
+        assert!(!matches(&pattern, &name));
+        assert!(matches(&pattern, &(name + "b")));
+        assert!(!matches(&"**a".repeat(7), &"a".repeat(6)));
+    }
+
+    #[test]
+    fn prefix() {
+        assert_eq!(literal_prefix("lib/*/x.so"), "lib/");
+        assert_eq!(literal_prefix("AndroidManifest.xml"), "AndroidManifest.xml");
+        assert_eq!(literal_prefix("**"), "");
+    }
+}
@@ -0,0 +1,426 @@
+//! Persistable index of a parsed central directory, to reopen an archive without parsing it again
+
+use super::{zip_archive, Limits, ParseOptions, ZipArchive};
+use crate::compression::CompressionMethod;
+use crate::result::{ZipError, ZipResult};
+use crate::spec;
+use crate::types::{AesMode, AesVendorVersion, AtomicU64, DateTime, System, ZipFileData};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, prelude::*};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Identifies the cache file format, followed by its version
+const INDEX_MAGIC: &[u8; 6] = b"ZIPIDX";
+const INDEX_VERSION: u16 = 1;
+
+/// Identity of an archive, used to detect an index that no longer matches the archive it was made for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexKey {
+    /// Size of the archive in bytes
+    pub size: u64,
+    /// Modification time of the archive as nanoseconds since the unix epoch, or 0 if unknown
+    pub modified: u128,
+    /// CRC32 of the end of central directory record, including the archive comment
+    pub eocd_hash: u32,
+}
+
+impl IndexKey {
+    /// Compute the key of an archive.
+    ///
+    /// `modified` should be the modification time of the file backing the reader, if there is one.
+    pub fn new<R: Read + io::Seek>(
+        reader: &mut R,
+        modified: Option<SystemTime>,
+    ) -> ZipResult<IndexKey> {
+        let size = reader.stream_len()?;
+        let (_, cde_start_pos) = spec::CentralDirectoryEnd::find_and_parse(reader)?;
+
+        // The record is 22 bytes plus a comment of at most u16::MAX bytes
+        let mut record = Vec::new();
+        reader.seek(io::SeekFrom::Start(cde_start_pos))?;
+        reader.take(22 + u16::MAX as u64).read_to_end(&mut record)?;
+
+        let modified = modified
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_nanos());
+
+        Ok(IndexKey {
+            size,
+            modified,
+            eocd_hash: crc32fast::hash(&record),
+        })
+    }
+
+    /// Compute the key of an archive stored in a file
+    pub fn for_file(file: &mut fs::File) -> ZipResult<IndexKey> {
+        let modified = file.metadata()?.modified().ok();
+        IndexKey::new(file, modified)
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_u64::<LittleEndian>(self.size)?;
+        writer.write_u128::<LittleEndian>(self.modified)?;
+        writer.write_u32::<LittleEndian>(self.eocd_hash)
+    }
+
+    fn read<R: Read>(reader: &mut R) -> io::Result<IndexKey> {
+        Ok(IndexKey {
+            size: reader.read_u64::<LittleEndian>()?,
+            modified: reader.read_u128::<LittleEndian>()?,
+            eocd_hash: reader.read_u32::<LittleEndian>()?,
+        })
+    }
+}
+
+impl<R: Read + io::Seek> ZipArchive<R> {
+    /// Serialize the parsed central directory of this archive, so it can be reopened later with
+    /// [`ZipArchive::from_index`] without parsing it again.
+    ///
+    /// `key` should be computed from the same archive with [`IndexKey::new`]. For lazily opened
+    /// archives, the metadata of every file is decoded first.
+    pub fn write_index<W: Write>(&mut self, key: &IndexKey, writer: &mut W) -> ZipResult<()> {
+        writer.write_all(INDEX_MAGIC)?;
+        writer.write_u16::<LittleEndian>(INDEX_VERSION)?;
+        key.write(writer)?;
+        writer.write_u64::<LittleEndian>(self.shared.offset)?;
+        write_bytes(writer, &self.shared.comment)?;
+
+        writer.write_u64::<LittleEndian>(self.len() as u64)?;
+        for i in 0..self.len() {
+            let file = self.shared.file(i, &mut self.reader)?;
+            write_file(writer, file)?;
+        }
+        Ok(())
+    }
+
+    /// Open an archive from an index written by [`ZipArchive::write_index`].
+    ///
+    /// Fails with [`ZipError::InvalidArchive`] if the index is malformed or if its key is not `key`,
+    /// meaning the archive changed since the index was written.
+    pub fn from_index<I: Read>(
+        reader: R,
+        key: &IndexKey,
+        index: &mut I,
+    ) -> ZipResult<ZipArchive<R>> {
+        let shared = Arc::new(read_index(key, index)?);
+        Ok(ZipArchive {
+            reader,
+            shared,
+            limits: Limits::default(),
+            options: ParseOptions::default(),
+        })
+    }
+
+    /// Open an archive using the index stored at `index_path`.
+    ///
+    /// If the index is missing, unreadable or stale, the archive is parsed again and a new index is
+    /// written in its place. Failing to write the index does not fail opening the archive.
+    ///
+    /// `modified` should be the modification time of the file backing the reader, see [`IndexKey::new`].
+    pub fn open_cached<P: AsRef<Path>>(
+        mut reader: R,
+        modified: Option<SystemTime>,
+        index_path: P,
+    ) -> ZipResult<ZipArchive<R>> {
+        let index_path = index_path.as_ref();
+        let key = IndexKey::new(&mut reader, modified)?;
+
+        // Anything wrong with the index only means it has to be rebuilt
+        if let Ok(file) = fs::File::open(index_path) {
+            if let Ok(shared) = read_index(&key, &mut io::BufReader::new(file)) {
+                let shared = Arc::new(shared);
+                return Ok(ZipArchive {
+                    reader,
+                    shared,
+                    limits: Limits::default(),
+                    options: ParseOptions::default(),
+                });
+            }
+        }
+
+        let mut archive = ZipArchive::new(reader)?;
+        let _ = archive.persist_index(&key, index_path);
+        Ok(archive)
+    }
+
+    /// Write the index to a temporary file first, so a concurrent reader never sees a partial index
+    fn persist_index(&mut self, key: &IndexKey, index_path: &Path) -> ZipResult<()> {
+        let mut temp_path = index_path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+
+        let mut writer = io::BufWriter::new(fs::File::create(&temp_path)?);
+        let result = self
+            .write_index(key, &mut writer)
+            .and_then(|_| writer.flush().map_err(ZipError::from))
+            .and_then(|_| fs::rename(&temp_path, index_path).map_err(ZipError::from));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+}
+
+fn read_index<I: Read>(key: &IndexKey, index: &mut I) -> ZipResult<zip_archive::Shared> {
+    let mut magic = [0u8; 6];
+    index.read_exact(&mut magic)?;
+    if &magic != INDEX_MAGIC || index.read_u16::<LittleEndian>()? != INDEX_VERSION {
+        return Err(ZipError::InvalidArchive("Invalid index cache"));
+    }
+    if IndexKey::read(index)? != *key {
+        return Err(ZipError::InvalidArchive("Index cache is stale"));
+    }
+
+    let offset = index.read_u64::<LittleEndian>()?;
+    let comment = read_bytes(index)?;
+
+    let number_of_files = index.read_u64::<LittleEndian>()?;
+    let mut files = Vec::new();
+    let mut names_map = HashMap::new();
+    for _ in 0..number_of_files {
+        let file = read_file(index)?;
+        names_map.insert(file.file_name.clone(), files.len());
+        files.push(file);
+    }
+
+    Ok(zip_archive::Shared {
+        files,
+        names_map,
+        lazy: None,
+        offset,
+        comment,
+        tree: Default::default(),
+        seek_indexes: Default::default(),
+    })
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    writer.write_u32::<LittleEndian>(bytes.len() as u32)?;
+    writer.write_all(bytes)
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> ZipResult<Vec<u8>> {
+    let len = reader.read_u32::<LittleEndian>()? as u64;
+    // Don't trust the length for preallocating, the index may be corrupt
+    let mut bytes = Vec::new();
+    if reader.take(len).read_to_end(&mut bytes)? as u64 != len {
+        return Err(ZipError::InvalidArchive("Invalid index cache"));
+    }
+    Ok(bytes)
+}
+
+fn read_string<R: Read>(reader: &mut R) -> ZipResult<String> {
+    String::from_utf8(read_bytes(reader)?)
+        .map_err(|_| ZipError::InvalidArchive("Invalid index cache"))
+}
+
+fn write_file<W: Write>(writer: &mut W, file: &ZipFileData) -> io::Result<()> {
+    writer.write_u8(file.system as u8)?;
+    writer.write_u8(file.version_made_by)?;
+    writer.write_u8(
+        file.encrypted as u8
+            | (file.using_data_descriptor as u8) << 1
+            | (file.large_file as u8) << 2,
+    )?;
+    #[allow(deprecated)]
+    writer.write_u16::<LittleEndian>(file.compression_method.to_u16())?;
+    match file.compression_level {
+        Some(level) => {
+            writer.write_u8(1)?;
+            writer.write_i32::<LittleEndian>(level)?;
+        }
+        None => writer.write_u8(0)?,
+    }
+    writer.write_u16::<LittleEndian>(file.last_modified_time.datepart())?;
+    writer.write_u16::<LittleEndian>(file.last_modified_time.timepart())?;
+    writer.write_u32::<LittleEndian>(file.crc32)?;
+    writer.write_u64::<LittleEndian>(file.compressed_size)?;
+    writer.write_u64::<LittleEndian>(file.uncompressed_size)?;
+    write_bytes(writer, file.file_name.as_bytes())?;
+    write_bytes(writer, &file.file_name_raw)?;
+    write_bytes(writer, &file.extra_field)?;
+    write_bytes(writer, file.file_comment.as_bytes())?;
+    writer.write_u64::<LittleEndian>(file.header_start)?;
+    writer.write_u64::<LittleEndian>(file.central_header_start)?;
+    writer.write_u64::<LittleEndian>(file.data_start.load())?;
+    writer.write_u32::<LittleEndian>(file.external_attributes)?;
+    let aes_mode = match file.aes_mode {
+        None => [0, 0],
+        Some((mode, vendor_version)) => [
+            match mode {
+                AesMode::Aes128 => 1,
+                AesMode::Aes192 => 2,
+                AesMode::Aes256 => 3,
+            },
+            match vendor_version {
+                AesVendorVersion::Ae1 => 1,
+                AesVendorVersion::Ae2 => 2,
+            },
+        ],
+    };
+    writer.write_all(&aes_mode)
+}
+
+fn read_file<R: Read>(reader: &mut R) -> ZipResult<ZipFileData> {
+    let system = System::from_u8(reader.read_u8()?);
+    let version_made_by = reader.read_u8()?;
+    let flags = reader.read_u8()?;
+    #[allow(deprecated)]
+    let compression_method = CompressionMethod::from_u16(reader.read_u16::<LittleEndian>()?);
+    let compression_level = match reader.read_u8()? {
+        0 => None,
+        _ => Some(reader.read_i32::<LittleEndian>()?),
+    };
+    let datepart = reader.read_u16::<LittleEndian>()?;
+    let timepart = reader.read_u16::<LittleEndian>()?;
+
+    let mut file = ZipFileData {
+        system,
+        version_made_by,
+        encrypted: flags & 1 != 0,
+        using_data_descriptor: flags & (1 << 1) != 0,
+        compression_method,
+        compression_level,
+        last_modified_time: DateTime::from_msdos(datepart, timepart),
+        crc32: reader.read_u32::<LittleEndian>()?,
+        compressed_size: reader.read_u64::<LittleEndian>()?,
+        uncompressed_size: reader.read_u64::<LittleEndian>()?,
+        file_name: read_string(reader)?,
+        file_name_raw: read_bytes(reader)?,
+        extra_field: read_bytes(reader)?,
+        file_comment: read_string(reader)?,
+        header_start: reader.read_u64::<LittleEndian>()?,
+        central_header_start: reader.read_u64::<LittleEndian>()?,
+        data_start: AtomicU64::new(reader.read_u64::<LittleEndian>()?),
+        external_attributes: reader.read_u32::<LittleEndian>()?,
+        large_file: flags & (1 << 2) != 0,
+        aes_mode: None,
+    };
+
+    let mut aes_mode = [0u8; 2];
+    reader.read_exact(&mut aes_mode)?;
+    file.aes_mode = match aes_mode {
+        [0, _] => None,
+        [mode, vendor_version] => Some((
+            match mode {
+                1 => AesMode::Aes128,
+                2 => AesMode::Aes192,
+                3 => AesMode::Aes256,
+                _ => return Err(ZipError::InvalidArchive("Invalid index cache")),
+            },
+            match vendor_version {
+                1 => AesVendorVersion::Ae1,
+                2 => AesVendorVersion::Ae2,
+                _ => return Err(ZipError::InvalidArchive("Invalid index cache")),
+            },
+        )),
+    };
+
+    Ok(file)
+}
+
+#[cfg(test)]
+mod test {
+    use super::IndexKey;
+    use crate::write::FileOptions;
+    use crate::{CompressionMethod, ZipArchive, ZipWriter};
+    use std::io::{self, Read, Write};
+    use std::time::SystemTime;
+
+    fn archive(comment: &str) -> Vec<u8> {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("a.txt", options).unwrap();
+        writer.write_all(b"hello").unwrap();
+        writer.add_directory("dir/", options).unwrap();
+        writer.start_file("dir/b.txt", options).unwrap();
+        writer.write_all(b"world").unwrap();
+        writer.set_comment(comment);
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn index_roundtrip() {
+        let mut reader = io::Cursor::new(archive("comment"));
+        let key = IndexKey::new(&mut reader, None).unwrap();
+        let mut index = Vec::new();
+        ZipArchive::new_lazy(reader.clone())
+            .unwrap()
+            .write_index(&key, &mut index)
+            .unwrap();
+
+        let mut eager = ZipArchive::new(reader.clone()).unwrap();
+        let mut cached = ZipArchive::from_index(reader, &key, &mut index.as_slice()).unwrap();
+        assert_eq!(cached.len(), eager.len());
+        assert_eq!(cached.comment(), b"comment");
+        for i in 0..eager.len() {
+            let (name, crc32, attributes, start) = {
+                let file = eager.by_index(i).unwrap();
+                (
+                    file.name().to_owned(),
+                    file.crc32(),
+                    file.unix_mode(),
+                    file.header_start(),
+                )
+            };
+            let file = cached.by_index(i).unwrap();
+            assert_eq!(file.name(), name);
+            assert_eq!(file.crc32(), crc32);
+            assert_eq!(file.unix_mode(), attributes);
+            assert_eq!(file.header_start(), start);
+        }
+
+        let mut content = String::new();
+        cached
+            .by_name("dir/b.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "world");
+    }
+
+    #[test]
+    fn index_stale() {
+        let mut reader = io::Cursor::new(archive("first"));
+        let key = IndexKey::new(&mut reader, None).unwrap();
+        let mut index = Vec::new();
+        ZipArchive::new(reader.clone())
+            .unwrap()
+            .write_index(&key, &mut index)
+            .unwrap();
+
+        let mut changed = io::Cursor::new(archive("other"));
+        let changed_key = IndexKey::new(&mut changed, None).unwrap();
+        assert_ne!(changed_key, key);
+        assert!(ZipArchive::from_index(changed, &changed_key, &mut index.as_slice()).is_err());
+
+        let touched_key = IndexKey::new(&mut reader, Some(SystemTime::now())).unwrap();
+        assert!(ZipArchive::from_index(reader, &touched_key, &mut index.as_slice()).is_err());
+    }
+
+    #[test]
+    fn open_cached_rebuilds() {
+        let index_path = std::env::temp_dir().join(format!("zip-index-{}", std::process::id()));
+        let _ = std::fs::remove_file(&index_path);
+
+        let first = ZipArchive::open_cached(io::Cursor::new(archive("first")), None, &index_path);
+        assert_eq!(first.unwrap().comment(), b"first");
+        assert!(index_path.exists());
+
+        let again = ZipArchive::open_cached(io::Cursor::new(archive("first")), None, &index_path);
+        assert_eq!(again.unwrap().len(), 3);
+
+        // The archive changed, so the index must not be used
+        let changed = ZipArchive::open_cached(io::Cursor::new(archive("other")), None, &index_path);
+        assert_eq!(changed.unwrap().comment(), b"other");
+
+        std::fs::write(&index_path, b"garbage").unwrap();
+        let corrupt = ZipArchive::open_cached(io::Cursor::new(archive("other")), None, &index_path);
+        assert_eq!(corrupt.unwrap().comment(), b"other");
+
+        std::fs::remove_file(&index_path).unwrap();
+    }
+}
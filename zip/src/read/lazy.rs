@@ -0,0 +1,272 @@
+//! Lazily decoded central directory, for archives with a very large number of entries
+
+use super::{central_header_to_zip_file, missing_zip64, ParseOptions};
+use crate::cp437::FromCp437;
+use crate::result::{ZipError, ZipResult};
+use crate::spec;
+use crate::types::ZipFileData;
+use byteorder::{ByteOrder, LittleEndian};
+use std::borrow::Cow;
+use std::io::{self, prelude::*};
+use std::sync::OnceLock;
+
+/// Size of a central directory header without its variable length fields
+const CENTRAL_HEADER_SIZE: usize = 46;
+
+/// Compact index of a central directory, decoding the full metadata of a file only when it is used.
+#[derive(Debug)]
+pub(crate) struct LazyIndex {
+    /// Position of the central directory header of each file
+    central_offsets: Vec<u64>,
+    /// The names of all files, concatenated
+    names: String,
+    /// End of the name of each file in `names`
+    name_ends: Vec<u32>,
+    /// File indices, ordered by name
+    sorted: Vec<u32>,
+    /// Files that have been decoded so far
+    files: Vec<OnceLock<Box<ZipFileData>>>,
+    /// Start of the central directory, when the local header offsets may have been truncated to
+    /// 32 bits and are corrected as files are decoded
+    truncated_offsets: Option<u64>,
+}
+
+impl LazyIndex {
+    /// Walk the central directory, recording the position and name of every file in it.
+    pub(crate) fn build<R: Read + io::Seek>(
+        reader: &mut R,
+        directory_start: u64,
+        number_of_files: usize,
+        file_capacity: usize,
+    ) -> ZipResult<LazyIndex> {
+        if number_of_files > u32::MAX as usize {
+            return Err(ZipError::UnsupportedArchive(
+                "Too many files for a lazily read archive",
+            ));
+        }
+
+        let mut central_offsets = Vec::with_capacity(file_capacity);
+        let mut names = String::new();
+        let mut name_ends = Vec::with_capacity(file_capacity);
+        let mut reader = io::BufReader::new(reader);
+        let mut position = directory_start;
+        let mut header = [0u8; CENTRAL_HEADER_SIZE];
+        let mut name_raw = Vec::new();
+
+        loop {
+            // Archives with more than 65535 files written without Zip64 only declare the number of
+            // files modulo 65536, so the headers past the declared number are read as well
+            let past_declared = central_offsets.len() >= number_of_files;
+            match reader.read_exact(&mut header) {
+                Err(e) if past_declared && e.kind() == io::ErrorKind::UnexpectedEof => break,
+                result => result?,
+            }
+            let signature = LittleEndian::read_u32(&header[0..]);
+            if signature != spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE {
+                if past_declared {
+                    break;
+                }
+                return Err(ZipError::InvalidSignature {
+                    record: "central directory header",
+                    offset: position,
+                    found: signature,
+                });
+            }
+
+            let flags = LittleEndian::read_u16(&header[8..]);
+            let file_name_length = LittleEndian::read_u16(&header[28..]) as usize;
+            let extra_field_length = LittleEndian::read_u16(&header[30..]) as i64;
+            let file_comment_length = LittleEndian::read_u16(&header[32..]) as i64;
+
+            name_raw.resize(file_name_length, 0);
+            reader.read_exact(&mut name_raw)?;
+            reader.seek_relative(extra_field_length + file_comment_length)?;
+
+            let name: Cow<str> = match flags & (1 << 11) != 0 {
+                true => String::from_utf8_lossy(&name_raw),
+                false => name_raw.as_slice().from_cp437(),
+            };
+            names.push_str(&name);
+            if names.len() > u32::MAX as usize {
+                return Err(ZipError::UnsupportedArchive(
+                    "File names are too large for a lazily read archive",
+                ));
+            }
+            name_ends.push(names.len() as u32);
+            central_offsets.push(position);
+
+            position += (CENTRAL_HEADER_SIZE + file_name_length) as u64
+                + (extra_field_length + file_comment_length) as u64;
+        }
+
+        let mut index = LazyIndex {
+            files: (0..central_offsets.len()).map(|_| OnceLock::new()).collect(),
+            sorted: (0..central_offsets.len() as u32).collect(),
+            central_offsets,
+            names,
+            name_ends,
+            truncated_offsets: None,
+        };
+        // The sort is stable, so files sharing a name stay in archive order
+        let mut sorted = std::mem::take(&mut index.sorted);
+        sorted.sort_by(|&a, &b| index.name(a as usize).cmp(index.name(b as usize)));
+        index.sorted = sorted;
+
+        Ok(index)
+    }
+
+    /// Correct the local header offsets truncated to 32 bits when decoding files, like
+    /// [`missing_zip64::fix_header_offsets`] does for eagerly read archives
+    pub(crate) fn fix_header_offsets(&mut self, directory_start: u64) {
+        self.truncated_offsets = Some(directory_start);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.central_offsets.len()
+    }
+
+    /// Get the name of a file by its index
+    pub(crate) fn name(&self, index: usize) -> &str {
+        let start = match index {
+            0 => 0,
+            _ => self.name_ends[index - 1] as usize,
+        };
+        &self.names[start..self.name_ends[index] as usize]
+    }
+
+    /// Iterate over the names of all files, in archive order
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        (0..self.len()).map(move |index| self.name(index))
+    }
+
+    /// Find the index of a file by its name.
+    ///
+    /// If several files share the name, the last one wins, like it does for eagerly read archives.
+    pub(crate) fn index_of(&self, name: &str) -> Option<usize> {
+        let end = self
+            .sorted
+            .partition_point(|&index| self.name(index as usize) <= name);
+        let index = *self.sorted[..end].last()? as usize;
+        (self.name(index) == name).then_some(index)
+    }
+
+    /// Get the metadata of a file if it was decoded already
+    pub(crate) fn decoded(&self, index: usize) -> Option<&ZipFileData> {
+        self.files.get(index)?.get().map(|file| &**file)
+    }
+
+    /// Get the metadata of a file, decoding its central directory header if this is the first use.
+    pub(crate) fn file<R: Read + io::Seek>(
+        &self,
+        index: usize,
+        reader: &mut R,
+        archive_offset: u64,
+    ) -> ZipResult<&ZipFileData> {
+        let slot = self.files.get(index).ok_or(ZipError::FileNotFound)?;
+        if let Some(file) = slot.get() {
+            return Ok(file);
+        }
+
+        let directory_start = match self.truncated_offsets {
+            Some(directory_start) => directory_start,
+            None => {
+                let file = self.decode(index, reader, archive_offset)?;
+                return Ok(slot.get_or_init(|| Box::new(file)));
+            }
+        };
+
+        // Offsets are corrected from the end of the previous file, so the files before this one
+        // are decoded first, in order
+        let first = (0..index)
+            .rev()
+            .find(|&previous| self.files[previous].get().is_some())
+            .map_or(0, |previous| previous + 1);
+        let mut previous_end = match first.checked_sub(1).and_then(|i| self.decoded(i)) {
+            Some(previous) => previous.data_start.load() + previous.compressed_size,
+            None => 0,
+        };
+        let mut current = first;
+        loop {
+            let mut file = self.decode(current, reader, archive_offset)?;
+            previous_end =
+                missing_zip64::fix_header_offset(reader, &mut file, previous_end, directory_start)?;
+            let file = self.files[current].get_or_init(|| Box::new(file));
+            if current == index {
+                return Ok(file);
+            }
+            current += 1;
+        }
+    }
+
+    /// Decode the central directory header of a file
+    fn decode<R: Read + io::Seek>(
+        &self,
+        index: usize,
+        reader: &mut R,
+        archive_offset: u64,
+    ) -> ZipResult<ZipFileData> {
+        reader.seek(io::SeekFrom::Start(self.central_offsets[index]))?;
+        central_header_to_zip_file(reader, archive_offset, &ParseOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::read::test_util::zip;
+    use crate::write::FileOptions;
+    use crate::{CompressionMethod, ZipArchive};
+    use std::io::{self, Read};
+
+    fn archive() -> Vec<u8> {
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        zip(&[
+            ("b.txt", b"first b", options),
+            ("a/", b"", options),
+            ("a/c.txt", b"c", options),
+            ("b.txt", b"second b", options),
+            ("\u{e9}.txt", b"e", options),
+        ])
+    }
+
+    #[test]
+    fn lazy_matches_eager() {
+        let mut eager = ZipArchive::new(io::Cursor::new(archive())).unwrap();
+        let mut lazy = ZipArchive::new_lazy(io::Cursor::new(archive())).unwrap();
+        assert!(lazy.is_lazy() && !eager.is_lazy());
+        assert_eq!(lazy.len(), eager.len());
+
+        let names = lazy.file_names().collect::<Vec<_>>();
+        assert_eq!(names, ["b.txt", "a/", "a/c.txt", "b.txt", "\u{e9}.txt"]);
+
+        for i in 0..eager.len() {
+            let eager_file = eager.by_index(i).unwrap();
+            let (name, crc32, start) = (
+                eager_file.name().to_owned(),
+                eager_file.crc32(),
+                eager_file.central_header_start(),
+            );
+            drop(eager_file);
+            let lazy_file = lazy.by_index(i).unwrap();
+            assert_eq!(lazy_file.name(), name);
+            assert_eq!(lazy_file.crc32(), crc32);
+            assert_eq!(lazy_file.central_header_start(), start);
+        }
+        assert!(lazy.by_index(5).is_err());
+    }
+
+    #[test]
+    fn lazy_by_name() {
+        let mut lazy = ZipArchive::new_lazy(io::Cursor::new(archive())).unwrap();
+
+        let mut content = String::new();
+        lazy.by_name("b.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "second b");
+        assert_eq!(lazy.by_name("a/c.txt").unwrap().index(), 2);
+        assert_eq!(lazy.by_name("\u{e9}.txt").unwrap().size(), 1);
+        assert!(lazy.by_name("a").is_err());
+        assert!(lazy.by_name("c.txt").is_err());
+    }
+}
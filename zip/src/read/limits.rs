@@ -0,0 +1,264 @@
+//! Limits on the resources used to read archives from untrusted sources
+
+use super::ZipArchive;
+use crate::result::{ZipError, ZipResult};
+use crate::types::ZipFileData;
+use std::io::{self, prelude::*};
+
+/// Limits on the resources used to read an archive, to protect against zip bombs.
+///
+/// No limit is set by default. Once any limit is set on an archive with [`ZipArchive::with_limits`]
+/// or [`ZipArchive::set_limits`], reading a file also fails with [`ZipError::LimitExceeded`] if it
+/// decompresses to more data than its declared size, since the declared sizes are what the other
+/// limits are checked against.
+///
+/// ```
+/// let limits = zip::read::Limits::default()
+///     .max_entries(10_000)
+///     .max_total_size(1 << 30)
+///     .max_compression_ratio(100)
+///     .reject_overlapping(true);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    max_entries: Option<usize>,
+    max_total_size: Option<u64>,
+    max_entry_size: Option<u64>,
+    max_compression_ratio: Option<u64>,
+    reject_overlapping: bool,
+}
+
+impl Limits {
+    /// Set the maximum number of files in the archive
+    pub fn max_entries(mut self, max_entries: usize) -> Limits {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Set the maximum uncompressed size of all files together
+    pub fn max_total_size(mut self, max_total_size: u64) -> Limits {
+        self.max_total_size = Some(max_total_size);
+        self
+    }
+
+    /// Set the maximum uncompressed size of a file
+    pub fn max_entry_size(mut self, max_entry_size: u64) -> Limits {
+        self.max_entry_size = Some(max_entry_size);
+        self
+    }
+
+    /// Set the maximum ratio between the uncompressed and compressed size of a file
+    pub fn max_compression_ratio(mut self, max_compression_ratio: u64) -> Limits {
+        self.max_compression_ratio = Some(max_compression_ratio);
+        self
+    }
+
+    /// Set whether archives whose files overlap each other are rejected.
+    ///
+    /// Overlapping files are how the data of a file is reused by many others in the most
+    /// efficient zip bombs, but never occur in archives made by regular tools.
+    pub fn reject_overlapping(mut self, reject_overlapping: bool) -> Limits {
+        self.reject_overlapping = reject_overlapping;
+        self
+    }
+
+    /// Whether any limit is set
+    pub(crate) fn is_set(&self) -> bool {
+        self.max_entries.is_some()
+            || self.max_total_size.is_some()
+            || self.max_entry_size.is_some()
+            || self.max_compression_ratio.is_some()
+            || self.reject_overlapping
+    }
+
+    pub(crate) fn check_entries(&self, entries: usize) -> ZipResult<()> {
+        match self.max_entries {
+            Some(max_entries) if entries > max_entries => {
+                Err(ZipError::LimitExceeded("Too many files"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the declared sizes of a file
+    pub(crate) fn check_size(&self, compressed_size: u64, uncompressed_size: u64) -> ZipResult<()> {
+        self.check_entry_size(uncompressed_size)?;
+        if self
+            .max_compression_ratio
+            .is_some_and(|max_compression_ratio| {
+                uncompressed_size > compressed_size.saturating_mul(max_compression_ratio)
+            })
+        {
+            return Err(ZipError::LimitExceeded("Compression ratio is too high"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_entry_size(&self, size: u64) -> ZipResult<()> {
+        match self.max_entry_size {
+            Some(max_entry_size) if size > max_entry_size => {
+                Err(ZipError::LimitExceeded("File is too large"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_total_size(&self, total_size: u64) -> ZipResult<()> {
+        match self.max_total_size {
+            Some(max_total_size) if total_size > max_total_size => {
+                Err(ZipError::LimitExceeded("Files are too large in total"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the declared sizes of all files, and whether they overlap
+    pub(crate) fn check_files<'a>(
+        &self,
+        files: impl Iterator<Item = &'a ZipFileData>,
+    ) -> ZipResult<()> {
+        let mut total_size = 0u64;
+        let mut ranges = Vec::new();
+        for file in files {
+            self.check_size(file.compressed_size, file.uncompressed_size)?;
+            total_size = total_size.saturating_add(file.uncompressed_size);
+            if self.reject_overlapping {
+                // The extra field of the local header is unknown, so this is the least the file takes
+                let length = 30 + file.file_name_raw.len() as u64 + file.compressed_size;
+                ranges.push((file.header_start, file.header_start.saturating_add(length)));
+            }
+        }
+        self.check_total_size(total_size)?;
+
+        ranges.sort_unstable();
+        let mut end = 0;
+        for (i, (start, range_end)) in ranges.into_iter().enumerate() {
+            if i > 0 && start < end {
+                return Err(ZipError::LimitExceeded("Files overlap"));
+            }
+            end = end.max(range_end);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read + io::Seek> ZipArchive<R> {
+    /// Check the archive against `limits`, and enforce them when reading files from now on.
+    ///
+    /// Fails with [`ZipError::LimitExceeded`] if the archive exceeds them, in which case the
+    /// limits are not changed. For archives opened with [`ZipArchive::new_lazy`], the metadata of
+    /// every file is decoded first.
+    pub fn set_limits(&mut self, limits: Limits) -> ZipResult<()> {
+        limits.check_entries(self.len())?;
+        self.decode_metadata()?;
+        let shared = &*self.shared;
+        limits.check_files((0..self.len()).map(|index| match &shared.lazy {
+            Some(lazy) => lazy.decoded(index).unwrap(),
+            None => &shared.files[index],
+        }))?;
+        self.limits = limits;
+        Ok(())
+    }
+
+    /// Get the limits enforced on this archive
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Limits;
+    use crate::read::test_util::{archive, point_second_file_to_first};
+    use crate::result::ZipError;
+    use crate::{CompressionMethod, ZipArchive};
+    use std::io::{self, Read};
+
+    fn limit_exceeded(result: Result<ZipArchive<io::Cursor<Vec<u8>>>, ZipError>) -> &'static str {
+        match result {
+            Err(ZipError::LimitExceeded(limit)) => limit,
+            _ => panic!("a limit should be exceeded"),
+        }
+    }
+
+    #[test]
+    fn declared_sizes() {
+        let v = archive(CompressionMethod::Stored, 2000);
+        let open = |limits| ZipArchive::with_limits(io::Cursor::new(v.clone()), limits);
+
+        assert_eq!(
+            limit_exceeded(open(Limits::default().max_entries(2))),
+            "Too many files"
+        );
+        assert_eq!(
+            limit_exceeded(open(Limits::default().max_entry_size(9_999))),
+            "File is too large"
+        );
+        assert_eq!(
+            limit_exceeded(open(Limits::default().max_total_size(29_999))),
+            "Files are too large in total"
+        );
+        let limits = Limits::default()
+            .max_entries(3)
+            .max_entry_size(10_000)
+            .max_total_size(30_000)
+            .reject_overlapping(true);
+        assert!(open(limits).is_ok());
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn compression_ratio() {
+        let mut archive =
+            ZipArchive::new(io::Cursor::new(archive(CompressionMethod::Deflated, 2000))).unwrap();
+        match archive.set_limits(Limits::default().max_compression_ratio(10)) {
+            Err(ZipError::LimitExceeded("Compression ratio is too high")) => {}
+            _ => panic!("the compression ratio should be too high"),
+        }
+        assert!(!archive.limits().is_set());
+        archive
+            .set_limits(Limits::default().max_compression_ratio(1000))
+            .unwrap();
+    }
+
+    #[test]
+    fn overlapping_files() {
+        let mut v = archive(CompressionMethod::Stored, 2000);
+        point_second_file_to_first(&mut v);
+
+        assert!(ZipArchive::with_limits(io::Cursor::new(v.clone()), Limits::default()).is_ok());
+        let limits = Limits::default().reject_overlapping(true);
+        assert_eq!(
+            limit_exceeded(ZipArchive::with_limits(io::Cursor::new(v), limits)),
+            "Files overlap"
+        );
+    }
+
+    #[test]
+    fn undeclared_size() {
+        let mut v = archive(CompressionMethod::Stored, 2000);
+        // Declare a smaller uncompressed size for the first file in the central directory
+        let central = v.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        v[central + 24..central + 28].copy_from_slice(&100u32.to_le_bytes());
+
+        let mut archive = ZipArchive::new(io::Cursor::new(v.clone())).unwrap();
+        let mut content = Vec::new();
+        assert!(archive
+            .by_index(0)
+            .unwrap()
+            .read_to_end(&mut content)
+            .is_ok());
+
+        let limits = Limits::default().max_entry_size(10_000);
+        let mut archive = ZipArchive::with_limits(io::Cursor::new(v), limits).unwrap();
+        let err = archive
+            .by_index(0)
+            .unwrap()
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "limit exceeded: File is larger than its declared size"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Borrowed view of the metadata of the files in an archive, without opening them
+
+use super::ffi;
+use crate::compression::CompressionMethod;
+use crate::types::{AesMode, AesVendorVersion, DateTime, System, ZipFileData};
+use std::path::{Component, Path};
+
+/// Metadata of a file in an archive, as read from the central directory.
+///
+/// Unlike [`ZipFile`](super::ZipFile), this does not read from the archive at all.
+/// Obtained with [`ZipArchive::entries`](super::ZipArchive::entries).
+#[derive(Clone, Copy, Debug)]
+pub struct FileMetadata<'a> {
+    index: usize,
+    data: &'a ZipFileData,
+}
+
+impl<'a> FileMetadata<'a> {
+    pub(crate) fn new(index: usize, data: &'a ZipFileData) -> FileMetadata<'a> {
+        FileMetadata { index, data }
+    }
+
+    /// Get the index of this file starting from 0 in the archive
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the version of the file
+    pub fn version_made_by(&self) -> (u8, u8) {
+        (
+            self.data.version_made_by / 10,
+            self.data.version_made_by % 10,
+        )
+    }
+
+    /// Get the name of the file. See [`ZipFile::name`](super::ZipFile::name) for the dangers of using it as a path.
+    pub fn name(&self) -> &'a str {
+        &self.data.file_name
+    }
+
+    /// Get the name of the file, in the raw (internal) byte representation
+    pub fn name_raw(&self) -> &'a [u8] {
+        &self.data.file_name_raw
+    }
+
+    /// Rewrite the path, ignoring any path components with special meaning.
+    /// See [`ZipFile::mangled_name`](super::ZipFile::mangled_name).
+    pub fn mangled_name(&self) -> ::std::path::PathBuf {
+        self.data.file_name_sanitized()
+    }
+
+    /// Ensure the file path is safe to use as a [`Path`].
+    /// See [`ZipFile::enclosed_name`](super::ZipFile::enclosed_name).
+    pub fn enclosed_name(&self) -> Option<&'a Path> {
+        if self.data.file_name.contains('\0') {
+            return None;
+        }
+        let path = Path::new(&self.data.file_name);
+        let mut depth = 0usize;
+        for component in path.components() {
+            match component {
+                Component::Prefix(_) | Component::RootDir => return None,
+                Component::ParentDir => depth = depth.checked_sub(1)?,
+                Component::Normal(_) => depth += 1,
+                Component::CurDir => (),
+            }
+        }
+        Some(path)
+    }
+
+    /// Get the comment of the file
+    pub fn comment(&self) -> &'a str {
+        &self.data.file_comment
+    }
+
+    /// Get the compression method used to store the file
+    pub fn compression(&self) -> CompressionMethod {
+        self.data.compression_method
+    }
+
+    /// Get the size of the file in the archive
+    pub fn compressed_size(&self) -> u64 {
+        self.data.compressed_size
+    }
+
+    /// Get the size of the file when uncompressed
+    pub fn size(&self) -> u64 {
+        self.data.uncompressed_size
+    }
+
+    /// Get the CRC32 hash of the original file
+    pub fn crc32(&self) -> u32 {
+        self.data.crc32
+    }
+
+    /// Get the time the file was last modified
+    pub fn last_modified(&self) -> DateTime {
+        self.data.last_modified_time
+    }
+
+    /// Returns whether the file is actually a directory
+    pub fn is_dir(&self) -> bool {
+        self.name().ends_with(['/', '\\'])
+    }
+
+    /// Returns whether the file is a regular file
+    pub fn is_file(&self) -> bool {
+        !self.is_dir()
+    }
+
+    /// Returns whether the file is encrypted
+    pub fn encrypted(&self) -> bool {
+        self.data.encrypted
+    }
+
+    /// Get the AES mode and vendor version if the file is encrypted with AES
+    pub fn aes_mode(&self) -> Option<(AesMode, AesVendorVersion)> {
+        self.data.aes_mode
+    }
+
+    /// Returns whether the sizes and CRC32 of the file are also stored in a data descriptor after its data
+    pub fn using_data_descriptor(&self) -> bool {
+        self.data.using_data_descriptor
+    }
+
+    /// Get the external attributes of the file, whose meaning depends on the system that made it
+    pub fn external_attributes(&self) -> u32 {
+        self.data.external_attributes
+    }
+
+    /// Get unix mode for the file
+    pub fn unix_mode(&self) -> Option<u32> {
+        if self.data.external_attributes == 0 {
+            return None;
+        }
+
+        match self.data.system {
+            System::Unix => Some(self.data.external_attributes >> 16),
+            System::Dos => {
+                // Interpret MS-DOS directory bit
+                let mut mode = if 0x10 == (self.data.external_attributes & 0x10) {
+                    ffi::S_IFDIR | 0o0775
+                } else {
+                    ffi::S_IFREG | 0o0664
+                };
+                if 0x01 == (self.data.external_attributes & 0x01) {
+                    // Read-only bit; strip write permissions
+                    mode &= 0o0555;
+                }
+                Some(mode)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the extra data of the central directory header for this file.
+    ///
+    /// The extra data of the local header can be read with [`ZipArchive::local_extra_data`](super::ZipArchive::local_extra_data).
+    pub fn extra_data(&self) -> &'a [u8] {
+        &self.data.extra_field
+    }
+
+    /// Get the starting offset of the data of the compressed file.
+    ///
+    /// This is estimated from the central directory, and only exact once the local header of the file
+    /// has been read, for example with [`ZipArchive::resolve_data_offsets`](super::ZipArchive::resolve_data_offsets).
+    pub fn data_start(&self) -> u64 {
+        self.data.data_start.load()
+    }
+
+    /// Get the starting offset of the zip header for this file
+    pub fn header_start(&self) -> u64 {
+        self.data.header_start
+    }
+
+    /// Get the starting offset of the zip header in the central directory for this file
+    pub fn central_header_start(&self) -> u64 {
+        self.data.central_header_start
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::read::test_util::zip;
+    use crate::write::FileOptions;
+    use crate::{CompressionMethod, ZipArchive};
+    use std::io;
+
+    fn archive() -> Vec<u8> {
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .unix_permissions(0o640);
+        zip(&[("a.txt", b"hello", options), ("dir/", b"", options)])
+    }
+
+    #[test]
+    fn entries_match_files() {
+        let mut archive = ZipArchive::new(io::Cursor::new(archive())).unwrap();
+        let entries = archive
+            .entries()
+            .map(|entry| {
+                (
+                    entry.index(),
+                    entry.name().to_owned(),
+                    entry.size(),
+                    entry.crc32(),
+                    entry.unix_mode(),
+                    entry.is_dir(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+
+        for (index, name, size, crc32, mode, is_dir) in entries {
+            let file = archive.by_index(index).unwrap();
+            assert_eq!(file.name(), name);
+            assert_eq!(file.size(), size);
+            assert_eq!(file.crc32(), crc32);
+            assert_eq!(file.unix_mode(), mode);
+            assert_eq!(file.is_dir(), is_dir);
+        }
+        assert_eq!(archive.entries().next().unwrap().unix_mode(), Some(0o100640));
+    }
+
+    #[test]
+    fn lazy_entries() {
+        let mut archive = ZipArchive::new_lazy(io::Cursor::new(archive())).unwrap();
+        assert_eq!(archive.entries().count(), 0);
+        archive.by_name("dir/").unwrap();
+        assert_eq!(archive.entries().map(|e| e.index()).collect::<Vec<_>>(), [1]);
+        archive.decode_metadata().unwrap();
+        assert_eq!(archive.entries().count(), 2);
+    }
+}
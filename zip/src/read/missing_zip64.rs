@@ -0,0 +1,242 @@
+//! Heuristics for archives over the limits of the format that were written without Zip64
+//!
+//! Such archives store their number of files modulo 65536, and their offsets modulo 4GiB.
+
+use crate::result::ZipResult;
+use crate::spec;
+use crate::types::ZipFileData;
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{self, prelude::*};
+
+/// Amount by which truncated 32 bit offsets wrap around
+const WRAP: u64 = 1 << 32;
+
+/// Check whether a central directory header starts at the current position, without moving past it
+pub(super) fn next_is_central_header<R: Read + io::Seek>(reader: &mut R) -> ZipResult<bool> {
+    let position = reader.stream_position()?;
+    let found = signature_at(reader, position, spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE)?;
+    reader.seek(io::SeekFrom::Start(position))?;
+    Ok(found)
+}
+
+/// Check whether `signature` is found at `position`
+fn signature_at<R: Read + io::Seek>(
+    reader: &mut R,
+    position: u64,
+    signature: u32,
+) -> ZipResult<bool> {
+    reader.seek(io::SeekFrom::Start(position))?;
+    match reader.read_u32::<LittleEndian>() {
+        Ok(found) => Ok(found == signature),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Find the start of the central directory and the archive offset, given the ones computed from
+/// the end of central directory record.
+///
+/// An archive offset over 4GiB means the directory is further than its offset says, which is
+/// most likely because the offset was truncated. The directory is then looked for where its size
+/// says it starts, and at every position the offset may have been truncated from, the last first.
+/// The archive offset is whatever is left once the multiples of 4GiB are removed.
+pub(super) fn locate_directory<R: Read + io::Seek>(
+    reader: &mut R,
+    footer: &spec::CentralDirectoryEnd,
+    cde_start_pos: u64,
+    archive_offset: u64,
+    directory_start: u64,
+) -> ZipResult<(u64, u64)> {
+    if archive_offset < WRAP {
+        return Ok((archive_offset, directory_start));
+    }
+
+    let offset = footer.central_directory_offset as u64;
+    let truncated_from = (0..=(cde_start_pos - offset) / WRAP)
+        .rev()
+        .map(|wraps| offset + wraps * WRAP);
+    for start in std::iter::once(directory_start).chain(truncated_from) {
+        if signature_at(reader, start, spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE)? {
+            return Ok(((start - offset) % WRAP, start));
+        }
+    }
+    Ok((archive_offset, directory_start))
+}
+
+/// Whether the offsets of the archive may have been truncated to 32 bits, given the archive offset
+/// and start of the central directory found by [`locate_directory`]
+pub(super) fn offsets_truncated(
+    footer: &spec::CentralDirectoryEnd,
+    archive_offset: u64,
+    directory_start: u64,
+) -> bool {
+    // Archives with Zip64 records saturate the offset instead of truncating it
+    footer.central_directory_offset != u32::MAX && directory_start - archive_offset >= WRAP
+}
+
+/// Correct the local header offsets that were truncated to 32 bits, for files past 4GiB.
+///
+/// Files are usually stored in central directory order, so a file starting before the end of the
+/// previous one is moved by multiples of 4GiB until it doesn't, as long as it stays before the
+/// central directory and a local header is found there.
+pub(super) fn fix_header_offsets<R: Read + io::Seek>(
+    reader: &mut R,
+    files: &mut [ZipFileData],
+    directory_start: u64,
+) -> ZipResult<()> {
+    let mut previous_end = 0;
+    for file in files {
+        previous_end = fix_header_offset(reader, file, previous_end, directory_start)?;
+    }
+    Ok(())
+}
+
+/// Correct the local header offset of a single file like [`fix_header_offsets`], given where the
+/// previous file ends, and return where this file ends
+pub(super) fn fix_header_offset<R: Read + io::Seek>(
+    reader: &mut R,
+    file: &mut ZipFileData,
+    previous_end: u64,
+    directory_start: u64,
+) -> ZipResult<u64> {
+    let mut header_start = file.header_start;
+    while header_start < previous_end && header_start + WRAP < directory_start {
+        header_start += WRAP;
+    }
+    if header_start != file.header_start
+        && signature_at(reader, header_start, spec::LOCAL_FILE_HEADER_SIGNATURE)?
+    {
+        let shift = header_start - file.header_start;
+        file.header_start = header_start;
+        file.data_start.store(file.data_start.load() + shift);
+    }
+    Ok(file.data_start.load() + file.compressed_size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::WRAP;
+    use crate::read::test_util::archive;
+    use crate::{CompressionMethod, ZipArchive};
+    use std::io::{self, Read, Seek};
+
+    fn read(archive: &mut ZipArchive<impl Read + Seek>, name: &str) -> String {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn wrapped_file_count() {
+        let mut v = archive(CompressionMethod::Stored, 10);
+        // 65539 files would be declared as 3, this declares 1 file for 3
+        let end = v.windows(4).rposition(|w| w == b"PK\x05\x06").unwrap();
+        v[end + 8] = 1;
+        v[end + 10] = 1;
+
+        let mut archive = ZipArchive::new(io::Cursor::new(v.clone())).unwrap();
+        assert_eq!(archive.len(), 3);
+        assert_eq!(read(&mut archive, "c.txt"), "c.txt".repeat(10));
+        let mut lazy = ZipArchive::new_lazy(io::Cursor::new(v)).unwrap();
+        assert_eq!(lazy.len(), 3);
+        assert_eq!(read(&mut lazy, "c.txt"), "c.txt".repeat(10));
+    }
+
+    /// A reader over a large file made of zeros, except for a few segments of data
+    #[derive(Clone)]
+    struct Sparse {
+        segments: Vec<(u64, Vec<u8>)>,
+        len: u64,
+        position: u64,
+    }
+
+    impl Read for Sparse {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let remaining = self.len.saturating_sub(self.position);
+            let mut count = (buf.len() as u64).min(remaining) as usize;
+            for (start, data) in &self.segments {
+                let end = start + data.len() as u64;
+                if (*start..end).contains(&self.position) {
+                    let offset = (self.position - start) as usize;
+                    count = count.min(data.len() - offset);
+                    buf[..count].copy_from_slice(&data[offset..offset + count]);
+                    self.position += count as u64;
+                    return Ok(count);
+                }
+                if *start > self.position {
+                    count = count.min((start - self.position) as usize);
+                }
+            }
+            buf[..count].iter_mut().for_each(|b| *b = 0);
+            self.position += count as u64;
+            Ok(count)
+        }
+    }
+
+    impl Seek for Sparse {
+        fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+            self.position = match pos {
+                io::SeekFrom::Start(position) => position,
+                io::SeekFrom::End(offset) => (self.len as i64 + offset) as u64,
+                io::SeekFrom::Current(offset) => (self.position as i64 + offset) as u64,
+            };
+            Ok(self.position)
+        }
+    }
+
+    #[test]
+    fn truncated_offsets() {
+        let v = archive(CompressionMethod::Stored, 10);
+        let local_headers: Vec<_> = v
+            .windows(4)
+            .enumerate()
+            .filter(|(_, w)| *w == b"PK\x03\x04")
+            .map(|(i, _)| i)
+            .collect();
+        let directory = v.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        let end = v.windows(4).rposition(|w| w == b"PK\x05\x06").unwrap();
+
+        // Move c.txt and the central directory past 4GiB, with the offsets truncated to 32 bits
+        let moved = WRAP + 64;
+        let shift = moved - local_headers[2] as u64;
+        let mut directory_data = v[directory..].to_vec();
+        let c_header = directory_data
+            .windows(4)
+            .enumerate()
+            .filter(|(_, w)| *w == b"PK\x01\x02")
+            .nth(2)
+            .unwrap()
+            .0;
+        directory_data[c_header + 42..c_header + 46].copy_from_slice(&(moved as u32).to_le_bytes());
+        let directory_offset = ((directory as u64 + shift) as u32).to_le_bytes();
+        let end = end - directory;
+        directory_data[end + 16..end + 20].copy_from_slice(&directory_offset);
+
+        let sparse = Sparse {
+            len: directory as u64 + shift + directory_data.len() as u64,
+            segments: vec![
+                (0, v[..local_headers[2]].to_vec()),
+                (moved, v[local_headers[2]..directory].to_vec()),
+                (directory as u64 + shift, directory_data),
+            ],
+            position: 0,
+        };
+        let mut archive = ZipArchive::new(sparse.clone()).unwrap();
+        assert_eq!(archive.offset(), 0);
+        assert_eq!(read(&mut archive, "a.txt"), "a.txt".repeat(10));
+        assert_eq!(read(&mut archive, "b.txt"), "b.txt".repeat(10));
+        assert_eq!(read(&mut archive, "c.txt"), "c.txt".repeat(10));
+        assert_eq!(archive.by_name("c.txt").unwrap().header_start(), moved);
+
+        // Lazily read archives correct the offsets as files are decoded, in any order
+        let mut lazy = ZipArchive::new_lazy(sparse).unwrap();
+        assert_eq!(read(&mut lazy, "c.txt"), "c.txt".repeat(10));
+        assert_eq!(lazy.by_name("c.txt").unwrap().header_start(), moved);
+        assert_eq!(read(&mut lazy, "a.txt"), "a.txt".repeat(10));
+        assert_eq!(read(&mut lazy, "b.txt"), "b.txt".repeat(10));
+    }
+}
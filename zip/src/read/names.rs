@@ -0,0 +1,408 @@
+//! Detection of file names that conflict with each other or can't be created on some file systems
+
+use super::ZipArchive;
+use crate::result::ZipResult;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, prelude::*};
+use unicode_normalization::UnicodeNormalization;
+
+/// A problem with the name of a file when extracting it, found by [`ZipArchive::analyze_names`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NameIssue {
+    /// Two files have the same name, so the later one replaces the earlier one
+    Duplicate {
+        /// Index of the later file
+        index: usize,
+        /// Index of the earlier file
+        other: usize,
+    },
+    /// Two file names only differ by case, so they collide on case-insensitive file systems
+    CaseCollision {
+        /// Index of the later file
+        index: usize,
+        /// Index of the earlier file
+        other: usize,
+    },
+    /// Two file names only differ by their Unicode normalization (NFC or NFD), so they collide on
+    /// file systems that normalize names
+    NormalizationCollision {
+        /// Index of the later file
+        index: usize,
+        /// Index of the earlier file
+        other: usize,
+    },
+    /// A file has the same path as a directory, which is either a file ending with `/` or the
+    /// parent directory of another file
+    FileDirectoryConflict {
+        /// Index of the file
+        file: usize,
+        /// Index of the first file using the path as a directory
+        directory: usize,
+    },
+    /// A file name can't be created on FAT and exFAT file systems
+    InvalidOnFat {
+        /// Index of the file
+        index: usize,
+        /// Why the name is invalid
+        reason: &'static str,
+    },
+}
+
+impl NameIssue {
+    /// Get the index of the file with the problem, which is the later one for collisions
+    pub fn index(&self) -> usize {
+        match *self {
+            NameIssue::Duplicate { index, .. }
+            | NameIssue::CaseCollision { index, .. }
+            | NameIssue::NormalizationCollision { index, .. }
+            | NameIssue::InvalidOnFat { index, .. } => index,
+            NameIssue::FileDirectoryConflict { file, directory } => file.max(directory),
+        }
+    }
+}
+
+impl fmt::Display for NameIssue {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NameIssue::Duplicate { index, other } => {
+                write!(fmt, "file {} has the same name as file {}", index, other)
+            }
+            NameIssue::CaseCollision { index, other } => write!(
+                fmt,
+                "file {} has the same name as file {} when ignoring case",
+                index, other
+            ),
+            NameIssue::NormalizationCollision { index, other } => write!(
+                fmt,
+                "file {} has the same name as file {} once normalized",
+                index, other
+            ),
+            NameIssue::FileDirectoryConflict { file, directory } => write!(
+                fmt,
+                "file {} has the same path as a directory used by file {}",
+                file, directory
+            ),
+            NameIssue::InvalidOnFat { index, reason } => {
+                write!(fmt, "file {} can't be created on FAT: {}", index, reason)
+            }
+        }
+    }
+}
+
+/// The problems with the names of the files of an archive, see [`ZipArchive::analyze_names`]
+#[derive(Clone, Debug)]
+pub struct NameReport {
+    issues: Vec<NameIssue>,
+}
+
+impl NameReport {
+    /// Whether every file can be extracted under its own name
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The problems found, in archive order
+    pub fn issues(&self) -> &[NameIssue] {
+        &self.issues
+    }
+}
+
+impl<R: Read + io::Seek> ZipArchive<R> {
+    /// Find the files whose names would conflict with each other when extracted, or that can't be
+    /// created on FAT and exFAT file systems, such as the external storage of Android devices.
+    ///
+    /// Names are compared like case-insensitive file systems that normalize Unicode would, and
+    /// directories that only differ by case are not reported, since their files end up in the
+    /// same directory. See [`ExtractOptions::conflicts`](super::ExtractOptions::conflicts) to
+    /// handle these files when extracting.
+    ///
+    /// For archives opened with [`ZipArchive::new_lazy`], the metadata of every file is decoded
+    /// first.
+    pub fn analyze_names(&mut self) -> ZipResult<NameReport> {
+        self.decode_metadata()?;
+        let mut names = Names::default();
+        let mut issues = Vec::new();
+        for file in self.entries() {
+            let name = file.name().trim_end_matches('/');
+            names.check(file.index(), name, file.is_dir(), &mut issues);
+        }
+        Ok(NameReport { issues })
+    }
+}
+
+/// The paths used by the files of an archive so far, in archive order.
+///
+/// Paths are relative, with components separated by `/` and no trailing `/`. Maps are keyed by
+/// folded paths, as compared by case-insensitive file systems, and hold the first file using them.
+#[derive(Debug, Default)]
+pub(crate) struct Names {
+    exact: HashMap<String, usize>,
+    normalized: HashMap<String, usize>,
+    files: HashMap<String, usize>,
+    directories: HashMap<String, usize>,
+    /// The paths that directories were renamed to, by their original folded path
+    renamed_directories: HashMap<String, String>,
+}
+
+impl Names {
+    /// Record the path of a file, and add its problems with the previous files to `issues`
+    pub(crate) fn check(
+        &mut self,
+        index: usize,
+        path: &str,
+        is_dir: bool,
+        issues: &mut Vec<NameIssue>,
+    ) {
+        if let Some(reason) = path.split('/').find_map(fat_problem) {
+            issues.push(NameIssue::InvalidOnFat { index, reason });
+        }
+
+        let mut directory = String::new();
+        for component in directories(path, is_dir) {
+            if !directory.is_empty() {
+                directory.push('/');
+            }
+            directory.push_str(component);
+            let key = fold(&directory);
+            if self.directories.contains_key(&key) {
+                continue;
+            }
+            if let Some(&file) = self.files.get(&key) {
+                issues.push(NameIssue::FileDirectoryConflict {
+                    file,
+                    directory: index,
+                });
+            }
+            self.directories.insert(key, index);
+        }
+        if is_dir {
+            return;
+        }
+
+        let key = fold(path);
+        if let Some(&directory) = self.directories.get(&key) {
+            issues.push(NameIssue::FileDirectoryConflict {
+                file: index,
+                directory,
+            });
+        }
+        let normalized = path.nfc().collect::<String>();
+        if let Some(&other) = self.exact.get(path) {
+            issues.push(NameIssue::Duplicate { index, other });
+        } else if let Some(&other) = self.normalized.get(&normalized) {
+            issues.push(NameIssue::NormalizationCollision { index, other });
+        } else if let Some(&other) = self.files.get(&key) {
+            issues.push(NameIssue::CaseCollision { index, other });
+        }
+        self.exact.entry(path.to_owned()).or_insert(index);
+        self.normalized.entry(normalized).or_insert(index);
+        self.files.entry(key).or_insert(index);
+    }
+
+    /// Record the path of a file, renamed so it has none of the problems reported by
+    /// [`Names::check`], and return it.
+    ///
+    /// Characters FAT doesn't allow are replaced with `_`, and names conflicting with a previous
+    /// file are numbered. Files in a renamed directory are moved to the new directory.
+    pub(crate) fn rename(&mut self, index: usize, path: &str, is_dir: bool) -> String {
+        let mut original = String::new();
+        let mut renamed = String::new();
+        let mut components = path.split('/').peekable();
+        while let Some(component) = components.next() {
+            if !original.is_empty() {
+                original.push('/');
+            }
+            original.push_str(component);
+            let component = sanitize(component);
+
+            if components.peek().is_some() || is_dir {
+                let original_key = fold(&original);
+                if let Some(directory) = self.renamed_directories.get(&original_key) {
+                    renamed = directory.clone();
+                    continue;
+                }
+                renamed = numbered(&renamed, &component, |key| self.files.contains_key(key));
+                self.directories.entry(fold(&renamed)).or_insert(index);
+                self.renamed_directories
+                    .insert(original_key, renamed.clone());
+            } else {
+                renamed = numbered(&renamed, &component, |key| {
+                    self.files.contains_key(key) || self.directories.contains_key(key)
+                });
+                self.files.insert(fold(&renamed), index);
+            }
+        }
+        renamed
+    }
+}
+
+/// Get the directories a path consists of, which includes the path itself for directories
+fn directories(path: &str, is_dir: bool) -> impl Iterator<Item = &str> {
+    let mut components = path.split('/').collect::<Vec<_>>();
+    if !is_dir {
+        components.pop();
+    }
+    components.into_iter()
+}
+
+/// Fold a path like case-insensitive file systems that normalize names compare them
+fn fold(path: &str) -> String {
+    path.nfc().flat_map(char::to_lowercase).collect()
+}
+
+/// Join `name` to `parent`, numbering it until `is_taken` is false for the folded path
+fn numbered(parent: &str, name: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    let join = |name: &str| match parent.is_empty() {
+        true => name.to_owned(),
+        false => format!("{}/{}", parent, name),
+    };
+    let path = join(name);
+    if !is_taken(&fold(&path)) {
+        return path;
+    }
+
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+    (1..)
+        .map(|n| join(&format!("{} ({}){}", stem, n, extension)))
+        .find(|path| !is_taken(&fold(path)))
+        .unwrap()
+}
+
+/// Characters that FAT doesn't allow in names, besides control characters
+const FAT_RESERVED_CHARS: &[char] = &['"', '*', '/', ':', '<', '>', '?', '\\', '|'];
+
+/// Names of devices, which can't be used as file names on FAT even with an extension
+const FAT_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Find why a component of a path can't be created on FAT and exFAT, if it can't
+fn fat_problem(component: &str) -> Option<&'static str> {
+    if component
+        .chars()
+        .any(|c| c.is_control() || FAT_RESERVED_CHARS.contains(&c))
+    {
+        return Some("reserved character");
+    }
+    if component.ends_with('.') || component.ends_with(' ') {
+        return Some("trailing dot or space");
+    }
+    if is_reserved_name(component) {
+        return Some("reserved device name");
+    }
+    if component.encode_utf16().count() > 255 {
+        return Some("name longer than 255 characters");
+    }
+    None
+}
+
+fn is_reserved_name(component: &str) -> bool {
+    let stem = component.split('.').next().unwrap_or_default().trim_end();
+    FAT_RESERVED_NAMES
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(stem))
+}
+
+/// Make a component of a path valid on FAT, except for its length
+fn sanitize(component: &str) -> Cow<'_, str> {
+    if fat_problem(component).is_none() {
+        return Cow::Borrowed(component);
+    }
+    let mut sanitized = component
+        .chars()
+        .map(
+            |c| match c.is_control() || FAT_RESERVED_CHARS.contains(&c) {
+                true => '_',
+                false => c,
+            },
+        )
+        .collect::<String>();
+    let trailing = sanitized.len() - sanitized.trim_end_matches(&['.', ' '][..]).len();
+    sanitized.truncate(sanitized.len() - trailing);
+    sanitized.extend(std::iter::repeat_n('_', trailing));
+    if is_reserved_name(&sanitized) {
+        sanitized.insert(0, '_');
+    }
+    Cow::Owned(sanitized)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sanitize, NameIssue, Names};
+    use crate::write::FileOptions;
+    use crate::{ZipArchive, ZipWriter};
+    use std::io;
+
+    #[test]
+    fn analyze_names() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let names = [
+            "Res/a.png",
+            "res/a.png",
+            "res/b.png",
+            "caf\u{e9}.txt",
+            "cafe\u{301}.txt",
+            "res/b.png",
+            "lib",
+            "lib/x.so",
+            "aux.txt",
+            "what?.txt",
+        ];
+        for name in names.iter() {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+        }
+        let v = writer.finish().unwrap().into_inner();
+
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        let report = archive.analyze_names().unwrap();
+        assert_eq!(
+            report.issues(),
+            [
+                NameIssue::CaseCollision { index: 1, other: 0 },
+                NameIssue::NormalizationCollision { index: 4, other: 3 },
+                NameIssue::Duplicate { index: 5, other: 2 },
+                NameIssue::FileDirectoryConflict {
+                    file: 6,
+                    directory: 7
+                },
+                NameIssue::InvalidOnFat {
+                    index: 8,
+                    reason: "reserved device name"
+                },
+                NameIssue::InvalidOnFat {
+                    index: 9,
+                    reason: "reserved character"
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rename() {
+        let mut names = Names::default();
+        let mut rename = |path, is_dir| names.rename(0, path, is_dir);
+        assert_eq!(rename("Res/a.png", false), "Res/a.png");
+        assert_eq!(rename("res/a.png", false), "Res/a (1).png");
+        assert_eq!(rename("RES/A.png", false), "Res/A (2).png");
+        assert_eq!(rename("lib", false), "lib");
+        assert_eq!(rename("lib/x.so", false), "lib (1)/x.so");
+        assert_eq!(rename("lib/y.so", false), "lib (1)/y.so");
+        assert_eq!(rename("Res", false), "Res (1)");
+        assert_eq!(rename("con.txt", false), "_con.txt");
+        assert_eq!(rename("a:b/c.", false), "a_b/c_");
+    }
+
+    #[test]
+    fn sanitize_names() {
+        assert_eq!(sanitize("valid.txt"), "valid.txt");
+        assert_eq!(sanitize("what?.txt"), "what_.txt");
+        assert_eq!(sanitize("trailing.. "), "trailing___");
+        assert_eq!(sanitize("NUL"), "_NUL");
+    }
+}
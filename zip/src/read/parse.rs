@@ -0,0 +1,353 @@
+//! Options controlling how strictly archives are parsed, and the problems found in odd archives
+
+use super::HeaderField;
+use crate::result::{ZipError, ZipResult};
+use std::fmt;
+use std::sync::Arc;
+
+/// How strictly an archive is parsed by [`ZipArchive::with_options`](super::ZipArchive::with_options)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Parse archives like [`ZipArchive::new`](super::ZipArchive::new).
+    ///
+    /// Extra fields that end early are ignored, other malformed metadata and CRC32 mismatches
+    /// fail, and local headers are not compared with the central directory.
+    #[default]
+    Standard,
+    /// Fail on any problem, including the ones [`ParseMode::Standard`] ignores or doesn't check
+    Strict,
+    /// Report problems as [`Diagnostic`]s and keep going as long as the files can be located.
+    ///
+    /// Malformed extra fields are skipped, local headers that differ from the central directory
+    /// are reported, and files whose CRC32 doesn't match can still be read.
+    Lenient,
+}
+
+type Sink = Arc<dyn Fn(&Diagnostic) + Send + Sync>;
+
+/// Options for parsing an archive with [`ZipArchive::with_options`](super::ZipArchive::with_options)
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+///
+/// let diagnostics = Arc::new(Mutex::new(Vec::new()));
+/// let sink = diagnostics.clone();
+/// let options = zip::read::ParseOptions::lenient()
+///     .diagnostics(move |diagnostic| sink.lock().unwrap().push(diagnostic.to_string()));
+/// ```
+#[derive(Clone, Default)]
+pub struct ParseOptions {
+    mode: ParseMode,
+    sink: Option<Sink>,
+}
+
+/// A problem found while parsing or reading an archive
+#[derive(Debug)]
+pub enum Diagnostic {
+    /// An extra field of a file could not be parsed
+    MalformedExtraField {
+        /// Name of the file
+        name: String,
+        /// Why the extra field could not be parsed
+        error: ZipError,
+    },
+    /// A file is AES encrypted but has no AES extra field, so it can't be decrypted
+    MissingAesExtraField {
+        /// Name of the file
+        name: String,
+    },
+    /// The local header of a file could not be read
+    UnreadableLocalHeader {
+        /// Name of the file
+        name: String,
+        /// Why the local header could not be read
+        error: ZipError,
+    },
+    /// A field of the local header of a file differs from the central directory
+    LocalHeaderMismatch {
+        /// Name of the file
+        name: String,
+        /// The field that differs
+        field: HeaderField,
+    },
+    /// The number of central directory headers differs from the end of central directory record
+    EntryCountMismatch {
+        /// Number of files in the end of central directory record
+        declared: u64,
+        /// Number of central directory headers found
+        found: u64,
+    },
+    /// The CRC32 of the data of a file doesn't match the central directory
+    Crc32Mismatch {
+        /// Name of the file
+        name: String,
+        /// CRC32 from the central directory
+        expected: u32,
+        /// CRC32 of the data
+        actual: u32,
+    },
+}
+
+impl fmt::Debug for ParseOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParseOptions")
+            .field("mode", &self.mode)
+            .field("sink", &self.sink.is_some())
+            .finish()
+    }
+}
+
+impl ParseOptions {
+    /// Construct options that fail on any problem
+    pub fn strict() -> ParseOptions {
+        ParseOptions::default().mode(ParseMode::Strict)
+    }
+
+    /// Construct options that report problems and keep going
+    pub fn lenient() -> ParseOptions {
+        ParseOptions::default().mode(ParseMode::Lenient)
+    }
+
+    /// Set how strictly the archive is parsed
+    pub fn mode(mut self, mode: ParseMode) -> ParseOptions {
+        self.mode = mode;
+        self
+    }
+
+    /// Set a function called with every problem found, including the ones that don't fail in the
+    /// current mode.
+    ///
+    /// CRC32 mismatches are only found once the data of a file has been read.
+    pub fn diagnostics<F>(mut self, sink: F) -> ParseOptions
+    where
+        F: Fn(&Diagnostic) + Send + Sync + 'static,
+    {
+        self.sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Whether problems are tolerated, including CRC32 mismatches when reading files
+    pub(crate) fn is_lenient(&self) -> bool {
+        self.mode == ParseMode::Lenient
+    }
+
+    /// Whether the records of the archive are checked against each other
+    pub(crate) fn checks_records(&self) -> bool {
+        self.mode != ParseMode::Standard
+    }
+
+    pub(crate) fn report(&self, diagnostic: &Diagnostic) {
+        if let Some(sink) = &self.sink {
+            sink(diagnostic);
+        }
+    }
+
+    /// Report a problem, and fail if the mode doesn't tolerate it
+    pub(crate) fn check(&self, diagnostic: Diagnostic) -> ZipResult<()> {
+        self.report(&diagnostic);
+        let fails = match self.mode {
+            ParseMode::Standard => diagnostic.fails_by_default(),
+            ParseMode::Strict => true,
+            ParseMode::Lenient => false,
+        };
+        match fails {
+            true => Err(diagnostic.into_error()),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Whether the problem fails in [`ParseMode::Standard`]
+    fn fails_by_default(&self) -> bool {
+        match self {
+            Diagnostic::MalformedExtraField { error, .. } => !matches!(error, ZipError::Io(_)),
+            Diagnostic::MissingAesExtraField { .. } | Diagnostic::Crc32Mismatch { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn into_error(self) -> ZipError {
+        match self {
+            Diagnostic::MalformedExtraField {
+                error: ZipError::Io(_),
+                ..
+            } => ZipError::InvalidArchive("Malformed extra field"),
+            Diagnostic::MalformedExtraField { error, .. }
+            | Diagnostic::UnreadableLocalHeader { error, .. } => error,
+            Diagnostic::MissingAesExtraField { .. } => {
+                ZipError::InvalidArchive("AES encryption without AES extra data field")
+            }
+            Diagnostic::LocalHeaderMismatch { .. } => {
+                ZipError::InvalidArchive("Local file header differs from the central directory")
+            }
+            Diagnostic::EntryCountMismatch { .. } => {
+                ZipError::InvalidArchive("Number of files differs from the central directory end")
+            }
+            Diagnostic::Crc32Mismatch {
+                name,
+                expected,
+                actual,
+            } => ZipError::Crc32Mismatch {
+                name,
+                expected,
+                actual,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::MalformedExtraField { name, error } => {
+                write!(fmt, "{}: malformed extra field: {}", name, error)
+            }
+            Diagnostic::MissingAesExtraField { name } => {
+                write!(fmt, "{}: AES encryption without AES extra field", name)
+            }
+            Diagnostic::UnreadableLocalHeader { name, error } => {
+                write!(fmt, "{}: unreadable local header: {}", name, error)
+            }
+            Diagnostic::LocalHeaderMismatch { name, field } => {
+                write!(fmt, "{}: {:?} of the local header differs", name, field)
+            }
+            Diagnostic::EntryCountMismatch { declared, found } => write!(
+                fmt,
+                "found {} files instead of the {} declared",
+                found, declared
+            ),
+            Diagnostic::Crc32Mismatch {
+                name,
+                expected,
+                actual,
+            } => write!(
+                fmt,
+                "{}: CRC32 is {:08x} instead of {:08x}",
+                name, actual, expected
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ParseOptions;
+    use crate::read::test_util::archive;
+    use crate::read::HeaderField;
+    use crate::result::{ZipError, ZipResult};
+    use crate::write::FileOptions;
+    use crate::{CompressionMethod, ZipArchive, ZipWriter};
+    use std::io::{self, Read, Write};
+    use std::sync::{Arc, Mutex};
+
+    type Diagnostics = Arc<Mutex<Vec<String>>>;
+
+    /// Open an archive, collecting the diagnostics as strings
+    fn open(
+        v: Vec<u8>,
+        options: ParseOptions,
+    ) -> (ZipResult<ZipArchive<io::Cursor<Vec<u8>>>>, Diagnostics) {
+        let diagnostics = Arc::new(Mutex::new(Vec::new()));
+        let sink = diagnostics.clone();
+        let options = options
+            .diagnostics(move |diagnostic| sink.lock().unwrap().push(diagnostic.to_string()));
+        (
+            ZipArchive::with_options(io::Cursor::new(v), options),
+            diagnostics,
+        )
+    }
+
+    #[test]
+    fn malformed_extra_field() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file_with_extra_data("a.txt", options).unwrap();
+        writer.write_all(b"\xfe\xca\0\0\xef\xbe\0\0").unwrap();
+        writer.end_extra_data().unwrap();
+        writer.write_all(b"a.txt").unwrap();
+        let mut v = writer.finish().unwrap().into_inner();
+        // Turn the first field of the central directory into an AES extra field that ends early
+        let header = v.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        v[header + 51..header + 55].copy_from_slice(b"\x01\x99\x07\0");
+
+        let (archive, diagnostics) = open(v.clone(), ParseOptions::default());
+        assert!(archive.is_ok());
+        assert_eq!(diagnostics.lock().unwrap().len(), 1);
+        assert!(diagnostics.lock().unwrap()[0].starts_with("a.txt: malformed extra field"));
+
+        let (archive, _) = open(v, ParseOptions::strict());
+        match archive {
+            Err(ZipError::InvalidArchive("Malformed extra field")) => {}
+            e => panic!("unexpected result: {:?}", e.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn local_header_mismatch() {
+        let mut v = archive(CompressionMethod::Stored, 1);
+        // Change the CRC32 in the local header of a.txt
+        v[14] ^= 0xff;
+
+        let (archive, diagnostics) = open(v.clone(), ParseOptions::lenient());
+        assert_eq!(archive.unwrap().len(), 3);
+        let diagnostics = diagnostics.lock().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0],
+            format!(
+                "a.txt: {:?} of the local header differs",
+                HeaderField::Crc32
+            )
+        );
+
+        assert!(ZipArchive::new(io::Cursor::new(v.clone())).is_ok());
+        assert!(open(v, ParseOptions::strict()).0.is_err());
+    }
+
+    #[test]
+    fn entry_count_mismatch() {
+        let mut v = archive(CompressionMethod::Stored, 1);
+        // Declare 2 files instead of 3 in the end of central directory record
+        let end = v.windows(4).rposition(|w| w == b"PK\x05\x06").unwrap();
+        v[end + 8] = 2;
+        v[end + 10] = 2;
+
+        assert_eq!(
+            ZipArchive::new(io::Cursor::new(v.clone())).unwrap().len(),
+            3
+        );
+        let (archive, diagnostics) = open(v.clone(), ParseOptions::lenient());
+        assert_eq!(archive.unwrap().len(), 3);
+        assert_eq!(
+            *diagnostics.lock().unwrap(),
+            ["found 3 files instead of the 2 declared"]
+        );
+        assert!(open(v, ParseOptions::strict()).0.is_err());
+    }
+
+    #[test]
+    fn crc32_mismatch() {
+        let mut v = archive(CompressionMethod::Stored, 1);
+        // Change the data of c.txt
+        let data = v.windows(5).position(|w| w == b"c.txt").unwrap() + 5;
+        v[data] = b'C';
+
+        let (archive, diagnostics) = open(v.clone(), ParseOptions::lenient());
+        let mut content = String::new();
+        archive
+            .unwrap()
+            .by_name("c.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "C.txt");
+        let diagnostics = diagnostics.lock().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].starts_with("c.txt: CRC32 is"));
+
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        let mut file = archive.by_name("c.txt").unwrap();
+        assert!(file.read_to_string(&mut content).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! Recovery of the files of archives whose central directory is missing or corrupt
+
+use super::{
+    read_local_header_at, read_zipfile_from_bufread, zip_archive, Limits, ParseOptions, ZipArchive,
+};
+use crate::result::{ZipError, ZipResult};
+use crate::spec;
+use crate::types::ZipFileData;
+use std::collections::HashMap;
+use std::io::{self, prelude::*};
+use std::sync::Arc;
+
+/// A file that [`ZipArchive::recover`] found but could not recover
+#[derive(Debug)]
+pub struct Unrecoverable {
+    offset: u64,
+    name: Option<String>,
+    error: ZipError,
+}
+
+impl Unrecoverable {
+    /// Get the offset of the local header of the file
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the name of the file, if its local header could be read
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Get the error that prevented recovering the file
+    pub fn error(&self) -> &ZipError {
+        &self.error
+    }
+}
+
+impl<R: Read + io::Seek> ZipArchive<R> {
+    /// Read the files of an archive from their local headers, ignoring the central directory.
+    ///
+    /// This is meant for archives that [`ZipArchive::new`] can't read because their central
+    /// directory is missing or corrupt, for example when they were truncated. The archive is
+    /// scanned for local file headers, and files whose data is followed by a data descriptor are
+    /// decompressed to find their sizes. Everything that looks like a local header but can't be
+    /// recovered is returned along with the archive, such as a file cut off by the end of the
+    /// archive, or an encrypted file with a data descriptor.
+    ///
+    /// Since it is only stored in the central directory, the recovered files have no comment and
+    /// no unix permissions, and the archive has no comment.
+    ///
+    /// ```no_run
+    /// # fn main() -> zip::result::ZipResult<()> {
+    /// let file = std::fs::File::open("truncated.zip")?;
+    /// let (mut archive, unrecoverable) = zip::ZipArchive::recover(file)?;
+    /// for file in unrecoverable {
+    ///     eprintln!("Lost the file at {}: {}", file.offset(), file.error());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn recover(mut reader: R) -> ZipResult<(ZipArchive<R>, Vec<Unrecoverable>)> {
+        let length = reader.seek(io::SeekFrom::End(0))?;
+        let mut files = Vec::new();
+        let mut names_map = HashMap::new();
+        let mut unrecoverable = Vec::new();
+
+        let mut position = 0;
+        while let Some(offset) = find_local_header(&mut reader, position, length)? {
+            match recover_file(&mut reader, offset, length) {
+                Ok(file) => {
+                    position = file.data_start.load() + file.compressed_size;
+                    names_map.insert(file.file_name.clone(), files.len());
+                    files.push(file);
+                }
+                Err((name, error)) => {
+                    unrecoverable.push(Unrecoverable {
+                        offset,
+                        name,
+                        error,
+                    });
+                    position = offset + 4;
+                }
+            }
+        }
+
+        let shared = Arc::new(zip_archive::Shared {
+            files,
+            names_map,
+            lazy: None,
+            offset: 0,
+            comment: Vec::new(),
+            tree: Default::default(),
+            seek_indexes: Default::default(),
+        });
+        let archive = ZipArchive {
+            reader,
+            shared,
+            limits: Limits::default(),
+            options: ParseOptions::default(),
+        };
+        Ok((archive, unrecoverable))
+    }
+}
+
+/// Find the next local header signature from `position`
+fn find_local_header<R: Read + io::Seek>(
+    reader: &mut R,
+    mut position: u64,
+    length: u64,
+) -> io::Result<Option<u64>> {
+    let signature = spec::LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes();
+    let mut buf = vec![0; 1 << 16];
+    while position + 4 <= length {
+        reader.seek(io::SeekFrom::Start(position))?;
+        let count = (length - position).min(buf.len() as u64) as usize;
+        reader.read_exact(&mut buf[..count])?;
+        if let Some(found) = buf[..count].windows(4).position(|w| w == signature) {
+            return Ok(Some(position + found as u64));
+        }
+        // The signature may start in the last 3 bytes
+        position += count as u64 - 3;
+    }
+    Ok(None)
+}
+
+/// Read the metadata of the file whose local header is at `offset`, or its name if it is known and
+/// why it can't be recovered
+fn recover_file<R: Read + io::Seek>(
+    reader: &mut R,
+    offset: u64,
+    length: u64,
+) -> Result<ZipFileData, (Option<String>, ZipError)> {
+    let mut file = read_local_header_at(reader, offset).map_err(|e| (None, e))?;
+    let name = Some(file.file_name.clone());
+    let data_start = offset + 30 + file.file_name_raw.len() as u64 + file.extra_field.len() as u64;
+    file.header_start = offset;
+    file.data_start.store(data_start);
+    // Everything up to the end of the archive may contain files
+    file.central_header_start = length;
+
+    if file.using_data_descriptor {
+        if file.encrypted {
+            return Err((
+                name,
+                ZipError::UnsupportedArchive(
+                    "Encrypted files with a data descriptor can't be recovered",
+                ),
+            ));
+        }
+        // The sizes and CRC32 are only known once the data has been read
+        let (crc32, compressed_size, uncompressed_size) =
+            read_descriptor(reader, offset, length).map_err(|e| (name.clone(), e))?;
+        file.crc32 = crc32;
+        file.compressed_size = compressed_size;
+        file.uncompressed_size = uncompressed_size;
+    }
+
+    if data_start + file.compressed_size > length {
+        return Err((name, ZipError::InvalidArchive("File data is truncated")));
+    }
+    Ok(file)
+}
+
+/// Decompress the file at `offset` up to its data descriptor, and return the CRC32, compressed
+/// size and uncompressed size it contains
+fn read_descriptor<R: Read + io::Seek>(
+    reader: &mut R,
+    offset: u64,
+    length: u64,
+) -> ZipResult<(u32, u64, u64)> {
+    reader.seek(io::SeekFrom::Start(offset))?;
+    let mut reader = io::BufReader::new(reader.take(length - offset));
+    let mut file = read_zipfile_from_bufread(&mut reader)?.ok_or(ZipError::InvalidSignature {
+        record: "local file header",
+        offset,
+        found: spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE,
+    })?;
+    io::copy(&mut file, &mut io::sink())?;
+    Ok((file.crc32(), file.compressed_size(), file.size()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::read::test_util::archive;
+    use crate::result::ZipError;
+    use crate::{CompressionMethod, ZipArchive};
+    use std::io::{self, Read};
+
+    fn read(archive: &mut ZipArchive<io::Cursor<Vec<u8>>>, name: &str) -> Vec<u8> {
+        let mut content = Vec::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn truncated() {
+        let mut v = archive(CompressionMethod::Stored, 100);
+        // Cut the archive in the middle of the last file
+        let last = v.windows(4).rposition(|w| w == b"PK\x03\x04").unwrap();
+        v.truncate(last + 100);
+        v.splice(0..0, b"leading junk".iter().copied());
+        assert!(ZipArchive::new(io::Cursor::new(v.clone())).is_err());
+
+        let (mut archive, unrecoverable) = ZipArchive::recover(io::Cursor::new(v)).unwrap();
+        assert_eq!(archive.file_names().count(), 2);
+        assert_eq!(read(&mut archive, "a.txt"), "a.txt".repeat(100).as_bytes());
+        assert_eq!(read(&mut archive, "b.txt"), "b.txt".repeat(100).as_bytes());
+
+        assert_eq!(unrecoverable.len(), 1);
+        assert_eq!(unrecoverable[0].offset(), last as u64 + 12);
+        assert_eq!(unrecoverable[0].name(), Some("c.txt"));
+        match unrecoverable[0].error() {
+            ZipError::InvalidArchive("File data is truncated") => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    /// Write a stored file whose sizes and CRC32 are in a data descriptor
+    fn write_with_descriptor(out: &mut Vec<u8>, name: &str, content: &[u8]) {
+        let crc32 = crc32fast::hash(content);
+        out.extend_from_slice(b"PK\x03\x04");
+        // Version, flags and compression method, followed by the empty time, CRC32 and sizes
+        out.extend_from_slice(&[20, 0, 8, 0, 0, 0]);
+        out.extend_from_slice(&[0; 16]);
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(content);
+        out.extend_from_slice(b"PK\x07\x08");
+        out.extend_from_slice(&crc32.to_le_bytes());
+        out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    }
+
+    #[test]
+    fn data_descriptors() {
+        let mut v = Vec::new();
+        write_with_descriptor(&mut v, "a.txt", "a.txt".repeat(100).as_bytes());
+        write_with_descriptor(&mut v, "b.txt", "b.txt".repeat(100).as_bytes());
+
+        let (mut archive, unrecoverable) = ZipArchive::recover(io::Cursor::new(v)).unwrap();
+        assert!(unrecoverable.is_empty());
+        assert_eq!(read(&mut archive, "a.txt"), "a.txt".repeat(100).as_bytes());
+        assert_eq!(read(&mut archive, "b.txt"), "b.txt".repeat(100).as_bytes());
+    }
+}
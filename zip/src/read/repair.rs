@@ -0,0 +1,282 @@
+//! Repair of damaged archives by copying their intact files to a new archive
+
+use super::{Unrecoverable, ZipArchive};
+use crate::result::{ZipError, ZipResult};
+use crate::truncate::Truncate;
+use crate::write::ZipWriter;
+use std::io::{self, prelude::*};
+
+/// Options for [`repair`]
+#[derive(Clone, Debug, Default)]
+pub struct RepairOptions {
+    quarantine: Option<String>,
+}
+
+/// Result of [`repair`], with the files that were not copied to the new archive
+#[derive(Debug)]
+pub struct RepairReport {
+    rebuilt: bool,
+    copied: usize,
+    removed: Vec<RemovedFile>,
+    unrecoverable: Vec<Unrecoverable>,
+}
+
+/// A file that [`repair`] did not copy as is, because its data is corrupt or unreadable
+#[derive(Debug)]
+pub struct RemovedFile {
+    name: String,
+    error: ZipError,
+    quarantined: Option<String>,
+}
+
+impl RepairOptions {
+    /// Create options that drop corrupt files
+    pub fn new() -> RepairOptions {
+        RepairOptions::default()
+    }
+
+    /// Copy corrupt files to the new archive with their names prefixed by `prefix` instead of
+    /// dropping them, for example `quarantine/`.
+    ///
+    /// Their data is copied as is, so reading them still fails.
+    #[must_use]
+    pub fn quarantine(mut self, prefix: &str) -> RepairOptions {
+        self.quarantine = Some(prefix.to_owned());
+        self
+    }
+}
+
+impl RepairReport {
+    /// Whether the central directory was unreadable, so the files were recovered from their local
+    /// headers with [`ZipArchive::recover`]
+    pub fn rebuilt(&self) -> bool {
+        self.rebuilt
+    }
+
+    /// Get the number of intact files copied to the new archive
+    pub fn copied(&self) -> usize {
+        self.copied
+    }
+
+    /// The files that were dropped or quarantined, in archive order
+    pub fn removed(&self) -> &[RemovedFile] {
+        &self.removed
+    }
+
+    /// The files found in the archive whose metadata could not be recovered at all
+    pub fn unrecoverable(&self) -> &[Unrecoverable] {
+        &self.unrecoverable
+    }
+
+    /// Whether every file of the archive was copied intact
+    pub fn is_ok(&self) -> bool {
+        self.removed.is_empty() && self.unrecoverable.is_empty()
+    }
+}
+
+impl RemovedFile {
+    /// Get the name of the file in the damaged archive
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the error found while checking the file
+    pub fn error(&self) -> &ZipError {
+        &self.error
+    }
+
+    /// Get the name the file was quarantined under in the new archive, or `None` if it was dropped
+    pub fn quarantined(&self) -> Option<&str> {
+        self.quarantined.as_deref()
+    }
+}
+
+/// Copy the intact files of a damaged archive to `writer`, which gets a new central directory.
+///
+/// The archive is read with [`ZipArchive::new`], or with [`ZipArchive::recover`] if its central
+/// directory can't be read. Every file is then decompressed to check its CRC32 and measure its
+/// size, and the intact files are copied with [`ZipWriter::raw_copy_file`] so their data is
+/// never recompressed. The new archive records the measured sizes and uses Zip64 where needed
+/// once [`ZipWriter::finish`] is called.
+///
+/// Files that fail to decompress or whose CRC32 doesn't match are dropped, or quarantined with
+/// [`RepairOptions::quarantine`]. Encrypted files can't be checked nor copied with their
+/// encryption, so they are always dropped.
+///
+/// ```no_run
+/// # fn main() -> zip::result::ZipResult<()> {
+/// let damaged = std::fs::File::open("damaged.zip")?;
+/// let repaired = std::fs::File::create("repaired.zip")?;
+/// let mut writer = zip::ZipWriter::new(repaired);
+/// let report = zip::read::repair(damaged, &mut writer, Default::default())?;
+/// writer.finish()?;
+/// for file in report.removed() {
+///     eprintln!("Dropped {}: {}", file.name(), file.error());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn repair<R, W>(
+    mut reader: R,
+    writer: &mut ZipWriter<W>,
+    options: RepairOptions,
+) -> ZipResult<RepairReport>
+where
+    R: Read + io::Seek,
+    W: Write + io::Seek + Read + Truncate,
+{
+    let (mut archive, rebuilt, unrecoverable) = match ZipArchive::new(&mut reader) {
+        Ok(archive) => (archive, false, Vec::new()),
+        Err(ZipError::Io(err)) => return Err(ZipError::Io(err)),
+        Err(_) => {
+            let (archive, unrecoverable) = ZipArchive::recover(&mut reader)?;
+            (archive, true, unrecoverable)
+        }
+    };
+
+    let mut report = RepairReport {
+        rebuilt,
+        copied: 0,
+        removed: Vec::new(),
+        unrecoverable,
+    };
+    for index in 0..archive.len() {
+        match check_file(&mut archive, index) {
+            Ok(size) => {
+                let mut file = archive.by_index_raw(index)?;
+                file.data.to_mut().uncompressed_size = size;
+                writer.raw_copy_file(file)?;
+                report.copied += 1;
+            }
+            Err((name, error)) => {
+                let quarantined = match &options.quarantine {
+                    Some(prefix) => quarantine(&mut archive, index, prefix, writer)?,
+                    None => None,
+                };
+                report.removed.push(RemovedFile {
+                    name,
+                    error,
+                    quarantined,
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Decompress a file to check its CRC32, and return its actual size, or its name and why it is
+/// corrupt
+fn check_file<R: Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+) -> Result<u64, (String, ZipError)> {
+    let name = match archive.shared.file(index, &mut archive.reader) {
+        Ok(data) => data.file_name.clone(),
+        Err(e) => return Err((String::new(), e)),
+    };
+    match archive.by_index(index) {
+        Ok(mut file) => io::copy(&mut file, &mut io::sink()).map_err(|e| (name, e.into())),
+        Err(e) => Err((name, e)),
+    }
+}
+
+/// Copy a corrupt file under `prefix`, if its raw data can still be read, and return its new name
+fn quarantine<R, W>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+    prefix: &str,
+    writer: &mut ZipWriter<W>,
+) -> ZipResult<Option<String>>
+where
+    R: Read + io::Seek,
+    W: Write + io::Seek + Read + Truncate,
+{
+    let file = match archive.by_index_raw(index) {
+        Ok(file) if !file.data.encrypted => file,
+        _ => return Ok(None),
+    };
+    let name = format!("{}{}", prefix, file.name());
+    writer.raw_copy_file_rename(file, name.clone())?;
+    Ok(Some(name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{repair, RepairOptions};
+    use crate::read::test_util::archive;
+    use crate::{CompressionMethod, ZipArchive, ZipWriter};
+    use std::io::{self, Read};
+
+    fn read(archive: &mut ZipArchive<io::Cursor<Vec<u8>>>, name: &str) -> io::Result<Vec<u8>> {
+        let mut content = Vec::new();
+        archive.by_name(name)?.read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    /// Flip a byte at the start of the data of `name`
+    fn corrupt(v: &mut [u8], name: &str) {
+        let header = v
+            .windows(name.len())
+            .position(|w| w == name.as_bytes())
+            .unwrap();
+        v[header + name.len() + 2] ^= 0xff;
+    }
+
+    #[test]
+    fn quarantine() {
+        let mut v = archive(CompressionMethod::Stored, 100);
+        corrupt(&mut v, "b.txt");
+        // Remove the central directory
+        let directory = v.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        v.truncate(directory + 10);
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = RepairOptions::new().quarantine("quarantine/");
+        let report = repair(io::Cursor::new(v), &mut writer, options).unwrap();
+        assert!(report.rebuilt());
+        assert_eq!(report.copied(), 2);
+        assert_eq!(report.removed().len(), 1);
+        assert_eq!(report.removed()[0].name(), "b.txt");
+        assert_eq!(report.removed()[0].quarantined(), Some("quarantine/b.txt"));
+
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+        let names: Vec<_> = archive.file_names().collect();
+        assert_eq!(names.len(), 3);
+        assert_eq!(
+            read(&mut archive, "a.txt").unwrap(),
+            "a.txt".repeat(100).as_bytes()
+        );
+        assert_eq!(
+            read(&mut archive, "c.txt").unwrap(),
+            "c.txt".repeat(100).as_bytes()
+        );
+        assert!(read(&mut archive, "quarantine/b.txt").is_err());
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn drop_corrupt() {
+        let mut v = archive(CompressionMethod::Deflated, 100);
+        corrupt(&mut v, "a.txt");
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let report = repair(io::Cursor::new(v), &mut writer, RepairOptions::new()).unwrap();
+        assert!(!report.rebuilt());
+        assert!(!report.is_ok());
+        assert_eq!(report.copied(), 2);
+        assert_eq!(report.removed()[0].name(), "a.txt");
+        assert_eq!(report.removed()[0].quarantined(), None);
+
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+        assert_eq!(archive.len(), 2);
+        assert!(read(&mut archive, "a.txt").is_err());
+        assert_eq!(
+            read(&mut archive, "b.txt").unwrap(),
+            "b.txt".repeat(100).as_bytes()
+        );
+        assert_eq!(
+            read(&mut archive, "c.txt").unwrap(),
+            "c.txt".repeat(100).as_bytes()
+        );
+    }
+}
@@ -0,0 +1,705 @@
+//! Checkpoint indexes for random access into the data of compressed files
+//!
+//! Deflate data can only be decompressed from the start, because every block may refer back to the
+//! previous 32KiB of output. A checkpoint stores the state of the decompressor along with that
+//! window, in the spirit of zlib's `zran.c`, so decompression can resume from it. For zstd, frames
+//! are independent of each other, so their boundaries are used as checkpoints.
+
+use super::{find_content, ZipArchive};
+use crate::compression::CompressionMethod;
+use crate::result::{ZipError, ZipResult};
+use crate::types::ZipFileData;
+use std::fmt;
+use std::io::{self, prelude::*};
+use std::sync::Arc;
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+use miniz_oxide::inflate::{
+    core::{decompress, inflate_flags, DecompressorOxide, TINFL_LZ_DICT_SIZE},
+    TINFLStatus,
+};
+
+#[cfg(feature = "zstd")]
+use zstd::stream::raw::{Decoder as ZstdRawDecoder, InBuffer, Operation, OutBuffer};
+
+/// Default distance between two checkpoints, in bytes of uncompressed data
+pub const DEFAULT_CHECKPOINT_SPACING: u64 = 1 << 20;
+
+/// Size of the buffer for compressed data
+const INPUT_BUFFER_SIZE: usize = 1 << 16;
+
+/// Positions in the data of a compressed file from which decompression can resume, so it can be
+/// read at any offset without decompressing everything before it.
+///
+/// Built by scanning the whole file once with [`ZipArchive::seek_index`], and used by
+/// [`ZipArchive::by_index_indexed`]. It can be shared between clones of the archive.
+#[derive(Clone)]
+pub struct SeekIndex {
+    /// Ordered by offset, the first one is the start of the data
+    checkpoints: Vec<Checkpoint>,
+    spacing: u64,
+    size: u64,
+}
+
+#[derive(Clone)]
+struct Checkpoint {
+    compressed_offset: u64,
+    uncompressed_offset: u64,
+    /// State of the deflate decompressor, `None` for stored files and zstd frame boundaries
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    deflate: Option<Arc<DeflateDecoder>>,
+}
+
+impl SeekIndex {
+    /// Number of checkpoints in the index, including the one at the start of the data
+    pub fn len(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    /// Whether the index has no checkpoints, which is never the case
+    pub fn is_empty(&self) -> bool {
+        self.checkpoints.is_empty()
+    }
+
+    /// Minimum distance between two checkpoints, in bytes of uncompressed data
+    pub fn spacing(&self) -> u64 {
+        self.spacing
+    }
+
+    /// Size of the uncompressed data
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Get the last checkpoint at or before an offset in the uncompressed data
+    fn checkpoint_before(&self, offset: u64) -> &Checkpoint {
+        let after = self
+            .checkpoints
+            .partition_point(|checkpoint| checkpoint.uncompressed_offset <= offset);
+        &self.checkpoints[after.max(1) - 1]
+    }
+}
+
+impl fmt::Debug for SeekIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeekIndex")
+            .field("checkpoints", &self.checkpoints.len())
+            .field("spacing", &self.spacing)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl<R: Read + io::Seek> ZipArchive<R> {
+    /// Build a [`SeekIndex`] for a file by decompressing it once, checking its CRC32 along the way.
+    ///
+    /// A checkpoint is recorded every `spacing` bytes of uncompressed data, or at the first
+    /// possible position after that. For deflate, each checkpoint takes around 45KiB of memory.
+    /// For zstd, checkpoints can only be at frame boundaries, so a file that is a single frame
+    /// gets no checkpoint besides the start.
+    ///
+    /// The index is kept by the archive for [`ZipArchive::by_index_indexed`], replacing any
+    /// previous one for the file. Only stored, deflated and zstd files that are not encrypted are
+    /// supported.
+    pub fn seek_index(&mut self, file_number: usize, spacing: u64) -> ZipResult<Arc<SeekIndex>> {
+        let data = self.shared.file(file_number, &mut self.reader)?;
+        let mut stream = DataStream::new(data, &mut self.reader)?;
+
+        let mut seek_index = SeekIndex {
+            checkpoints: vec![stream.checkpoint().unwrap()],
+            spacing: spacing.max(1),
+            size: 0,
+        };
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buffer = vec![0; INPUT_BUFFER_SIZE];
+        let mut last_checkpoint = 0;
+
+        loop {
+            let count = stream.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+            hasher.update(&buffer[..count]);
+
+            if stream.uncompressed_offset >= last_checkpoint + seek_index.spacing {
+                if let Some(checkpoint) = stream.checkpoint() {
+                    last_checkpoint = checkpoint.uncompressed_offset;
+                    seek_index.checkpoints.push(checkpoint);
+                }
+            }
+        }
+
+        let actual = hasher.finalize();
+        if actual != data.crc32 {
+            return Err(ZipError::Crc32Mismatch {
+                name: data.file_name.clone(),
+                expected: data.crc32,
+                actual,
+            });
+        }
+        seek_index.size = stream.uncompressed_offset;
+
+        let seek_index = Arc::new(seek_index);
+        self.shared
+            .seek_indexes
+            .lock()
+            .unwrap()
+            .insert(file_number, seek_index.clone());
+        Ok(seek_index)
+    }
+
+    /// Get a file by index as a reader that also implements [`Seek`], resuming decompression from
+    /// the nearest checkpoint of its [`SeekIndex`] when reading at an arbitrary offset.
+    ///
+    /// The index built by [`ZipArchive::seek_index`] is used, or built first with
+    /// [`DEFAULT_CHECKPOINT_SPACING`] if there is none yet. Since the data can be read in any
+    /// order, its CRC32 is only checked while building the index.
+    pub fn by_index_indexed(&mut self, file_number: usize) -> ZipResult<IndexedZipFile<'_, R>> {
+        let cached = self
+            .shared
+            .seek_indexes
+            .lock()
+            .unwrap()
+            .get(&file_number)
+            .cloned();
+        let seek_index = match cached {
+            Some(seek_index) => seek_index,
+            None => self.seek_index(file_number, DEFAULT_CHECKPOINT_SPACING)?,
+        };
+        self.by_index_with_seek_index(file_number, seek_index)
+    }
+
+    /// Get a file by index as a reader that also implements [`Seek`], using a [`SeekIndex`] that was
+    /// built for the same file, for example by a clone of this archive.
+    pub fn by_index_with_seek_index(
+        &mut self,
+        file_number: usize,
+        seek_index: Arc<SeekIndex>,
+    ) -> ZipResult<IndexedZipFile<'_, R>> {
+        let data = self.shared.file(file_number, &mut self.reader)?;
+        let stream = DataStream::new(data, &mut self.reader)?;
+        Ok(IndexedZipFile {
+            data,
+            seek_index,
+            stream,
+            position: 0,
+        })
+    }
+}
+
+/// A compressed file in an archive, which can be read at any offset using a [`SeekIndex`].
+///
+/// Obtained with [`ZipArchive::by_index_indexed`].
+pub struct IndexedZipFile<'a, R> {
+    data: &'a ZipFileData,
+    seek_index: Arc<SeekIndex>,
+    stream: DataStream<'a, R>,
+    /// Position in the uncompressed data
+    position: u64,
+}
+
+impl<'a, R> IndexedZipFile<'a, R> {
+    /// Get the name of the file
+    pub fn name(&self) -> &'a str {
+        &self.data.file_name
+    }
+
+    /// Get the size of the file when uncompressed
+    pub fn size(&self) -> u64 {
+        self.seek_index.size
+    }
+
+    /// Get the index used to seek in the file
+    pub fn seek_index(&self) -> &Arc<SeekIndex> {
+        &self.seek_index
+    }
+}
+
+impl<'a, R: Read + io::Seek> Read for IndexedZipFile<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size() {
+            return Ok(0);
+        }
+
+        if self.stream.uncompressed_offset != self.position {
+            let checkpoint = self.seek_index.checkpoint_before(self.position);
+            // Skipping forward is cheaper than restoring, unless a checkpoint is in between
+            if self.stream.uncompressed_offset > self.position
+                || checkpoint.uncompressed_offset > self.stream.uncompressed_offset
+            {
+                self.stream.restore(checkpoint)?;
+            }
+            self.stream.skip_to(self.position)?;
+        }
+
+        let count = self.stream.read(buf)?;
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<'a, R> Seek for IndexedZipFile<'a, R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            io::SeekFrom::Start(offset) => Some(offset),
+            io::SeekFrom::End(offset) => self.size().checked_add_signed(offset),
+            io::SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        // The underlying stream is only moved on the next read
+        self.position = position;
+        Ok(position)
+    }
+}
+
+impl<'a, R> fmt::Debug for IndexedZipFile<'a, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IndexedZipFile")
+            .field("name", &self.name())
+            .field("seek_index", &self.seek_index)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+/// Decompression of the data of a file, from the start or from a checkpoint
+struct DataStream<'a, R> {
+    reader: &'a mut R,
+    data_start: u64,
+    compressed_size: u64,
+    /// Compressed bytes consumed by the decoder
+    compressed_offset: u64,
+    uncompressed_offset: u64,
+    /// Compressed data read ahead of the decoder, `reader` is positioned after it
+    input: Vec<u8>,
+    input_pos: usize,
+    input_end: usize,
+    decoder: Decoder,
+}
+
+enum Decoder {
+    Stored,
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    Deflate(Box<DeflateDecoder>),
+    #[cfg(feature = "zstd")]
+    Zstd {
+        decoder: ZstdRawDecoder<'static>,
+        /// Whether the decoder is between two frames
+        frame_done: bool,
+    },
+}
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+#[derive(Clone)]
+struct DeflateDecoder {
+    decompressor: DecompressorOxide,
+    /// Ring buffer of the last 32KiB of output, which later blocks can refer to
+    window: Box<[u8]>,
+    window_pos: usize,
+    /// Part of the window that was decompressed but not read yet
+    pending: std::ops::Range<usize>,
+    done: bool,
+}
+
+impl<'a, R: Read + io::Seek> DataStream<'a, R> {
+    fn new(data: &ZipFileData, reader: &'a mut R) -> ZipResult<DataStream<'a, R>> {
+        if data.encrypted {
+            return Err(ZipError::UnsupportedArchive(
+                "Encrypted files cannot be indexed for seeking",
+            ));
+        }
+        let decoder = match data.compression_method {
+            CompressionMethod::Stored => Decoder::Stored,
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            CompressionMethod::Deflated => Decoder::Deflate(Box::new(DeflateDecoder {
+                decompressor: DecompressorOxide::new(),
+                window: vec![0; TINFL_LZ_DICT_SIZE].into_boxed_slice(),
+                window_pos: 0,
+                pending: 0..0,
+                done: false,
+            })),
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => Decoder::Zstd {
+                decoder: ZstdRawDecoder::new()?,
+                frame_done: true,
+            },
+            _ => {
+                return Err(ZipError::UnsupportedArchive(
+                    "Compression method cannot be indexed for seeking",
+                ))
+            }
+        };
+
+        find_content(data, reader)?;
+        Ok(DataStream {
+            reader,
+            data_start: data.data_start.load(),
+            compressed_size: data.compressed_size,
+            compressed_offset: 0,
+            uncompressed_offset: 0,
+            input: vec![0; INPUT_BUFFER_SIZE],
+            input_pos: 0,
+            input_end: 0,
+            decoder,
+        })
+    }
+
+    /// Compressed data that was not read into the input buffer yet
+    fn unread_input(&self) -> u64 {
+        self.compressed_size - self.compressed_offset - (self.input_end - self.input_pos) as u64
+    }
+
+    /// Refill the input buffer if the decoder consumed all of it
+    fn fill_input(&mut self) -> io::Result<()> {
+        if self.input_pos == self.input_end && self.unread_input() > 0 {
+            let len = self.unread_input().min(self.input.len() as u64) as usize;
+            self.reader.read_exact(&mut self.input[..len])?;
+            self.input_pos = 0;
+            self.input_end = len;
+        }
+        Ok(())
+    }
+
+    fn consume_input(&mut self, count: usize) {
+        self.input_pos += count;
+        self.compressed_offset += count as u64;
+    }
+
+    /// Get a checkpoint for the current position, if decompression can resume from it
+    fn checkpoint(&self) -> Option<Checkpoint> {
+        #[cfg(any(
+            feature = "deflate",
+            feature = "deflate-miniz",
+            feature = "deflate-zlib"
+        ))]
+        let deflate = match &self.decoder {
+            Decoder::Deflate(decoder) if !decoder.pending.is_empty() => return None,
+            Decoder::Deflate(decoder) => Some(Arc::new((**decoder).clone())),
+            _ => None,
+        };
+        #[cfg(feature = "zstd")]
+        if let Decoder::Zstd {
+            frame_done: false, ..
+        } = self.decoder
+        {
+            return None;
+        }
+
+        Some(Checkpoint {
+            compressed_offset: self.compressed_offset,
+            uncompressed_offset: self.uncompressed_offset,
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            deflate,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: &Checkpoint) -> io::Result<()> {
+        match &mut self.decoder {
+            Decoder::Stored => {}
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            Decoder::Deflate(decoder) => {
+                let state = checkpoint.deflate.as_ref().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Seek index is for another file",
+                    )
+                })?;
+                (**decoder).clone_from(state);
+            }
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd {
+                decoder,
+                frame_done,
+            } => {
+                decoder.reinit()?;
+                *frame_done = true;
+            }
+        }
+
+        self.compressed_offset = checkpoint.compressed_offset;
+        self.uncompressed_offset = checkpoint.uncompressed_offset;
+        self.input_pos = 0;
+        self.input_end = 0;
+        self.reader.seek(io::SeekFrom::Start(
+            self.data_start + checkpoint.compressed_offset,
+        ))?;
+        Ok(())
+    }
+
+    /// Decompress and discard data up to an offset in the uncompressed data
+    fn skip_to(&mut self, offset: u64) -> io::Result<()> {
+        let mut buffer = [0; 1 << 13];
+        while self.uncompressed_offset < offset {
+            let len = (offset - self.uncompressed_offset).min(buffer.len() as u64) as usize;
+            if self.read(&mut buffer[..len])? == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = match self.decoder {
+            Decoder::Stored => self.read_stored(buf)?,
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            Decoder::Deflate(_) => self.read_deflate(buf)?,
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd { .. } => self.read_zstd(buf)?,
+        };
+        self.uncompressed_offset += count as u64;
+        Ok(count)
+    }
+
+    fn read_stored(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill_input()?;
+        let count = buf.len().min(self.input_end - self.input_pos);
+        buf[..count].copy_from_slice(&self.input[self.input_pos..self.input_pos + count]);
+        self.consume_input(count);
+        Ok(count)
+    }
+
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    fn read_deflate(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let decoder = match &mut self.decoder {
+                Decoder::Deflate(decoder) => decoder,
+                _ => unreachable!(),
+            };
+            if !decoder.pending.is_empty() {
+                let count = buf.len().min(decoder.pending.len());
+                let start = decoder.pending.start;
+                buf[..count].copy_from_slice(&decoder.window[start..start + count]);
+                decoder.pending.start += count;
+                return Ok(count);
+            }
+            if decoder.done || buf.is_empty() {
+                return Ok(0);
+            }
+
+            self.fill_input()?;
+            let flags = match self.unread_input() {
+                0 => 0,
+                _ => inflate_flags::TINFL_FLAG_HAS_MORE_INPUT,
+            };
+            let decoder = match &mut self.decoder {
+                Decoder::Deflate(decoder) => decoder,
+                _ => unreachable!(),
+            };
+            let (status, read, written) = decompress(
+                &mut decoder.decompressor,
+                &self.input[self.input_pos..self.input_end],
+                &mut decoder.window,
+                decoder.window_pos,
+                flags,
+            );
+            decoder.pending = decoder.window_pos..decoder.window_pos + written;
+            decoder.window_pos = (decoder.window_pos + written) & (TINFL_LZ_DICT_SIZE - 1);
+            match status {
+                TINFLStatus::Done => decoder.done = true,
+                TINFLStatus::NeedsMoreInput | TINFLStatus::HasMoreOutput => {}
+                TINFLStatus::FailedCannotMakeProgress if written > 0 => {}
+                TINFLStatus::FailedCannotMakeProgress => {
+                    return Err(io::ErrorKind::UnexpectedEof.into())
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Invalid deflate stream",
+                    ))
+                }
+            }
+            self.consume_input(read);
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    fn read_zstd(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            self.fill_input()?;
+            let (decoder, frame_done) = match &mut self.decoder {
+                Decoder::Zstd {
+                    decoder,
+                    frame_done,
+                } => (decoder, frame_done),
+                _ => unreachable!(),
+            };
+            let input_left = self.input_end - self.input_pos;
+            if input_left == 0 && *frame_done {
+                return Ok(0);
+            }
+
+            let mut input = InBuffer::around(&self.input[self.input_pos..self.input_end]);
+            let mut output = OutBuffer::around(&mut *buf);
+            // A frame is only done once all of its output has been flushed
+            *frame_done = decoder.run(&mut input, &mut output)? == 0;
+            let (read, written) = (input.pos(), output.pos());
+            self.consume_input(read);
+
+            if written > 0 {
+                return Ok(written);
+            }
+            if input_left == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::write::FileOptions;
+    use crate::{CompressionMethod, ZipArchive, ZipWriter};
+    use std::io::{self, Read, Seek, SeekFrom, Write};
+
+    /// Data that compresses well but doesn't repeat exactly, so offsets can be told apart
+    fn content() -> Vec<u8> {
+        (0u32..400_000)
+            .flat_map(|i| format!("{:08}\n", i.wrapping_mul(2654435761) % 1000).into_bytes())
+            .collect()
+    }
+
+    fn zip(method: CompressionMethod, content: &[u8]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(method);
+        writer.start_file("padding.txt", options).unwrap();
+        writer.write_all(b"padding").unwrap();
+        writer.start_file("data.bin", options).unwrap();
+        writer.write_all(content).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn check_random_access(method: CompressionMethod, expected_checkpoints: usize) {
+        let content = content();
+        let mut archive = ZipArchive::new(io::Cursor::new(zip(method, &content))).unwrap();
+        let seek_index = archive.seek_index(1, 1 << 18).unwrap();
+        assert_eq!(seek_index.size(), content.len() as u64);
+        assert!(seek_index.len() >= expected_checkpoints, "{:?}", seek_index);
+
+        let mut file = archive.by_index_indexed(1).unwrap();
+        let mut buf = [0u8; 100];
+        for &offset in &[2_000_000u64, 17, 1_500_000, 2_999_950, 1_500_100, 0] {
+            file.seek(SeekFrom::Start(offset)).unwrap();
+            let count = file.read(&mut buf).unwrap();
+            assert!(count > 0);
+            let offset = offset as usize;
+            assert_eq!(&buf[..count], &content[offset..offset + count]);
+        }
+
+        file.seek(SeekFrom::End(-10)).unwrap();
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, &content[content.len() - 10..]);
+
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut all = Vec::new();
+        file.read_to_end(&mut all).unwrap();
+        assert!(all == content);
+    }
+
+    #[test]
+    fn stored_random_access() {
+        check_random_access(CompressionMethod::Stored, 1);
+    }
+
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    #[test]
+    fn deflate_random_access() {
+        check_random_access(CompressionMethod::Deflated, 8);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_random_access() {
+        check_random_access(CompressionMethod::Zstd, 1);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_frame_checkpoints() {
+        let content = content();
+        let mut compressed = Vec::new();
+        for chunk in content.chunks(1 << 18) {
+            compressed.extend(zstd::encode_all(chunk, 3).unwrap());
+        }
+
+        // Store the frames, then mark them as zstd data in both headers
+        let mut v = zip(CompressionMethod::Stored, &compressed);
+        let local_header = 30 + "padding.txt".len() + "padding".len();
+        let central_header = v.len() - 22 - 46 - "data.bin".len();
+        for (header, method, crc32) in [(local_header, 8, 14), (central_header, 10, 16)] {
+            v[header + method..header + method + 2].copy_from_slice(&93u16.to_le_bytes());
+            let crc32 = header + crc32;
+            v[crc32..crc32 + 4].copy_from_slice(&crc32fast::hash(&content).to_le_bytes());
+            let size = crc32 + 8;
+            v[size..size + 4].copy_from_slice(&(content.len() as u32).to_le_bytes());
+        }
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+
+        let seek_index = archive.seek_index(1, 1).unwrap();
+        assert!(seek_index.len() > content.len() / (1 << 18));
+        let mut file = archive.by_index_indexed(1).unwrap();
+        let mut buf = [0u8; 10];
+        file.seek(SeekFrom::Start(2_000_000)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, &content[2_000_000..2_000_010]);
+    }
+
+    #[test]
+    fn corrupt_data_is_detected() {
+        let content = content();
+        let mut v = zip(CompressionMethod::Stored, &content);
+        let position = v.len() / 2;
+        v[position] ^= 1;
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        assert!(archive.seek_index(1, 1 << 18).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! Seekable access to the data of stored files, for opening nested archives in place
+
+use super::{find_content, FileMetadata, ZipArchive};
+use crate::compression::CompressionMethod;
+use crate::result::{ZipError, ZipResult};
+use crate::types::ZipFileData;
+use std::io::{self, prelude::*};
+
+/// A stored, unencrypted file in an archive, which can be read and seeked like a file of its own.
+///
+/// Since the data can be read in any order, its CRC32 is not checked.
+///
+/// Obtained with [`ZipArchive::by_index_seekable`] or [`ZipArchive::by_name_seekable`]. This is
+/// [`Read`] and [`Seek`], so an archive stored inside another one can be opened in place with
+/// [`ZipArchive::new`], and so on recursively.
+#[derive(Debug)]
+pub struct SeekableZipFile<'a, R> {
+    index: usize,
+    data: &'a ZipFileData,
+    reader: &'a mut R,
+    /// Position of the data in `reader`
+    data_start: u64,
+    /// Position in the data, which `reader` is kept at
+    position: u64,
+}
+
+impl<R: Read + io::Seek> ZipArchive<R> {
+    /// Get a stored, unencrypted file by index, as a reader that also implements [`Seek`].
+    ///
+    /// Fails with [`ZipError::UnsupportedArchive`] if the file is compressed or encrypted, and with
+    /// [`ZipError::InvalidArchive`] if its compressed and uncompressed sizes differ.
+    pub fn by_index_seekable(&mut self, file_number: usize) -> ZipResult<SeekableZipFile<'_, R>> {
+        let data = self.shared.file(file_number, &mut self.reader)?;
+        if data.compression_method != CompressionMethod::Stored || data.encrypted {
+            return Err(ZipError::UnsupportedArchive(
+                "Only stored, unencrypted files can be seeked",
+            ));
+        }
+        // Otherwise the data would extend into whatever follows the file
+        if data.compressed_size != data.uncompressed_size {
+            return Err(ZipError::InvalidArchive(
+                "Stored file with different compressed and uncompressed sizes",
+            ));
+        }
+
+        find_content(data, &mut self.reader)?;
+        Ok(SeekableZipFile {
+            index: file_number,
+            data,
+            reader: &mut self.reader,
+            data_start: data.data_start.load(),
+            position: 0,
+        })
+    }
+
+    /// Search for a stored, unencrypted file by name, as a reader that also implements [`Seek`].
+    ///
+    /// Fails like [`ZipArchive::by_index_seekable`].
+    pub fn by_name_seekable(&mut self, name: &str) -> ZipResult<SeekableZipFile<'_, R>> {
+        let index = self.shared.index_of(name).ok_or(ZipError::FileNotFound)?;
+        self.by_index_seekable(index)
+    }
+}
+
+impl<'a, R> SeekableZipFile<'a, R> {
+    /// Get the metadata of the file, as read from the central directory
+    pub fn metadata(&self) -> FileMetadata<'a> {
+        FileMetadata::new(self.index, self.data)
+    }
+
+    /// Get the size of the file
+    pub fn size(&self) -> u64 {
+        self.data.uncompressed_size
+    }
+}
+
+impl<'a, R: Read> Read for SeekableZipFile<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size().saturating_sub(self.position);
+        let len = remaining.min(buf.len() as u64) as usize;
+        let count = self.reader.read(&mut buf[..len])?;
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<'a, R: io::Seek> Seek for SeekableZipFile<'a, R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            io::SeekFrom::Start(offset) => Some(offset),
+            io::SeekFrom::End(offset) => self.size().checked_add_signed(offset),
+            io::SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        self.reader
+            .seek(io::SeekFrom::Start(self.data_start + position))?;
+        self.position = position;
+        Ok(position)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::result::ZipError;
+    use crate::write::FileOptions;
+    use crate::{CompressionMethod, ZipArchive, ZipWriter};
+    use std::io::{self, Read, Seek, SeekFrom, Write};
+
+    fn zip(files: &[(&str, &[u8], CompressionMethod)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (name, content, method) in files {
+            let options = FileOptions::default().compression_method(*method);
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn seek_stored_file() {
+        let v = zip(&[
+            ("a.txt", b"first", CompressionMethod::Stored),
+            ("b.txt", b"0123456789", CompressionMethod::Stored),
+        ]);
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        let mut file = archive.by_name_seekable("b.txt").unwrap();
+        assert_eq!(file.metadata().name(), "b.txt");
+
+        let mut buf = [0u8; 4];
+        file.seek(SeekFrom::Start(3)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"3456");
+        assert_eq!(file.seek(SeekFrom::Current(-2)).unwrap(), 5);
+        assert_eq!(file.seek(SeekFrom::End(-3)).unwrap(), 7);
+
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"789");
+        assert!(file.seek(SeekFrom::Current(-11)).is_err());
+    }
+
+    #[test]
+    fn mismatched_sizes() {
+        let mut v = zip(&[
+            ("a.txt", b"0123456789", CompressionMethod::Stored),
+            ("b.txt", b"0123456789", CompressionMethod::Stored),
+        ]);
+        // Declare a compressed size of 2 for the first file in the central directory
+        let central = v.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        v[central + 20..central + 24].copy_from_slice(&2u32.to_le_bytes());
+
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        assert!(matches!(
+            archive.by_index_seekable(0),
+            Err(ZipError::InvalidArchive(_))
+        ));
+        assert!(archive.by_name_seekable("b.txt").is_ok());
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn compressed_file_is_not_seekable() {
+        let v = zip(&[("a.txt", b"first", CompressionMethod::Deflated)]);
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        assert!(archive.by_index_seekable(0).is_err());
+    }
+
+    #[test]
+    fn nested_archives() {
+        let innermost = zip(&[("file.txt", b"nested", CompressionMethod::Stored)]);
+        let inner = zip(&[
+            ("padding.txt", b"padding", CompressionMethod::Stored),
+            ("innermost.zip", &innermost, CompressionMethod::Stored),
+        ]);
+        let outer = zip(&[("inner.apk", &inner, CompressionMethod::Stored)]);
+
+        let mut outer = ZipArchive::new(io::Cursor::new(outer)).unwrap();
+        let mut inner = ZipArchive::new(outer.by_index_seekable(0).unwrap()).unwrap();
+        let mut innermost =
+            ZipArchive::new(inner.by_name_seekable("innermost.zip").unwrap()).unwrap();
+
+        let mut content = String::new();
+        innermost
+            .by_name("file.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "nested");
+    }
+}
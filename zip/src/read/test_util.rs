@@ -0,0 +1,41 @@
+//! Archives and patches shared by the tests of the read modules
+
+use crate::write::FileOptions;
+use crate::{CompressionMethod, ZipWriter};
+use std::io::{self, Write};
+
+/// Write an archive of `a.txt`, `b.txt` and `c.txt`, each holding its own name `repeat` times
+pub(super) fn archive(method: CompressionMethod, repeat: usize) -> Vec<u8> {
+    let options = FileOptions::default().compression_method(method);
+    let contents = ["a.txt", "b.txt", "c.txt"]
+        .iter()
+        .map(|name| (*name, name.repeat(repeat)))
+        .collect::<Vec<_>>();
+    let files = contents
+        .iter()
+        .map(|(name, content)| (*name, content.as_bytes(), options))
+        .collect::<Vec<_>>();
+    zip(&files)
+}
+
+/// Write an archive of the given files, where the names ending with `/` are directories
+pub(super) fn zip(files: &[(&str, &[u8], FileOptions)]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+    for (name, content, options) in files {
+        if name.ends_with('/') {
+            writer.add_directory(*name, *options).unwrap();
+        } else {
+            writer.start_file(*name, *options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// Point the second file of the central directory to the local header of the first one
+pub(super) fn point_second_file_to_first(v: &mut [u8]) {
+    let central = v.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+    let length = |offset: usize| u16::from_le_bytes([v[offset], v[offset + 1]]) as usize;
+    let second = central + 46 + length(central + 28) + length(central + 30) + length(central + 32);
+    v[second + 42..second + 46].copy_from_slice(&0u32.to_le_bytes());
+}
@@ -0,0 +1,284 @@
+//! Directory tree view over the names of the files in an archive
+
+use super::glob;
+use std::collections::HashMap;
+
+/// Index of the files in an archive by directory.
+///
+/// Directories that have no entry of their own in the archive, but contain files, are part of the
+/// tree as implicit directories. The paths of directories always end with a `/`.
+///
+/// Built with [`ZipArchive::tree`](crate::ZipArchive::tree).
+#[derive(Debug)]
+pub struct DirectoryTree {
+    nodes: Vec<Node>,
+    /// Node index by path
+    paths: HashMap<Box<str>, usize>,
+    /// Node indices ordered by path, excluding the root
+    sorted: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct Node {
+    path: Box<str>,
+    /// Index of the file in the archive, `None` for implicit directories
+    index: Option<usize>,
+    /// Child node indices, ordered by path
+    children: Vec<usize>,
+}
+
+/// A file or directory in a [`DirectoryTree`]
+#[derive(Clone, Copy, Debug)]
+pub struct TreeEntry<'a> {
+    tree: &'a DirectoryTree,
+    node: usize,
+}
+
+impl DirectoryTree {
+    /// Build the tree from the names of the files, in archive order.
+    ///
+    /// When several files share a name the last one wins, like it does for [`crate::ZipArchive::by_name`].
+    pub(crate) fn build<'a>(names: impl Iterator<Item = &'a str>) -> DirectoryTree {
+        let mut tree = DirectoryTree {
+            nodes: vec![Node {
+                path: "".into(),
+                index: None,
+                children: Vec::new(),
+            }],
+            paths: HashMap::new(),
+            sorted: Vec::new(),
+        };
+        tree.paths.insert("".into(), 0);
+
+        for (index, name) in names.enumerate() {
+            // Make sure every parent directory exists, as an implicit directory if needed
+            let mut parent = 0;
+            for (end, _) in name.match_indices('/') {
+                let node = tree.node_or_insert(&name[..=end], parent);
+                if end == name.len() - 1 {
+                    tree.nodes[node].index = Some(index);
+                }
+                parent = node;
+            }
+            if !name.ends_with('/') {
+                let node = tree.node_or_insert(name, parent);
+                tree.nodes[node].index = Some(index);
+            }
+        }
+
+        for node in 0..tree.nodes.len() {
+            let mut children = std::mem::take(&mut tree.nodes[node].children);
+            children.sort_by(|&a, &b| tree.nodes[a].path.cmp(&tree.nodes[b].path));
+            tree.nodes[node].children = children;
+        }
+        tree.sorted = (1..tree.nodes.len()).collect();
+        let nodes = &tree.nodes;
+        tree.sorted
+            .sort_by(|&a, &b| nodes[a].path.cmp(&nodes[b].path));
+
+        tree
+    }
+
+    fn node_or_insert(&mut self, path: &str, parent: usize) -> usize {
+        if let Some(&node) = self.paths.get(path) {
+            return node;
+        }
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            path: path.into(),
+            index: None,
+            children: Vec::new(),
+        });
+        self.nodes[parent].children.push(node);
+        self.paths.insert(path.into(), node);
+        node
+    }
+
+    fn entry(&self, node: usize) -> TreeEntry<'_> {
+        TreeEntry { tree: self, node }
+    }
+
+    /// The root directory of the archive
+    pub fn root(&self) -> TreeEntry<'_> {
+        self.entry(0)
+    }
+
+    /// Look up a file or directory by its path. Directories can be given with or without a trailing `/`.
+    pub fn get(&self, path: &str) -> Option<TreeEntry<'_>> {
+        let node = match self.paths.get(path) {
+            Some(&node) => node,
+            None if !path.ends_with('/') => *self.paths.get(format!("{}/", path).as_str())?,
+            None => return None,
+        };
+        Some(self.entry(node))
+    }
+
+    /// Whether the directory exists, either with an entry of its own or implicitly by containing files
+    pub fn is_dir(&self, path: &str) -> bool {
+        self.get(path).is_some_and(|entry| entry.is_dir())
+    }
+
+    /// The files and directories directly inside the directory `dir`, or `None` if there is no such directory.
+    ///
+    /// Use an empty path for the root directory.
+    pub fn list(&self, dir: &str) -> Option<impl Iterator<Item = TreeEntry<'_>>> {
+        let dir = self.get(dir).filter(|entry| entry.is_dir())?;
+        Some(dir.children())
+    }
+
+    /// All files and directories whose path starts with `prefix`, ordered by path
+    pub fn with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = TreeEntry<'a>> {
+        let start = self
+            .sorted
+            .partition_point(|&node| &*self.nodes[node].path < prefix);
+        self.sorted[start..]
+            .iter()
+            .take_while(move |&&node| self.nodes[node].path.starts_with(prefix))
+            .map(move |&node| self.entry(node))
+    }
+
+    /// All files and directories whose path matches a glob pattern, ordered by path.
+    ///
+    /// The trailing `/` of directories is not part of the matched path. The pattern supports
+    /// `?`, `*`, `**`, character classes such as `[a-z]` or `[!a-z]`, and `\` escapes.
+    pub fn glob<'a>(&'a self, pattern: &'a str) -> impl Iterator<Item = TreeEntry<'a>> {
+        self.with_prefix(glob::literal_prefix(pattern))
+            .filter(move |entry| glob::matches(pattern, entry.path().trim_end_matches('/')))
+    }
+}
+
+impl<'a> TreeEntry<'a> {
+    fn node(&self) -> &'a Node {
+        &self.tree.nodes[self.node]
+    }
+
+    /// Full path of the entry, ending with a `/` for directories. This is empty for the root.
+    pub fn path(&self) -> &'a str {
+        &self.node().path
+    }
+
+    /// Last component of the path, without a trailing `/`
+    pub fn name(&self) -> &'a str {
+        let path = self.path().trim_end_matches('/');
+        match path.rfind('/') {
+            Some(i) => &path[i + 1..],
+            None => path,
+        }
+    }
+
+    /// Index of the file in the archive, or `None` for the root and implicit directories
+    pub fn index(&self) -> Option<usize> {
+        self.node().index
+    }
+
+    /// Whether this is a directory
+    pub fn is_dir(&self) -> bool {
+        self.node == 0 || self.path().ends_with('/')
+    }
+
+    /// Whether this is a directory without an entry of its own in the archive
+    pub fn is_implicit(&self) -> bool {
+        self.is_dir() && self.index().is_none()
+    }
+
+    /// The files and directories directly inside this directory, ordered by path
+    pub fn children(&self) -> impl Iterator<Item = TreeEntry<'a>> {
+        let tree = self.tree;
+        self.node()
+            .children
+            .iter()
+            .map(move |&node| tree.entry(node))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DirectoryTree;
+
+    fn tree() -> DirectoryTree {
+        DirectoryTree::build(
+            [
+                "AndroidManifest.xml",
+                "classes.dex",
+                "classes2.dex",
+                "lib/arm64-v8a/libziprs.so",
+                "lib/x86/libziprs.so",
+                "assets/",
+                "assets/fonts/a.ttf",
+                "res/drawable/icon.png",
+            ]
+            .iter()
+            .copied(),
+        )
+    }
+
+    fn paths<'a>(entries: impl Iterator<Item = super::TreeEntry<'a>>) -> Vec<&'a str> {
+        entries.map(|entry| entry.path()).collect()
+    }
+
+    #[test]
+    fn implicit_directories() {
+        let tree = tree();
+        assert!(tree.is_dir("lib"));
+        assert!(tree.is_dir("lib/arm64-v8a/"));
+        assert!(tree.get("lib/").unwrap().is_implicit());
+        assert!(!tree.get("assets/").unwrap().is_implicit());
+        assert_eq!(tree.get("assets").unwrap().index(), Some(5));
+        assert!(!tree.is_dir("classes.dex"));
+        assert!(!tree.is_dir("missing"));
+        assert_eq!(tree.get("classes2.dex").unwrap().index(), Some(2));
+    }
+
+    #[test]
+    fn list() {
+        let tree = tree();
+        assert_eq!(
+            paths(tree.list("").unwrap()),
+            [
+                "AndroidManifest.xml",
+                "assets/",
+                "classes.dex",
+                "classes2.dex",
+                "lib/",
+                "res/"
+            ]
+        );
+        assert_eq!(
+            paths(tree.list("lib").unwrap()),
+            ["lib/arm64-v8a/", "lib/x86/"]
+        );
+        assert_eq!(
+            paths(tree.list("lib/arm64-v8a/").unwrap()),
+            ["lib/arm64-v8a/libziprs.so"]
+        );
+        assert!(tree.list("classes.dex").is_none());
+        assert!(tree.list("missing").is_none());
+        assert_eq!(tree.root().children().count(), 6);
+        assert_eq!(
+            tree.get("lib/x86/libziprs.so").unwrap().name(),
+            "libziprs.so"
+        );
+    }
+
+    #[test]
+    fn prefix_and_glob() {
+        let tree = tree();
+        assert_eq!(
+            paths(tree.with_prefix("lib/")),
+            [
+                "lib/",
+                "lib/arm64-v8a/",
+                "lib/arm64-v8a/libziprs.so",
+                "lib/x86/",
+                "lib/x86/libziprs.so"
+            ]
+        );
+        assert_eq!(paths(tree.glob("*.dex")), ["classes.dex", "classes2.dex"]);
+        assert_eq!(
+            paths(tree.glob("lib/*/*.so")),
+            ["lib/arm64-v8a/libziprs.so", "lib/x86/libziprs.so"]
+        );
+        assert_eq!(paths(tree.glob("lib/*")), ["lib/arm64-v8a/", "lib/x86/"]);
+        assert_eq!(paths(tree.glob("**/*.png")), ["res/drawable/icon.png"]);
+    }
+}
@@ -0,0 +1,437 @@
+//! Integrity checks of whole archives, like the "test" operation of archivers
+
+use super::{read_local_header_at, ZipArchive};
+use crate::result::{ZipError, ZipResult};
+use crate::types::{AesVendorVersion, ZipFileData};
+use std::fmt;
+use std::io::{self, prelude::*};
+
+/// Result of [`ZipArchive::verify`], with the problems found in every file
+#[derive(Debug)]
+pub struct VerifyReport {
+    files: Vec<FileReport>,
+}
+
+/// Problems found in a file of an archive by [`ZipArchive::verify`]
+#[derive(Debug)]
+pub struct FileReport {
+    index: usize,
+    name: String,
+    problems: Vec<Problem>,
+}
+
+/// A problem found in a file of an archive
+#[derive(Debug)]
+pub enum Problem {
+    /// The file is encrypted, so its data was not checked
+    PasswordRequired,
+    /// The file could not be opened or its data could not be decompressed
+    Unreadable(ZipError),
+    /// The CRC32 of the data differs from the one in the central directory
+    Crc32Mismatch {
+        /// CRC32 from the central directory
+        expected: u32,
+        /// CRC32 of the data
+        actual: u32,
+    },
+    /// The size of the data differs from the one in the central directory
+    SizeMismatch {
+        /// Size from the central directory
+        expected: u64,
+        /// Size of the data
+        actual: u64,
+    },
+    /// A field of the local header differs from the central directory
+    LocalHeaderMismatch(HeaderField),
+    /// The file does not end before the central directory
+    OutOfBounds,
+    /// The file overlaps with another one, given by its index
+    Overlap(usize),
+}
+
+/// A field that is stored in both the local header and the central directory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderField {
+    /// The name of the file
+    Name,
+    /// The compression method, or the AES extra field
+    CompressionMethod,
+    /// The encryption or data descriptor flags
+    Flags,
+    /// The CRC32 of the data
+    Crc32,
+    /// The size of the compressed data
+    CompressedSize,
+    /// The size of the uncompressed data
+    UncompressedSize,
+}
+
+impl VerifyReport {
+    /// Whether no problem was found in any file
+    pub fn is_ok(&self) -> bool {
+        self.files.iter().all(FileReport::is_ok)
+    }
+
+    /// The reports of all files, in archive order
+    pub fn files(&self) -> &[FileReport] {
+        &self.files
+    }
+
+    /// The reports of the files with problems, in archive order
+    pub fn failed(&self) -> impl Iterator<Item = &FileReport> {
+        self.files.iter().filter(|file| !file.is_ok())
+    }
+}
+
+impl FileReport {
+    /// Get the index of the file in the archive
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the name of the file
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The problems found in the file, if any
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    /// Whether no problem was found in the file
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::PasswordRequired => write!(fmt, "{}", ZipError::PASSWORD_REQUIRED),
+            Problem::Unreadable(err) => write!(fmt, "{}", err),
+            Problem::Crc32Mismatch { expected, actual } => {
+                write!(fmt, "CRC32 is {:08x} instead of {:08x}", actual, expected)
+            }
+            Problem::SizeMismatch { expected, actual } => {
+                write!(fmt, "size is {} instead of {}", actual, expected)
+            }
+            Problem::LocalHeaderMismatch(field) => {
+                write!(fmt, "{:?} of the local header differs", field)
+            }
+            Problem::OutOfBounds => write!(fmt, "data extends past the central directory"),
+            Problem::Overlap(index) => write!(fmt, "data overlaps with file {}", index),
+        }
+    }
+}
+
+impl<R: Read + io::Seek> ZipArchive<R> {
+    /// Check the integrity of every file in the archive.
+    ///
+    /// Every file is decompressed to check its CRC32 and size, and its local header is compared
+    /// with the central directory. Files that overlap each other or the central directory are
+    /// also reported. Encrypted files are only checked structurally.
+    ///
+    /// An error is only returned if the central directory itself can't be read. For archives
+    /// opened with [`ZipArchive::new_lazy`], the metadata of every file is decoded first.
+    pub fn verify(&mut self) -> ZipResult<VerifyReport> {
+        let mut files = self.file_reports()?;
+        for report in &mut files {
+            self.verify_file(report);
+        }
+        self.verify_layout(&mut files)?;
+        Ok(VerifyReport { files })
+    }
+
+    /// Check the integrity of every file in the archive like [`ZipArchive::verify`], decompressing
+    /// the files on up to `threads` threads, each with its own clone of the reader.
+    pub fn verify_parallel(&mut self, threads: usize) -> ZipResult<VerifyReport>
+    where
+        R: Clone + Send,
+    {
+        let mut files = self.file_reports()?;
+        let threads = threads.clamp(1, files.len().max(1));
+        let chunk_size = files.len().div_ceil(threads).max(1);
+
+        std::thread::scope(|scope| {
+            for chunk in files.chunks_mut(chunk_size) {
+                let mut archive = self.clone();
+                scope.spawn(move || {
+                    for report in chunk {
+                        archive.verify_file(report);
+                    }
+                });
+            }
+        });
+        self.verify_layout(&mut files)?;
+        Ok(VerifyReport { files })
+    }
+
+    fn file_reports(&mut self) -> ZipResult<Vec<FileReport>> {
+        self.decode_metadata()?;
+        (0..self.len())
+            .map(|index| {
+                Ok(FileReport {
+                    index,
+                    name: self.shared.file(index, &mut self.reader)?.file_name.clone(),
+                    problems: Vec::new(),
+                })
+            })
+            .collect()
+    }
+
+    /// Compare the local header of a file with the central directory and check its data
+    fn verify_file(&mut self, report: &mut FileReport) {
+        let data = match self.shared.file(report.index, &mut self.reader) {
+            Ok(data) => data,
+            Err(err) => return report.problems.push(Problem::Unreadable(err)),
+        };
+        match local_header_mismatches(data, &mut self.reader) {
+            Ok(fields) => report
+                .problems
+                .extend(fields.into_iter().map(Problem::LocalHeaderMismatch)),
+            Err(err) => return report.problems.push(Problem::Unreadable(err)),
+        }
+
+        let expected_size = data.uncompressed_size;
+        // The CRC32 of AE-2 encrypted files is always 0, the authentication code is used instead
+        let expected_crc32 = match data.aes_mode {
+            Some((_, AesVendorVersion::Ae2)) => None,
+            _ => Some(data.crc32),
+        };
+        let mut file = match self.by_index(report.index) {
+            Ok(file) => file,
+            Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => {
+                return report.problems.push(Problem::PasswordRequired)
+            }
+            Err(err) => return report.problems.push(Problem::Unreadable(err)),
+        };
+
+        let mut hasher = crc32fast::Hasher::new();
+        let mut size = 0;
+        let mut buf = [0; 16 * 1024];
+        let result = loop {
+            match file.read(&mut buf) {
+                Ok(0) => break Ok(()),
+                Ok(count) => {
+                    hasher.update(&buf[..count]);
+                    size += count as u64;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => break Err(err),
+            }
+        };
+        let actual = hasher.finalize();
+        let crc32_mismatch = expected_crc32
+            .filter(|&expected| expected != actual)
+            .map(|expected| Problem::Crc32Mismatch { expected, actual });
+
+        match result {
+            Ok(()) => {
+                if size != expected_size {
+                    report.problems.push(Problem::SizeMismatch {
+                        expected: expected_size,
+                        actual: size,
+                    });
+                }
+                report.problems.extend(crc32_mismatch);
+            }
+            // The CRC32 is only checked once all the data has been decompressed
+            Err(_) if size == expected_size && crc32_mismatch.is_some() => {
+                report.problems.extend(crc32_mismatch)
+            }
+            Err(err) => report.problems.push(Problem::Unreadable(err.into())),
+        }
+    }
+
+    /// Report files whose data overlaps with another file or the central directory
+    fn verify_layout(&mut self, files: &mut [FileReport]) -> ZipResult<()> {
+        let mut ranges = Vec::with_capacity(files.len());
+        let mut directory_start = self.reader.stream_len()?;
+        for report in files.iter() {
+            let data = self.shared.file(report.index, &mut self.reader)?;
+            directory_start = directory_start.min(data.central_header_start);
+            // The data start is exact if the local header could be read
+            let end = data.data_start.load().checked_add(data.compressed_size);
+            ranges.push((data.header_start, end.unwrap_or(u64::MAX), report.index));
+        }
+        ranges.sort_unstable();
+
+        // End of the data seen so far, and the file it belongs to
+        let mut last: Option<(u64, usize)> = None;
+        for (start, end, index) in ranges {
+            let problems = &mut files[index].problems;
+            if end > directory_start {
+                problems.push(Problem::OutOfBounds);
+            }
+            match last {
+                Some((last_end, other)) if start < last_end => {
+                    problems.push(Problem::Overlap(other));
+                    if end > last_end {
+                        last = Some((end, index));
+                    }
+                }
+                _ => last = Some((end, index)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read the local header of a file, and list the fields that differ from the central directory
+pub(super) fn local_header_mismatches(
+    data: &ZipFileData,
+    reader: &mut (impl Read + io::Seek),
+) -> ZipResult<Vec<HeaderField>> {
+    let local = read_local_header_at(reader, data.header_start)?;
+    let local_header_length = 30 + local.file_name_raw.len() + local.extra_field.len();
+    data.data_start
+        .store(data.header_start + local_header_length as u64);
+
+    let mut fields = Vec::new();
+    if local.file_name_raw != data.file_name_raw {
+        fields.push(HeaderField::Name);
+    }
+    if local.compression_method != data.compression_method || local.aes_mode != data.aes_mode {
+        fields.push(HeaderField::CompressionMethod);
+    }
+    if local.encrypted != data.encrypted
+        || local.using_data_descriptor != data.using_data_descriptor
+    {
+        fields.push(HeaderField::Flags);
+    }
+    // These are only known after the data when there is a data descriptor
+    if !local.using_data_descriptor {
+        if local.crc32 != data.crc32 {
+            fields.push(HeaderField::Crc32);
+        }
+        if local.compressed_size != data.compressed_size {
+            fields.push(HeaderField::CompressedSize);
+        }
+        if local.uncompressed_size != data.uncompressed_size {
+            fields.push(HeaderField::UncompressedSize);
+        }
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HeaderField, Problem};
+    use crate::read::test_util::{point_second_file_to_first, zip};
+    use crate::result::ZipError;
+    use crate::write::FileOptions;
+    use crate::{CompressionMethod, ZipArchive};
+    use std::io;
+
+    fn archive() -> Vec<u8> {
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        zip(&[
+            ("a.txt", b"first file", options),
+            ("b.txt", b"second file", options),
+            ("dir/", b"", options),
+        ])
+    }
+
+    fn problems(v: Vec<u8>) -> Vec<(usize, String)> {
+        let report = ZipArchive::new(io::Cursor::new(v))
+            .unwrap()
+            .verify()
+            .unwrap();
+        report
+            .failed()
+            .flat_map(|file| {
+                let index = file.index();
+                file.problems()
+                    .iter()
+                    .map(move |problem| (index, problem.to_string()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_archive() {
+        let mut archive = ZipArchive::new(io::Cursor::new(archive())).unwrap();
+        let report = archive.verify().unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.files().len(), 3);
+        assert_eq!(report.files()[1].name(), "b.txt");
+        assert!(archive.verify_parallel(2).unwrap().is_ok());
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn parallel() {
+        let contents = (0..20)
+            .map(|i| (format!("{}.txt", i), i.to_string().repeat(1000)))
+            .collect::<Vec<_>>();
+        let files = contents
+            .iter()
+            .map(|(name, content)| (name.as_str(), content.as_bytes(), FileOptions::default()))
+            .collect::<Vec<_>>();
+        let mut v = zip(&files);
+        let position = v.windows(4).rposition(|w| w == b"PK\x03\x04").unwrap();
+        v[position + 40] ^= 0xff;
+
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        let report = archive.verify_parallel(4).unwrap();
+        let failed = report.failed().map(|file| file.index()).collect::<Vec<_>>();
+        assert_eq!(failed, [19]);
+    }
+
+    #[test]
+    fn corrupt_data() {
+        let mut v = archive();
+        let position = v.windows(11).position(|w| w == b"second file").unwrap();
+        v[position] = b'S';
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        let report = archive.verify().unwrap();
+        assert_eq!(report.failed().count(), 1);
+        match report.files()[1].problems() {
+            [Problem::Crc32Mismatch { expected, actual }] => {
+                assert_eq!(*expected, crc32fast::hash(b"second file"));
+                assert_eq!(*actual, crc32fast::hash(b"Second file"));
+            }
+            problems => panic!("unexpected problems: {:?}", problems),
+        }
+    }
+
+    #[test]
+    fn local_header_mismatch() {
+        let mut v = archive();
+        // Rename the local header of the first file
+        v[30] = b'c';
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        let report = archive.verify().unwrap();
+        match report.files()[0].problems() {
+            [Problem::LocalHeaderMismatch(HeaderField::Name)] => {}
+            problems => panic!("unexpected problems: {:?}", problems),
+        }
+    }
+
+    #[test]
+    fn local_header_signature() {
+        let mut v = archive();
+        v[0] = b'X';
+        let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        let report = archive.verify().unwrap();
+        match report.files()[0].problems() {
+            [Problem::Unreadable(ZipError::InvalidSignature {
+                record: "local file header",
+                offset: 0,
+                found,
+            })] => assert_eq!(found.to_le_bytes(), *b"XK\x03\x04"),
+            problems => panic!("unexpected problems: {:?}", problems),
+        }
+    }
+
+    #[test]
+    fn overlapping_files() {
+        let mut v = archive();
+        point_second_file_to_first(&mut v);
+
+        let report = problems(v);
+        assert!(report.contains(&(1, "data overlaps with file 0".to_owned())));
+        assert!(report.iter().all(|(index, _)| *index == 1));
+    }
+}
@@ -1,5 +1,6 @@
 //! Error types that can be emitted from this library
 
+use crate::compression::CompressionMethod;
 use std::error::Error;
 use std::fmt;
 use std::io;
@@ -33,11 +34,55 @@ pub enum ZipError {
 
     /// The requested file could not be found in the archive
     FileNotFound,
+
+    /// The archive exceeds one of the [`Limits`](crate::read::Limits) set on it
+    LimitExceeded(&'static str),
+
+    /// A record of the archive doesn't start with its signature
+    InvalidSignature {
+        /// Name of the record, for example `"local file header"`
+        record: &'static str,
+        /// Position of the record in the reader
+        offset: u64,
+        /// The value found instead of the signature
+        found: u32,
+    },
+
+    /// The data of a file doesn't match its CRC32
+    Crc32Mismatch {
+        /// Name of the file
+        name: String,
+        /// CRC32 recorded in the archive
+        expected: u32,
+        /// CRC32 of the data
+        actual: u32,
+    },
+
+    /// A file is compressed with a method that is unknown or was not enabled at compile time
+    UnsupportedCompressionMethod {
+        /// Name of the file
+        name: String,
+        /// The compression method of the file
+        method: CompressionMethod,
+    },
+
+    /// The password given to decrypt a file is wrong
+    InvalidPassword,
 }
 
 impl From<io::Error> for ZipError {
+    /// Wrap an I/O error, or unwrap the `ZipError` it was converted from
     fn from(err: io::Error) -> ZipError {
-        ZipError::Io(err)
+        match err.downcast::<ZipError>() {
+            Ok(err) => err,
+            Err(err) => ZipError::Io(err),
+        }
+    }
+}
+
+impl From<InvalidPassword> for ZipError {
+    fn from(_: InvalidPassword) -> ZipError {
+        ZipError::InvalidPassword
     }
 }
 
@@ -48,6 +93,31 @@ impl fmt::Display for ZipError {
             ZipError::InvalidArchive(err) => write!(fmt, "invalid Zip archive: {}", err),
             ZipError::UnsupportedArchive(err) => write!(fmt, "unsupported Zip archive: {}", err),
             ZipError::FileNotFound => write!(fmt, "specified file not found in archive"),
+            ZipError::LimitExceeded(err) => write!(fmt, "limit exceeded: {}", err),
+            ZipError::InvalidSignature {
+                record,
+                offset,
+                found,
+            } => write!(
+                fmt,
+                "invalid Zip archive: invalid {} signature {:#010x} at offset {}",
+                record, found, offset
+            ),
+            ZipError::Crc32Mismatch {
+                name,
+                expected,
+                actual,
+            } => write!(
+                fmt,
+                "invalid checksum of {:?}: CRC32 is {:08x} instead of {:08x}",
+                name, actual, expected
+            ),
+            ZipError::UnsupportedCompressionMethod { name, method } => write!(
+                fmt,
+                "unsupported Zip archive: compression method {} of {:?} is not supported",
+                method, name
+            ),
+            ZipError::InvalidPassword => write!(fmt, "{}", InvalidPassword),
         }
     }
 }
@@ -74,10 +144,73 @@ impl ZipError {
     /// # ()
     /// ```
     pub const PASSWORD_REQUIRED: &'static str = "Password required to decrypt file";
+
+    /// Get the kind of I/O error this error is converted to
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            ZipError::Io(err) => err.kind(),
+            ZipError::InvalidArchive(_)
+            | ZipError::InvalidSignature { .. }
+            | ZipError::Crc32Mismatch { .. }
+            | ZipError::LimitExceeded(_) => io::ErrorKind::InvalidData,
+            ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)
+            | ZipError::InvalidPassword => io::ErrorKind::PermissionDenied,
+            ZipError::UnsupportedArchive(_) | ZipError::UnsupportedCompressionMethod { .. } => {
+                io::ErrorKind::Unsupported
+            }
+            ZipError::FileNotFound => io::ErrorKind::NotFound,
+        }
+    }
 }
 
 impl From<ZipError> for io::Error {
+    /// Unwrap an I/O error, or wrap the error with a matching [`io::ErrorKind`]
     fn from(err: ZipError) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, err)
+        match err {
+            ZipError::Io(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ZipError;
+    use std::io;
+
+    #[test]
+    fn io_error_kind() {
+        let err: io::Error = ZipError::FileNotFound.into();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        let err: io::Error = ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED).into();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        let err: io::Error = ZipError::UnsupportedArchive("Multi-disk archive").into();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        let err: io::Error = ZipError::Io(io::ErrorKind::UnexpectedEof.into()).into();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn round_trip() {
+        let err: io::Error = ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED).into();
+        match ZipError::from(err) {
+            ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let err: io::Error = ZipError::Crc32Mismatch {
+            name: "a.txt".to_owned(),
+            expected: 1,
+            actual: 2,
+        }
+        .into();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        match ZipError::from(err) {
+            ZipError::Crc32Mismatch { name, .. } => assert_eq!(name, "a.txt"),
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let err = io::Error::new(io::ErrorKind::Other, "other");
+        assert!(matches!(ZipError::from(err), ZipError::Io(_)));
     }
 }
@@ -5,6 +5,7 @@ use std::io::prelude::*;
 
 pub const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
 pub const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
+pub const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
 const CENTRAL_DIRECTORY_END_SIGNATURE: u32 = 0x06054b50;
 pub const ZIP64_CENTRAL_DIRECTORY_END_SIGNATURE: u32 = 0x06064b50;
 const ZIP64_CENTRAL_DIRECTORY_END_LOCATOR_SIGNATURE: u32 = 0x07064b50;
@@ -23,10 +24,15 @@ pub struct CentralDirectoryEnd {
 }
 
 impl CentralDirectoryEnd {
-    pub fn parse<T: Read>(reader: &mut T) -> ZipResult<CentralDirectoryEnd> {
+    pub fn parse<T: Read + io::Seek>(reader: &mut T) -> ZipResult<CentralDirectoryEnd> {
+        let offset = reader.stream_position()?;
         let magic = reader.read_u32::<LittleEndian>()?;
         if magic != CENTRAL_DIRECTORY_END_SIGNATURE {
-            return Err(ZipError::InvalidArchive("Invalid digital signature header"));
+            return Err(ZipError::InvalidSignature {
+                record: "end of central directory record",
+                offset,
+                found: magic,
+            });
         }
         let disk_number = reader.read_u16::<LittleEndian>()?;
         let disk_with_central_directory = reader.read_u16::<LittleEndian>()?;
@@ -103,12 +109,15 @@ pub struct Zip64CentralDirectoryEndLocator {
 }
 
 impl Zip64CentralDirectoryEndLocator {
-    pub fn parse<T: Read>(reader: &mut T) -> ZipResult<Zip64CentralDirectoryEndLocator> {
+    pub fn parse<T: Read + io::Seek>(reader: &mut T) -> ZipResult<Zip64CentralDirectoryEndLocator> {
+        let offset = reader.stream_position()?;
         let magic = reader.read_u32::<LittleEndian>()?;
         if magic != ZIP64_CENTRAL_DIRECTORY_END_LOCATOR_SIGNATURE {
-            return Err(ZipError::InvalidArchive(
-                "Invalid zip64 locator digital signature header",
-            ));
+            return Err(ZipError::InvalidSignature {
+                record: "zip64 end of central directory locator",
+                offset,
+                found: magic,
+            });
         }
         let disk_with_central_directory = reader.read_u32::<LittleEndian>()?;
         let end_of_central_directory_offset = reader.read_u64::<LittleEndian>()?;
@@ -385,26 +385,33 @@ impl ZipFileData {
 ///
 /// According to the [specification](https://www.winzip.com/win/en/aes_info.html#winzip11) AE-2
 /// does not make use of the CRC check.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AesVendorVersion {
+    /// AE-1, which also stores the CRC32 of the data
     Ae1,
+    /// AE-2, which leaves the CRC32 out
     Ae2,
 }
 
 /// AES variant used.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AesMode {
+    /// AES with a 128 bit key
     Aes128,
+    /// AES with a 192 bit key
     Aes192,
+    /// AES with a 256 bit key
     Aes256,
 }
 
 #[cfg(feature = "aes-crypto")]
 impl AesMode {
+    /// Length of the salt stored before the encrypted data, in bytes
     pub fn salt_length(&self) -> usize {
         self.key_length() / 2
     }
 
+    /// Length of the encryption key, in bytes
     pub fn key_length(&self) -> usize {
         match self {
             Self::Aes128 => 16,
@@ -1,7 +1,7 @@
 //! Types for creating ZIP archives
 
 use crate::compression::CompressionMethod;
-use crate::read::{central_header_to_zip_file, ZipArchive, ZipFile};
+use crate::read::{central_header_to_zip_file, ParseOptions, ZipArchive, ZipFile};
 use crate::result::{ZipError, ZipResult};
 use crate::spec;
 use crate::truncate::Truncate;
@@ -283,7 +283,13 @@ impl<A: Write + Seek + Read + Truncate> ZipWriter<A> {
         }
 
         let files = (0..number_of_files)
-            .map(|_| central_header_to_zip_file(&mut readwriter, archive_offset))
+            .map(|_| {
+                central_header_to_zip_file(
+                    &mut readwriter,
+                    archive_offset,
+                    &ParseOptions::default(),
+                )
+            })
             .collect::<Result<Vec<_>, _>>()?;
 
         let _ = readwriter.seek(io::SeekFrom::Start(directory_start)); // seek directory_start to overwrite it
@@ -479,7 +485,9 @@ impl<W: Write + Seek + Read + Truncate> ZipWriter<W> {
             self.write_u16::<LittleEndian>(pad.len() as u16)
                 .map_err(ZipError::from)?;
             self.write_all(&pad).map_err(ZipError::from)?;
-            assert_eq!(self.end_local_start_central_extra_data()? % align, 0);
+            if self.end_local_start_central_extra_data()? % align != 0 {
+                return Err(io::Error::other("File data could not be aligned").into());
+            }
         }
         let extra_data_end = self.end_extra_data()?;
         Ok(extra_data_end - data_start)
@@ -743,6 +751,15 @@ impl<W: Write + Seek + Read + Truncate> ZipWriter<W> {
         self.add_directory(path_to_string(path), options)
     }
 
+    /// Finish the file being written, so that its header is complete before the next file is
+    /// started.
+    ///
+    /// This is done by [`ZipWriter::start_file`] and [`ZipWriter::finish`] anyway, and is only
+    /// needed to end a file early. Writing fails afterwards, until another file is started.
+    pub fn end_file(&mut self) -> ZipResult<()> {
+        self.finish_file()
+    }
+
     /// Finish the last file and write all other zip-structures
     ///
     /// This will return the writer, but one should normally not append any data to the end of the file.
@@ -786,7 +803,7 @@ impl<W: Write + Seek + Read + Truncate> ZipWriter<W> {
         self.start_entry(name, options, None)?;
         self.writing_to_file = true;
         self.write_all(target.into().as_bytes())?;
-        self.writing_to_file = false;
+        self.finish_file()?;
 
         Ok(())
     }
@@ -1373,10 +1390,11 @@ fn path_to_string(path: &std::path::Path) -> String {
 #[cfg(test)]
 mod test {
     use std::io;
-    use std::io::Write;
+    use std::io::{Read, Write};
 
     use crate::compression::CompressionMethod;
     use crate::types::DateTime;
+    use crate::ZipArchive;
 
     use super::{FileOptions, ZipWriter};
 
@@ -1510,6 +1528,26 @@ mod test {
         assert_eq!(result.get_ref(), &v);
     }
 
+    #[test]
+    fn end_file() {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("a.txt", options).unwrap();
+        writer.write_all(b"a").unwrap();
+        writer.end_file().unwrap();
+        assert!(writer.write_all(b"b").is_err());
+        writer.end_file().unwrap();
+
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+        let mut content = String::new();
+        archive
+            .by_name("a.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "a");
+    }
+
     #[test]
     fn path_to_string() {
         let mut path = std::path::PathBuf::new();
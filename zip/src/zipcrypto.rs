@@ -133,11 +133,28 @@ impl<R: std::io::Read> std::io::Read for ZipCryptoReaderValid<R> {
         // Note: There might be potential for optimization. Inspiration can be found at:
         // https://github.com/kornelski/7z/blob/master/CPP/7zip/Crypto/ZipCrypto.cpp
 
-        let result = self.reader.file.read(buf);
+        let count = self.reader.file.read(buf)?;
+        self.decrypt(&mut buf[..count]);
+        Ok(count)
+    }
+}
+
+impl<R> ZipCryptoReaderValid<R> {
+    /// Decrypt data that follows the data decrypted so far, in place.
+    pub(crate) fn decrypt(&mut self, buf: &mut [u8]) {
         for byte in buf.iter_mut() {
             *byte = self.reader.keys.decrypt_byte(*byte);
         }
-        result
+    }
+
+    /// Continue decrypting from another reader, which holds the data following the data read so far.
+    pub(crate) fn with_reader<S>(self, file: S) -> ZipCryptoReaderValid<S> {
+        ZipCryptoReaderValid {
+            reader: ZipCryptoReader {
+                file,
+                keys: self.reader.keys,
+            },
+        }
     }
 }
 
@@ -148,6 +165,23 @@ impl<R: std::io::Read> ZipCryptoReaderValid<R> {
     }
 }
 
+/// Encrypt data with its encryption header, whose last byte is `check_byte`, since there is no
+/// writer for encrypted files
+#[cfg(test)]
+pub(crate) fn encrypt(password: &[u8], check_byte: u8, data: &[u8]) -> Vec<u8> {
+    let mut keys = ZipCryptoKeys::new();
+    for byte in password.iter() {
+        keys.update(*byte);
+    }
+    let mut header = [0u8; 12];
+    header[11] = check_byte;
+    header
+        .iter()
+        .chain(data)
+        .map(|byte| keys.encrypt_byte(*byte))
+        .collect()
+}
+
 static CRCTABLE: [u32; 256] = [
     0x00000000, 0x77073096, 0xee0e612c, 0x990951ba, 0x076dc419, 0x706af48f, 0xe963a535, 0x9e6495a3,
     0x0edb8832, 0x79dcb8a4, 0xe0d5e91e, 0x97d2d988, 0x09b64c2b, 0x7eb17cbd, 0xe7b82d07, 0x90bf1d91,